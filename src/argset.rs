@@ -19,6 +19,18 @@ pub(crate) fn branch_arg() -> Arg {
         .value_parser(clap::value_parser!(BranchLocator))
 }
 
+/// The `--json` option for machine-readable output.
+pub(crate) fn json_arg() -> Arg {
+    Arg::new("json")
+        .long("json")
+        .help("Output as JSON")
+        .long_help(
+            "Output as JSON. The JSON objects output by StGit commands are described \
+             in the JSON OUTPUT section of stg(1).",
+        )
+        .action(clap::ArgAction::SetTrue)
+}
+
 /// The `--keep/-k` option.
 pub(crate) fn keep_arg() -> Arg {
     Arg::new("keep")
@@ -37,6 +49,29 @@ pub(crate) fn merged_arg() -> Arg {
         .action(clap::ArgAction::SetTrue)
 }
 
+/// The `--with-deps` option for moving patches along with their dependencies.
+///
+/// Commands should supply a `long_help()` describing which related patches are moved.
+pub(crate) fn with_deps_arg() -> Arg {
+    Arg::new("with-deps")
+        .long("with-deps")
+        .help("Also move patches related by dependencies")
+        .action(clap::ArgAction::SetTrue)
+}
+
+/// The `--ignore-deps` option for disregarding patch dependencies.
+pub(crate) fn ignore_deps_arg() -> Arg {
+    Arg::new("ignore-deps")
+        .long("ignore-deps")
+        .help("Ignore patch dependencies")
+        .long_help(
+            "Do not check whether the operation moves patches past patches they \
+             depend on. See `stg deps` for how dependencies are determined.",
+        )
+        .action(clap::ArgAction::SetTrue)
+        .conflicts_with("with-deps")
+}
+
 /// The --conflicts option determining how push-time conflicts are handled.
 pub(crate) fn push_conflicts_arg() -> clap::Arg {
     clap::Arg::new("conflicts")
@@ -0,0 +1,453 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg absorb` implementation.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use bstr::BString;
+use clap::{Arg, ArgMatches};
+use indexmap::IndexMap;
+
+use crate::{
+    argset,
+    color::get_color_stdout,
+    ext::{CommitExtended, RepositoryExtended},
+    patch::PatchName,
+    stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess, StackTransaction},
+    stupid::{parse_diff, FileDiff, Hunk, StatusOptions, Stupid, StupidContext},
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "absorb",
+    category: super::CommandCategory::PatchManipulation,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Absorb worktree changes into the patches they modify")
+        .long_about(
+            "Distribute the changes in the work tree and index amongst the applied \
+             patches.\n\
+             \n\
+             The local changes are split into hunks and each hunk is absorbed into the \
+             applied patch that last modified the lines changed by the hunk or the \
+             lines immediately surrounding it. If those lines were modified by more \
+             than one patch, the topmost of those patches is chosen.\n\
+             \n\
+             Hunks that cannot be attributed to any applied patch, for example \
+             changes to lines that predate the stack or changes to new or binary \
+             files, are absorbed into the topmost patch. Likewise, if a patch's hunks \
+             do not apply cleanly to the patch, or the patches above it could not be \
+             cleanly pushed after absorbing them, the hunks are absorbed into the \
+             topmost patch instead.\n\
+             \n\
+             All patches are updated in a single stack transaction which is recorded \
+             as a single entry in the stack log. Use '--dry-run' to see which patch \
+             each hunk would be absorbed into without changing anything.\n\
+             \n\
+             Like 'stg refresh', absorb will refuse to proceed if both the index and \
+             the work tree have changes unless '--index' or '--force' is used.",
+        )
+        .arg(
+            Arg::new("pathspecs")
+                .help("Only absorb changes to files matching path")
+                .value_name("path")
+                .num_args(1..)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("index")
+                .long("index")
+                .short('i')
+                .help("Absorb changes from index instead of worktree")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["pathspecs", "force"]),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .short('F')
+                .help("Force absorb even if index is dirty")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .short('n')
+                .help("Show the patch each hunk would be absorbed into")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(argset::push_conflicts_arg())
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::current(&repo, InitializationPolicy::AllowUninitialized)?;
+    let stupid = repo.stupid();
+    let config = repo.config_snapshot();
+    let allow_push_conflicts = argset::resolve_allow_push_conflicts(&config, matches);
+    let dry_run = matches.get_flag("dry-run");
+
+    repo.check_repository_state()?;
+    stack.check_head_top_mismatch()?;
+
+    let top_patchname = stack
+        .applied()
+        .last()
+        .ok_or(super::Error::NoAppliedPatches)?
+        .clone();
+
+    let head_tree_id = stack.get_branch_head().tree_id()?.detach();
+
+    let (tree_id, absorb_paths) = if matches.get_flag("index") {
+        (stupid.write_tree()?, None)
+    } else {
+        let mut status_opts = StatusOptions::default();
+        if let Some(pathspecs) = matches.get_many::<PathBuf>("pathspecs") {
+            status_opts.pathspecs(pathspecs);
+        }
+        let statuses = stupid.statuses(Some(&status_opts))?;
+        let absorb_paths = super::refresh::determine_refresh_paths(
+            &stupid,
+            &statuses,
+            None,
+            matches.get_flag("force"),
+        )?;
+        let tree_id = stupid.with_temp_index(|stupid_temp| {
+            stupid_temp.read_tree(head_tree_id)?;
+            if !absorb_paths.is_empty() {
+                stupid_temp.update_index(Some(&absorb_paths))?;
+            }
+            stupid_temp.write_tree()
+        })?;
+        (tree_id, Some(absorb_paths))
+    };
+
+    if tree_id == head_tree_id {
+        return Err(anyhow!("no local changes to absorb"));
+    }
+
+    let diff = stupid.diff_tree_patch(
+        head_tree_id,
+        tree_id,
+        None::<Vec<&str>>,
+        false,
+        ["-U0", "--binary", "--no-ext-diff", "--no-renames"],
+    )?;
+    let file_diffs = parse_diff(diff.as_ref())?;
+
+    let patch_commits: HashMap<gix::ObjectId, &PatchName> = stack
+        .applied()
+        .iter()
+        .map(|pn| (stack.get_patch_commit_id(pn), pn))
+        .collect();
+
+    // Hunks attributed to patches other than the topmost patch, grouped by file, along
+    // with each hunk's start line in the patch's version of the file.
+    let mut attributed: IndexMap<PatchName, FileHunks> = IndexMap::new();
+    let mut report: Vec<(&FileDiff, Option<&Hunk>, Option<&PatchName>)> = Vec::new();
+
+    for (file_index, file_diff) in file_diffs.iter().enumerate() {
+        if file_diff.hunks.is_empty() {
+            report.push((file_diff, None, None));
+            continue;
+        }
+
+        let blamed = if let Some(old_path) = file_diff.old_path.as_ref() {
+            stupid.blame(stack.base().id, stack.get_branch_head().id, old_path)?
+        } else {
+            Vec::new()
+        };
+
+        for hunk in &file_diff.hunks {
+            let attribution = attribute_hunk(hunk, &blamed, &patch_commits, stack.applied());
+            report.push((file_diff, Some(hunk), attribution.map(|(pn, _)| pn)));
+            if let Some((patchname, start)) = attribution {
+                if patchname != &top_patchname {
+                    attributed
+                        .entry(patchname.clone())
+                        .or_default()
+                        .entry(file_index)
+                        .or_default()
+                        .push((start, hunk));
+                }
+            }
+        }
+    }
+
+    // Sort the patches from bottom to top of the stack.
+    attributed.sort_by_cached_key(|pn, _| stack.applied().iter().position(|a| a == pn));
+
+    // Determine the new tree of each patch by applying its hunks to the patch's
+    // original tree. Working up from the bottommost patch, the hunks are only
+    // absorbed if the patches above can still be pushed cleanly.
+    let mut absorbed: IndexMap<PatchName, gix::ObjectId> = IndexMap::new();
+    let mut unabsorbed: Vec<PatchName> = Vec::new();
+    let bottom_pos = attributed
+        .first()
+        .and_then(|(pn, _)| stack.applied().iter().position(|a| a == pn))
+        .unwrap_or_default();
+    let to_rebase = stack.applied()[bottom_pos..]
+        .iter()
+        .map(|pn| {
+            let commit = stack.get_patch_commit(pn);
+            let parent_tree_id = commit.get_parent_commit()?.tree_id()?.detach();
+            Ok((pn, parent_tree_id, commit.tree_id()?.detach()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for (patchname, file_hunks) in attributed {
+        let patch_diff = make_patch_diff(&file_diffs, file_hunks);
+        let patch_tree_id = stack.get_patch_commit(&patchname).tree_id()?.detach();
+        let new_tree_id = stupid.with_temp_index(|stupid_temp| {
+            stupid_temp.read_tree(patch_tree_id)?;
+            if stupid_temp.apply_unidiff_zero_to_index(patch_diff.as_ref())? {
+                Ok(Some(stupid_temp.write_tree()?))
+            } else {
+                Ok(None)
+            }
+        })?;
+        if let Some(new_tree_id) = new_tree_id {
+            absorbed.insert(patchname.clone(), new_tree_id);
+            if !can_rebase(&stupid, &to_rebase, &absorbed)? {
+                absorbed.pop();
+                unabsorbed.push(patchname);
+            }
+        } else {
+            unabsorbed.push(patchname);
+        }
+    }
+
+    if dry_run {
+        for (file_diff, hunk, patchname) in report {
+            let description = hunk.map_or_else(|| file_diff.description(), |hunk| hunk.header);
+            match patchname {
+                Some(patchname) if unabsorbed.contains(patchname) => println!(
+                    "{}: {description} -> {top_patchname} (cannot absorb into {patchname})",
+                    file_diff.display_path(),
+                ),
+                Some(patchname) => {
+                    println!("{}: {description} -> {patchname}", file_diff.display_path(),)
+                }
+                None => println!(
+                    "{}: {description} -> {top_patchname} (unattributed)",
+                    file_diff.display_path(),
+                ),
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(absorb_paths) = absorb_paths.as_ref() {
+        if !absorb_paths.is_empty() {
+            stupid.update_index(Some(absorb_paths))?;
+        }
+    }
+
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .allow_push_conflicts(allow_push_conflicts)
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| {
+            if let Some((bottom_patchname, bottom_tree_id)) = absorbed.first() {
+                let pos = trans
+                    .applied()
+                    .iter()
+                    .position(|pn| pn == bottom_patchname)
+                    .expect("absorbing into applied patch");
+                let to_push = trans.applied()[pos + 1..].to_vec();
+                let popped_extra = trans.pop_patches(|pn| to_push.contains(pn))?;
+                assert!(popped_extra.is_empty());
+
+                update_patch_tree(trans, bottom_patchname, *bottom_tree_id)?;
+                for patchname in &to_push {
+                    if let Some(new_tree_id) = absorbed.get(patchname) {
+                        update_patch_tree(trans, patchname, *new_tree_id)?;
+                    }
+                    trans.push_patches(&[patchname], false)?;
+                }
+            }
+
+            // The topmost patch takes whatever was not absorbed into the other patches.
+            let top_patchname = trans.applied().last().expect("applied patches").clone();
+            let top_tree_id = trans.get_patch_commit(&top_patchname).tree_id()?.detach();
+            if top_tree_id != tree_id {
+                update_patch_tree(trans, &top_patchname, tree_id)?;
+            }
+            Ok(())
+        })
+        .execute("absorb")?;
+
+    for patchname in unabsorbed {
+        println!(
+            "The changes for {patchname} did not apply cleanly. \
+             They were absorbed into {top_patchname}.",
+        );
+    }
+
+    Ok(())
+}
+
+/// Determine whether the patches can be cleanly pushed with the absorbed trees.
+///
+/// Each entry of `patches` is a patch name along with the patch's original parent tree
+/// and tree. The first patch's parent is assumed to be unchanged.
+fn can_rebase(
+    stupid: &StupidContext,
+    patches: &[(&PatchName, gix::ObjectId, gix::ObjectId)],
+    absorbed: &IndexMap<PatchName, gix::ObjectId>,
+) -> Result<bool> {
+    stupid.with_temp_index(|stupid_temp| {
+        let mut new_parent_tree_id = None;
+        for (patchname, parent_tree_id, tree_id) in patches {
+            let tree_id = absorbed.get(*patchname).copied().unwrap_or(*tree_id);
+            let new_tree_id = match new_parent_tree_id {
+                Some(new_parent_tree_id) if new_parent_tree_id != *parent_tree_id => {
+                    stupid_temp.read_tree(new_parent_tree_id)?;
+                    if !stupid_temp.apply_treediff_to_index(*parent_tree_id, tree_id, true)? {
+                        return Ok(false);
+                    }
+                    stupid_temp.write_tree()?
+                }
+                _ => tree_id,
+            };
+            new_parent_tree_id = Some(new_tree_id);
+        }
+        Ok(true)
+    })
+}
+
+/// Determine the patch that a hunk should be absorbed into.
+///
+/// The patch is the topmost applied patch that last modified any of the lines
+/// replaced by the hunk or the lines immediately surrounding the hunk. Also returned
+/// is the hunk's start line translated to the patch's version of the file.
+fn attribute_hunk<'a>(
+    hunk: &Hunk,
+    blamed: &[(gix::ObjectId, usize)],
+    patch_commits: &HashMap<gix::ObjectId, &'a PatchName>,
+    applied: &[PatchName],
+) -> Option<(&'a PatchName, usize)> {
+    let lines = if hunk.old_count > 0 {
+        hunk.old_start.saturating_sub(1).max(1)..=hunk.old_start + hunk.old_count
+    } else {
+        hunk.old_start.max(1)..=hunk.old_start + 1
+    };
+    let candidates: Vec<(usize, &PatchName, usize)> = lines
+        .filter_map(|line| {
+            let (commit_id, orig_line) = blamed.get(line - 1)?;
+            patch_commits
+                .get(commit_id)
+                .map(|patchname| (line, *patchname, *orig_line))
+        })
+        .collect();
+
+    let position = |patchname: &PatchName| applied.iter().position(|pn| pn == patchname);
+    let patchname = candidates
+        .iter()
+        .map(|(_, patchname, _)| *patchname)
+        .max_by_key(|patchname| position(patchname))?;
+    let (line, _, orig_line) = candidates
+        .iter()
+        .find(|(_, pn, _)| pn == &patchname)
+        .expect("patch is a candidate");
+
+    let start = if hunk.old_count == 0 && *line == hunk.old_start {
+        *orig_line
+    } else {
+        (hunk.old_start + orig_line).checked_sub(*line)?
+    };
+    Some((patchname, start))
+}
+
+/// Assemble a zero-context diff from a subset of hunks relocated to new start lines.
+fn make_patch_diff(file_diffs: &[FileDiff], file_hunks: FileHunks) -> BString {
+    let mut patch_diff = BString::default();
+    for (file_index, mut hunks) in file_hunks {
+        patch_diff.extend_from_slice(file_diffs[file_index].header);
+        hunks.sort_by_key(|(start, _)| *start);
+        let mut offset: isize = 0;
+        for (start, hunk) in hunks {
+            let new_start = if hunk.old_count == 0 {
+                start + 1
+            } else if hunk.new_count == 0 {
+                start.saturating_sub(1)
+            } else {
+                start
+            };
+            let new_start = (new_start as isize + offset).max(0);
+            patch_diff.extend_from_slice(
+                format!(
+                    "@@ -{start},{} +{new_start},{} @@\n",
+                    hunk.old_count, hunk.new_count
+                )
+                .as_bytes(),
+            );
+            patch_diff.extend_from_slice(hunk.body);
+            offset += hunk.new_count as isize - hunk.old_count as isize;
+        }
+    }
+    patch_diff
+}
+
+/// Update a patch with a new commit having the given tree.
+fn update_patch_tree(
+    trans: &mut StackTransaction,
+    patchname: &PatchName,
+    tree_id: gix::ObjectId,
+) -> Result<()> {
+    let repo = trans.repo();
+    let patch_commit = trans.get_patch_commit(patchname).clone();
+    let commit_id = repo.commit_ex(
+        &patch_commit.author_strict()?,
+        repo.get_committer()?,
+        &patch_commit.message_ex(),
+        tree_id,
+        patch_commit.decode()?.parents(),
+    )?;
+    trans.update_patch(patchname, commit_id)
+}
+
+/// Hunks with their relocated start lines, keyed by the index of the hunks' file.
+type FileHunks<'a> = IndexMap<usize, Vec<(usize, &'a Hunk<'a>)>>;
+
+#[cfg(test)]
+mod tests {
+    use bstr::BStr;
+
+    use super::*;
+
+    #[test]
+    fn relocate_hunks() {
+        let diff = BStr::new(
+            "diff --git a/a.txt b/a.txt\n\
+             --- a/a.txt\n\
+             +++ b/a.txt\n\
+             @@ -2 +2,2 @@\n\
+             -two\n\
+             +TWO\n\
+             +2.5\n\
+             @@ -6,0 +8 @@\n\
+             +six\n",
+        );
+        let file_diffs = parse_diff(diff).unwrap();
+        let a = &file_diffs[0];
+        let file_hunks = IndexMap::from([(0, vec![(5, &a.hunks[1]), (1, &a.hunks[0])])]);
+        assert_eq!(
+            make_patch_diff(&file_diffs, file_hunks),
+            "diff --git a/a.txt b/a.txt\n\
+             --- a/a.txt\n\
+             +++ b/a.txt\n\
+             @@ -1,1 +1,2 @@\n\
+             -two\n\
+             +TWO\n\
+             +2.5\n\
+             @@ -5,0 +7,1 @@\n\
+             +six\n",
+        );
+    }
+}
@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg bisect` implementation.
+
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, ValueHint};
+
+use crate::{
+    argset,
+    color::get_color_stdout,
+    ext::RepositoryExtended,
+    patch::PatchName,
+    print_info_message,
+    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stupid::Stupid,
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "bisect",
+    category: super::CommandCategory::StackInspection,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Find the first applied patch for which a test command fails")
+        .long_about(
+            "Binary search the applied patches for the first patch for which a test \
+             command fails.\n\
+             \n\
+             The test command is run with 'sh -c' in the root of the work tree. An exit \
+             status of zero means the test passes; any other status means it fails. \
+             The test command must fail with all patches applied and is assumed to \
+             pass with no patches applied.\n\
+             \n\
+             At each step of the search, patches are pushed or popped, as with 'stg \
+             goto', so that HEAD always remains on the stack's branch. Unapplied and \
+             hidden patches are not tested and are left as they are. Once the first \
+             failing patch is found, the originally applied patches are pushed again \
+             and the name of the first failing patch is printed.\n\
+             \n\
+             The work tree and index must be clean when starting the search. The test \
+             command must not leave any changes to tracked files behind.",
+        )
+        .arg(
+            Arg::new("test")
+                .long("test")
+                .short('t')
+                .help("Shell command to test each step with")
+                .value_name("command")
+                .num_args(1)
+                .required(true)
+                .value_hint(ValueHint::CommandString),
+        )
+        .arg(argset::push_conflicts_arg())
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::current(&repo, InitializationPolicy::AllowUninitialized)?;
+    let stupid = repo.stupid();
+
+    repo.check_repository_state()?;
+    let statuses = stupid.statuses(None)?;
+    statuses.check_conflicts()?;
+    stack.check_head_top_mismatch()?;
+    statuses.check_index_and_worktree_clean()?;
+
+    let applied = stack.applied().to_vec();
+    if applied.is_empty() {
+        return Err(super::Error::NoAppliedPatches.into());
+    }
+
+    let test_command = matches
+        .get_one::<String>("test")
+        .expect("required argument");
+
+    let run_test = |num_applied: usize| -> Result<bool> {
+        if num_applied == 0 {
+            print_info_message(matches, "Testing with no patches applied");
+        } else {
+            let patchname = &applied[num_applied - 1];
+            print_info_message(matches, &format!("Testing `{patchname}`"));
+        }
+        Ok(stupid.user_exec_status(test_command)?.success())
+    };
+
+    if run_test(applied.len())? {
+        return Err(anyhow!(
+            "test command passes with all patches applied; nothing to bisect"
+        ));
+    }
+
+    let result = search(&repo, matches, &applied, run_test);
+    let restored = super::goto::apply_prefix(
+        &repo,
+        matches,
+        &applied,
+        applied.len(),
+        get_color_stdout(matches),
+        "bisect",
+    );
+    let first_failing = result?;
+    restored?;
+
+    println!("First failing patch: {}", applied[first_failing - 1]);
+
+    Ok(())
+}
+
+/// Search for the number of applied patches at which the test first fails.
+///
+/// The test is known to fail with all `applied` patches applied. The returned count is
+/// always at least one.
+fn search(
+    repo: &gix::Repository,
+    matches: &ArgMatches,
+    applied: &[PatchName],
+    mut run_test: impl FnMut(usize) -> Result<bool>,
+) -> Result<usize> {
+    let mut good = 0;
+    let mut bad = applied.len();
+
+    while bad - good > 1 {
+        let mid = good + (bad - good) / 2;
+        super::goto::apply_prefix(
+            repo,
+            matches,
+            applied,
+            mid,
+            get_color_stdout(matches),
+            "bisect",
+        )?;
+        if run_test(mid)? {
+            good = mid;
+        } else {
+            bad = mid;
+        }
+    }
+
+    if good == 0 {
+        super::goto::apply_prefix(
+            repo,
+            matches,
+            applied,
+            0,
+            get_color_stdout(matches),
+            "bisect",
+        )?;
+        if !run_test(0)? {
+            return Err(anyhow!("test command also fails with no patches applied"));
+        }
+    }
+
+    Ok(bad)
+}
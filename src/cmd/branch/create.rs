@@ -2,7 +2,7 @@
 
 //! `stg branch --create` implementation.
 
-use std::rc::Rc;
+use std::{path::PathBuf, rc::Rc};
 
 use anyhow::{anyhow, Context, Result};
 use bstr::ByteSlice;
@@ -21,7 +21,7 @@ pub(super) fn command() -> clap::Command {
         .short_flag('c')
         .override_usage(super::super::make_usage(
             "stg branch --create",
-            &["<new-branch> [committish]"],
+            &["[--worktree <path>] <new-branch> [committish]"],
         ))
         .about("Create and switch to a new branch")
         .long_about(
@@ -32,7 +32,11 @@ pub(super) fn command() -> clap::Command {
              StGit attempts to detect the branch from which the new branch forked, as \
              well as the remote repository of that parent branch such that 'stg pull' \
              will pull from the correct remote branch. A warning will be printed if \
-             the parent branch cannot be determined.",
+             the parent branch cannot be determined.\n\
+             \n\
+             With '--worktree', the new branch is checked-out in a new linked \
+             worktree at <path> instead of in the current worktree. The current \
+             worktree, its index, and its branch are left unchanged.",
         )
         .arg(
             clap::Arg::new("new-branch")
@@ -45,6 +49,15 @@ pub(super) fn command() -> clap::Command {
                 .help("Base commit for new branch")
                 .value_parser(clap::value_parser!(SingleRevisionSpec)),
         )
+        .arg(
+            clap::Arg::new("worktree")
+                .long("worktree")
+                .help("Check out the new branch in a new worktree at <path>")
+                .value_name("path")
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf))
+                .value_hint(clap::ValueHint::DirPath),
+        )
 }
 
 pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Result<()> {
@@ -64,10 +77,24 @@ pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Re
         return Err(anyhow!("branch `{new_branchname}` already exists"));
     }
 
-    repo.check_repository_state()?;
+    let opt_worktree = matches.get_one::<PathBuf>("worktree");
+    if let Some(worktree_path) = opt_worktree {
+        if worktree_path.exists() {
+            return Err(anyhow!("`{}` already exists", worktree_path.display()));
+        }
+    }
+
     let stupid = repo.stupid();
-    let statuses = stupid.statuses(None)?;
-    statuses.check_conflicts()?;
+
+    // The current worktree is only affected when checking-out the new branch in it.
+    let statuses = if opt_worktree.is_none() {
+        repo.check_repository_state()?;
+        let statuses = stupid.statuses(None)?;
+        statuses.check_conflicts()?;
+        Some(statuses)
+    } else {
+        None
+    };
 
     let maybe_committish = matches.get_one::<SingleRevisionSpec>("committish");
     let maybe_committish_str = matches
@@ -75,7 +102,9 @@ pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Re
         .map(|raw_values| raw_values.into_iter().next().unwrap().to_str().unwrap());
 
     let parent_branch = if let Some(committish_str) = maybe_committish_str {
-        statuses.check_worktree_clean()?;
+        if let Some(statuses) = statuses.as_ref() {
+            statuses.check_worktree_clean()?;
+        }
         if let Some(parent_reference) =
             repo.find_reference(committish_str)
                 .ok()
@@ -159,7 +188,14 @@ pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Re
         }
     }
 
-    match stupid.checkout(new_branch.get_branch_name().unwrap()) {
+    let new_branch_name = new_branch.get_branch_name().unwrap();
+    let checkout_result = if let Some(worktree_path) = opt_worktree {
+        stupid.worktree_add(worktree_path, new_branch_name)
+    } else {
+        stupid.checkout(new_branch_name)
+    };
+
+    match checkout_result {
         Ok(()) => Ok(()),
         Err(e) => {
             new_branch.delete()?;
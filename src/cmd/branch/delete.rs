@@ -78,6 +78,13 @@ pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Re
                 "cannot delete the current branch without a known parent branch"
             ));
         }
+    } else if let Some(worktree_path) =
+        repo.get_other_worktree_for_branch(target_branch.get_reference_name())?
+    {
+        return Err(anyhow!(
+            "cannot delete branch `{target_branchname}` checked out in worktree `{}`",
+            worktree_path.display()
+        ));
     } else {
         None
     };
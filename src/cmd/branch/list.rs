@@ -25,6 +25,10 @@ pub(super) fn command() -> clap::Command {
              StGit stacks are prefixed with 's'. Protected branches are prefixed with \
              'p'.",
         )
+        .arg(crate::argset::json_arg().long_help(
+            "Output the branches as a JSON array of branch objects. Branch objects \
+             are described in the JSON OUTPUT section of stg(1).",
+        ))
 }
 
 pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Result<()> {
@@ -48,6 +52,36 @@ pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Re
 
     let config = repo.config_snapshot();
 
+    if matches.get_flag("json") {
+        #[derive(serde::Serialize)]
+        struct BranchInfo {
+            name: String,
+            current: bool,
+            stack: bool,
+            protected: bool,
+            description: String,
+        }
+
+        let mut branches = Vec::with_capacity(branchnames.len());
+        for branchname in &branchnames {
+            let stack =
+                Stack::from_branch_name(repo, branchname, InitializationPolicy::RequireInitialized)
+                    .ok();
+            branches.push(BranchInfo {
+                name: branchname.to_string(),
+                current: Some(branchname) == current_branchname.as_ref(),
+                stack: stack.is_some(),
+                protected: stack.is_some_and(|stack| stack.is_protected(&config)),
+                description: config
+                    .string_by("branch", Some(branchname.into()), "description")
+                    .unwrap_or_default()
+                    .to_str_lossy()
+                    .to_string(),
+            });
+        }
+        return super::super::json::print(&branches);
+    }
+
     let mut stdout = crate::color::get_color_stdout(matches);
     let mut color_spec = termcolor::ColorSpec::new();
 
@@ -9,7 +9,10 @@ use bstr::{BString, ByteSlice};
 
 use crate::{
     ext::RepositoryExtended,
-    stack::{state_refname_from_branch_name, InitializationPolicy, Stack, StackAccess},
+    stack::{
+        create_series_version_ref, published_refname_prefix, state_refname_from_branch_name,
+        InitializationPolicy, Stack, StackAccess,
+    },
     stupid::Stupid,
     wrap::PartialRefName,
 };
@@ -77,6 +80,42 @@ pub(super) fn dispatch(repo: &gix::Repository, matches: &clap::ArgMatches) -> Re
             deref: false,
         })?;
 
+        for version in stack.series_versions()? {
+            create_series_version_ref(
+                repo,
+                new_branchname.as_ref(),
+                &version.name,
+                version.state_commit.id,
+                format!("rename {old_branchname} to {new_branchname}"),
+            )?;
+        }
+
+        let old_published_prefix = published_refname_prefix(old_branchname.as_ref());
+        let new_published_prefix = published_refname_prefix(new_branchname.as_ref());
+        let published: Vec<(String, gix::ObjectId)> = repo
+            .references()?
+            .prefixed(old_published_prefix.as_str())?
+            .filter_map(Result::ok)
+            .filter_map(|mut reference| {
+                let public_name = reference
+                    .name()
+                    .as_bstr()
+                    .strip_prefix(old_published_prefix.as_bytes())?
+                    .to_str_lossy()
+                    .to_string();
+                let id = reference.peel_to_id_in_place().ok()?.detach();
+                Some((public_name, id))
+            })
+            .collect();
+        for (public_name, id) in published {
+            repo.reference(
+                format!("{new_published_prefix}{public_name}"),
+                id,
+                gix::refs::transaction::PreviousValue::MustNotExist,
+                format!("rename {old_branchname} to {new_branchname}"),
+            )?;
+        }
+
         let mut local_config_file = repo.local_config_file().context("opening local config")?;
         let old_section_name = format!("{old_branchname}.stgit");
         let old_section_name = old_section_name.as_bytes().as_bstr();
@@ -302,7 +302,9 @@ fn insert_compreply(script: &mut ShStream, arg: &clap::Arg) {
             clap::ValueHint::EmailAddress => {
                 script.line(":");
             }
-            clap::ValueHint::CommandName => {
+            clap::ValueHint::CommandName
+            | clap::ValueHint::CommandString
+            | clap::ValueHint::CommandWithArguments => {
                 script.line("mapfile -t COMPREPLY < <(compgen -A command -- \"$cur\")");
             }
             clap::ValueHint::Username => {
@@ -312,8 +314,6 @@ fn insert_compreply(script: &mut ShStream, arg: &clap::Arg) {
                 script.line("mapfile -t COMPREPLY < <(compgen -A hostname -- \"$cur\")");
             }
             clap::ValueHint::ExecutablePath => todo!(),
-            clap::ValueHint::CommandString => todo!(),
-            clap::ValueHint::CommandWithArguments => todo!(),
             clap::ValueHint::Url => todo!(),
             _ => todo!(),
         };
@@ -11,7 +11,7 @@ use crate::{
     color::get_color_stdout,
     ext::RepositoryExtended,
     patch::{patchrange, PatchName, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stack::{DependencyGraph, InitializationPolicy, Stack, StackStateAccess},
     stupid::Stupid,
 };
 
@@ -101,6 +101,11 @@ fn make() -> clap::Command {
                 )
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(argset::with_deps_arg().long_help(
+            "Also delete the applied patches that depend on the deleted patches. \
+             See `stg deps`.",
+        ))
+        .arg(argset::ignore_deps_arg())
         .arg(argset::branch_arg())
         .arg(argset::push_conflicts_arg())
 }
@@ -138,6 +143,36 @@ fn run(matches: &ArgMatches) -> Result<()> {
         patches
     };
 
+    // Only applied patches above the lowest deleted patch may be affected.
+    let window: Vec<PatchName> = stack
+        .applied()
+        .iter()
+        .skip_while(|pn| !patches.contains(pn))
+        .cloned()
+        .collect();
+    let graph = if matches.get_flag("ignore-deps") || window.iter().all(|pn| patches.contains(pn)) {
+        None
+    } else {
+        Some(DependencyGraph::new(&stack, window)?)
+    };
+
+    let patches = match &graph {
+        Some(graph) if matches.get_flag("with-deps") => {
+            graph.expand(&patches, DependencyGraph::dependents)
+        }
+        _ => patches,
+    };
+
+    if let Some(graph) = &graph {
+        let remaining: Vec<PatchName> = stack
+            .applied()
+            .iter()
+            .filter(|pn| !patches.contains(pn))
+            .cloned()
+            .collect();
+        graph.check(&remaining)?;
+    }
+
     if spill_flag
         && stack
             .applied()
@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg deps` implementation.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches};
+
+use crate::{
+    argset,
+    branchloc::BranchLocator,
+    ext::RepositoryExtended,
+    patch::{LocationConstraint, PatchLocator, PatchName},
+    stack::{DependencyGraph, InitializationPolicy, Stack, StackStateAccess},
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "deps",
+    category: super::CommandCategory::StackInspection,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Show patch dependencies")
+        .long_about(
+            "Show the patches a patch depends on.\n\
+             \n\
+             A patch depends on an earlier patch in the stack when both patches \
+             change overlapping or adjacent lines of the same file. Such patches \
+             cannot be reordered relative to each other without causing conflicts. \
+             Changes to binary files make a patch depend on any earlier patch \
+             changing the same file.\n\
+             \n\
+             Only applied and unapplied patches are considered; hidden patches do \
+             not participate in dependency tracking.\n\
+             \n\
+             The dependencies determined here are also used by `stg float`, \
+             `stg sink`, `stg pop`, and `stg delete` to refuse moving a patch past \
+             a patch it depends on.",
+        )
+        .arg(
+            Arg::new("patch")
+                .help("Patch to show dependencies of")
+                .long_help(
+                    "Patch to show dependencies of. The topmost patch is used by \
+                     default.",
+                )
+                .allow_hyphen_values(true)
+                .value_parser(clap::value_parser!(PatchLocator)),
+        )
+        .arg(argset::branch_arg())
+        .arg(
+            Arg::new("reverse")
+                .long("reverse")
+                .short('r')
+                .help("Show patches that depend on the patch")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("transitive")
+                .long("transitive")
+                .short('t')
+                .help("Include indirect dependencies")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::from_branch_locator(
+        &repo,
+        matches.get_one::<BranchLocator>("branch"),
+        InitializationPolicy::AllowUninitialized,
+    )?;
+
+    let patchname = if let Some(loc) = matches.get_one::<PatchLocator>("patch") {
+        loc.resolve_name(&stack)?
+            .constrain(&stack, LocationConstraint::Visible)?
+    } else if let Some(patchname) = stack.applied().last() {
+        patchname.clone()
+    } else {
+        return Err(super::Error::NoAppliedPatches.into());
+    };
+
+    let graph = DependencyGraph::from_stack(&stack)?;
+
+    let related: Vec<&PatchName> =
+        match (matches.get_flag("reverse"), matches.get_flag("transitive")) {
+            (false, false) => graph.dependencies(&patchname),
+            (false, true) => graph.all_dependencies(&patchname),
+            (true, false) => graph.dependents(&patchname),
+            (true, true) => graph.all_dependents(&patchname),
+        };
+
+    let mut stdout = std::io::stdout().lock();
+    for pn in related {
+        writeln!(stdout, "{pn}")?;
+    }
+
+    Ok(())
+}
@@ -9,8 +9,11 @@ use crate::{
     argset,
     branchloc::BranchLocator,
     ext::{CommitExtended, RepositoryExtended},
+    patch::PatchName,
     patch::{patchrange, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stack::{
+        InitializationPolicy, SeriesVersion, Stack, StackAccess, StackState, StackStateAccess,
+    },
     stupid::Stupid,
 };
 
@@ -40,6 +43,14 @@ pub(super) fn command() -> clap::Command {
              always generate a cover letter or 'auto' to generate a cover letter when \
              formatting more than one patch.\n\
              \n\
+             When series versions have been saved with `stg series-version save`, \
+             the reroll count is determined automatically from the saved 'v<n>' \
+             versions: if the current series is saved as 'v<n>', the reroll count is \
+             <n>, otherwise it is one more than the highest saved version. When a \
+             cover letter is generated or a single patch is formatted, a range-diff \
+             against the previous saved version is also included. Use \
+             '--no-series-version' to disable this behavior.\n\
+             \n\
              Recipients may be specified using the '--to' and '--cc', or setting \
              recipients may be deferred to `stg email send`.\n\
              \n\
@@ -83,6 +94,16 @@ pub(super) fn command() -> clap::Command {
                 .action(clap::ArgAction::Append)
                 .value_name("option"),
         )
+        .arg(
+            Arg::new("no-series-version")
+                .long("no-series-version")
+                .help("Do not use saved series versions")
+                .long_help(
+                    "Do not use saved series versions to determine the reroll count \
+                     or to generate a range-diff against the previous version.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
         .next_help_heading("Format Options")
         .args(format_options())
         .next_help_heading("Message Options")
@@ -515,6 +536,10 @@ pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
         format_args.extend(values.cloned());
     }
 
+    if !matches.get_flag("no-series-version") {
+        format_args.extend(series_version_args(&stack, matches, &patches)?);
+    }
+
     {
         let base = stack
             .get_patch_commit(&patches[0])
@@ -528,3 +553,76 @@ pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
 
     repo.stupid().format_patch(format_args)
 }
+
+/// Determine `git format-patch` arguments based on saved series versions.
+///
+/// The reroll count is derived from the saved numbered versions. If the current stack
+/// state is itself the latest saved version, that version's number is used and the
+/// series is compared with the version before it. Otherwise, the series is treated as
+/// the next version and is compared with the latest saved version.
+fn series_version_args(
+    stack: &Stack,
+    matches: &clap::ArgMatches,
+    patches: &[PatchName],
+) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let versions: Vec<SeriesVersion> = stack
+        .series_versions()?
+        .into_iter()
+        .filter(|version| version.number().is_some())
+        .collect();
+    let Some(latest) = versions.last() else {
+        return Ok(args);
+    };
+    let repo = stack.repo;
+    let state_commit_id = repo
+        .find_reference(stack.get_stack_refname())?
+        .peel_to_commit()?
+        .id;
+    let (reroll_count, previous) = if latest.state_commit.id == state_commit_id {
+        (latest.number(), versions.iter().rev().nth(1))
+    } else {
+        (latest.number().map(|n| n + 1), Some(latest))
+    };
+
+    let from_cmdline = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            Some(clap::parser::ValueSource::CommandLine)
+        )
+    };
+
+    if !from_cmdline("reroll-count") {
+        if let Some(reroll_count) = reroll_count.filter(|&n| n > 1) {
+            args.push(format!("--reroll-count={reroll_count}"));
+        }
+    }
+
+    if let Some(previous) = previous {
+        let config = repo.config_snapshot();
+        let cover_letter = from_cmdline("cover-letter")
+            || match config.string("format.coverLetter") {
+                Some(value) if value.eq_ignore_ascii_case(b"auto") => patches.len() > 1,
+                Some(_) => config.boolean("format.coverLetter").unwrap_or(false),
+                None => false,
+            };
+        if !from_cmdline("range-diff")
+            && !from_cmdline("interdiff")
+            && (cover_letter || patches.len() == 1)
+        {
+            let previous_state = StackState::from_commit(repo, &previous.state_commit)?;
+            if let Some(first) = previous_state.applied().first() {
+                let base = previous_state
+                    .get_patch_commit(first)
+                    .parent_ids()
+                    .next()
+                    .unwrap()
+                    .detach();
+                let top = previous_state.top().id;
+                args.push(format!("--range-diff={base}..{top}"));
+            }
+        }
+    }
+
+    Ok(args)
+}
@@ -480,6 +480,8 @@ pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
         send_args.extend(values.cloned());
     }
 
+    crate::hook::run_pre_email_hook(&repo, &sources)?;
+
     let mut sources = sources;
     send_args.append(&mut sources);
 
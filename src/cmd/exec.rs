@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg exec` implementation.
+
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches, ValueHint};
+
+use crate::{
+    argset,
+    color::{get_color_stderr, get_color_stdout},
+    ext::RepositoryExtended,
+    patch::{patchrange, PatchName, PatchRange, RangeConstraint},
+    print_info_message,
+    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stupid::Stupid,
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "exec",
+    category: super::CommandCategory::StackInspection,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Run a command for each patch in a range")
+        .long_about(
+            "Run a shell command for each patch in a range of patches, reporting \
+             whether the command passed or failed for each patch.\n\
+             \n\
+             For each patch, patches are pushed or popped, as with 'stg goto', such \
+             that the patch is the topmost applied patch. The command is then run with \
+             'sh -c' in the root of the work tree. An exit status of zero means the \
+             command passed for the patch; any other status means it failed.\n\
+             \n\
+             A single <command> argument is run as a shell command, e.g. \
+             'stg exec -- \"make && make check\"'. Multiple arguments are run as a \
+             program followed by its arguments, each argument being passed as given, \
+             e.g. 'stg exec -- grep -q \"some text\" file.txt'.\n\
+             \n\
+             By default, the applied patches are tested. Use '--range' to test a \
+             different range of patches, which may include unapplied patches.\n\
+             \n\
+             By default, testing stops at the first patch for which the command fails, \
+             leaving that patch as the topmost applied patch. With '--keep-going', all \
+             patches in the range are tested. When all patches are tested, the \
+             originally applied patches are restored.\n\
+             \n\
+             The work tree and index must be clean before running 'stg exec'. The \
+             command must not leave any changes to tracked files behind.",
+        )
+        .override_usage("stg exec [OPTIONS] [--range <patch-range>] -- <command>...")
+        .arg(
+            Arg::new("command")
+                .help("Shell command to run for each patch")
+                .value_name("command")
+                .num_args(1..)
+                .required(true)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true)
+                .value_hint(ValueHint::CommandWithArguments),
+        )
+        .arg(
+            Arg::new("range")
+                .long("range")
+                .short('r')
+                .help("Run command for the patches in <patch-range>")
+                .value_name("patch-range")
+                .allow_hyphen_values(true)
+                .value_parser(clap::value_parser!(PatchRange))
+                .value_hint(ValueHint::Other),
+        )
+        .arg(
+            Arg::new("keep-going")
+                .long("keep-going")
+                .short('k')
+                .help("Continue with remaining patches when the command fails")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Output results as JSON")
+                .long_help(
+                    "Output results as JSON. The standard output of the command is \
+                     redirected to standard error such that only the JSON results are \
+                     written to standard output.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(argset::push_conflicts_arg())
+}
+
+/// Outcome of running the command for a patch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Status::Pass => "pass",
+            Status::Fail => "FAIL",
+            Status::Skipped => "skipped",
+        })
+    }
+}
+
+#[derive(serde::Serialize)]
+struct PatchResult<'a> {
+    patch: &'a str,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+}
+
+#[derive(serde::Serialize)]
+struct Report<'a> {
+    command: &'a str,
+    results: Vec<PatchResult<'a>>,
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::current(&repo, InitializationPolicy::AllowUninitialized)?;
+    let stupid = repo.stupid();
+
+    repo.check_repository_state()?;
+    let statuses = stupid.statuses(None)?;
+    statuses.check_conflicts()?;
+    stack.check_head_top_mismatch()?;
+    statuses.check_index_and_worktree_clean()?;
+
+    let mut patches = if let Some(range) = matches.get_one::<PatchRange>("range") {
+        patchrange::resolve_names(&stack, [range], RangeConstraint::Visible)?
+    } else if stack.applied().is_empty() {
+        return Err(super::Error::NoAppliedPatches.into());
+    } else {
+        stack.applied().to_vec()
+    };
+    patches.sort_by_key(|pn| stack.index_of(pn));
+    patches.dedup();
+
+    if patches.is_empty() {
+        return Err(anyhow!("no patches to run command for"));
+    }
+
+    let command_args: Vec<&String> = matches
+        .get_many::<String>("command")
+        .expect("required argument")
+        .collect();
+    let command = if let [command] = command_args.as_slice() {
+        command.to_string()
+    } else {
+        command_args
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let keep_going = matches.get_flag("keep-going");
+    let json_flag = matches.get_flag("json");
+
+    // Keep standard output for the JSON results.
+    let shell_command = if json_flag {
+        format!("exec >&2\n{command}")
+    } else {
+        command.clone()
+    };
+
+    let output = || {
+        if json_flag {
+            get_color_stderr(matches)
+        } else {
+            get_color_stdout(matches)
+        }
+    };
+
+    let series: Vec<PatchName> = stack.applied_and_unapplied().cloned().collect();
+    let orig_num_applied = stack.applied().len();
+
+    let mut results: Vec<PatchResult> = Vec::with_capacity(patches.len());
+    let mut failed: Vec<&PatchName> = Vec::new();
+
+    for patchname in &patches {
+        if !failed.is_empty() && !keep_going {
+            results.push(PatchResult {
+                patch: patchname.as_ref(),
+                status: Status::Skipped,
+                exit_code: None,
+            });
+            continue;
+        }
+
+        let position = series
+            .iter()
+            .position(|pn| pn == patchname)
+            .expect("patches are visible");
+        super::goto::apply_prefix(&repo, matches, &series, position + 1, output(), "exec")?;
+
+        print_info_message(matches, &format!("Executing `{command}` for `{patchname}`"));
+        let exit_status = stupid.user_exec_status(&shell_command)?;
+        let status = if exit_status.success() {
+            Status::Pass
+        } else {
+            failed.push(patchname);
+            Status::Fail
+        };
+        results.push(PatchResult {
+            patch: patchname.as_ref(),
+            status,
+            exit_code: exit_status.code(),
+        });
+    }
+
+    if failed.is_empty() || keep_going {
+        super::goto::apply_prefix(&repo, matches, &series, orig_num_applied, output(), "exec")?;
+    }
+
+    if json_flag {
+        let report = Report {
+            command: &command,
+            results,
+        };
+        super::json::print(&report)?;
+    } else {
+        let width = patches.iter().map(PatchName::len).max().unwrap_or_default();
+        for result in &results {
+            println!("{:width$}  {}", result.patch, result.status);
+        }
+    }
+
+    match failed.as_slice() {
+        [] => Ok(()),
+        [patchname] if !keep_going => Err(anyhow!(
+            "`{command}` failed for `{patchname}`; stopped with `{patchname}` as the topmost patch"
+        )),
+        _ => Err(anyhow!(
+            "`{command}` failed for {} of {} patches",
+            failed.len(),
+            patches.len()
+        )),
+    }
+}
+
+/// Quote an argument for use in a shell command line.
+///
+/// Arguments consisting only of characters without special meaning to the shell are
+/// left as-is.
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
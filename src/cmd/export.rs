@@ -52,7 +52,14 @@ fn make() -> clap::Command {
              \n    %(authemail)s   - author email\
              \n    %(authdate)s    - patch creation date (ISO-8601 format)\
              \n    %(commname)s    - committer name\
-             \n    %(commemail)s   - committer email",
+             \n    %(commemail)s   - committer email\n\
+             \n\
+             With '--stack-archive', the whole stack is instead exported to a git \
+             bundle file. The bundle contains the stack's metadata along with the \
+             commits of all applied, unapplied, and hidden patches, which allows the \
+             identical stack to be recreated elsewhere with 'stg import \
+             --stack-archive'. The repository importing the archive must contain the \
+             stack's base commit.",
         )
         .arg(
             Arg::new("patchranges")
@@ -119,11 +126,46 @@ fn make() -> clap::Command {
                 .action(clap::ArgAction::SetTrue),
         )
         .arg(argset::diff_opts_arg())
+        .arg(
+            Arg::new("stack-archive")
+                .long("stack-archive")
+                .help("Export the whole stack to a bundle file at <path>")
+                .value_name("path")
+                .value_hint(clap::ValueHint::FilePath)
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with_all([
+                    "patchranges",
+                    "dir",
+                    "patch",
+                    "extension",
+                    "numbered",
+                    "template",
+                    "stdout",
+                ]),
+        )
 }
 
 fn run(matches: &clap::ArgMatches) -> Result<()> {
     let repo = gix::Repository::open()?;
     let opt_branch = matches.get_one::<BranchLocator>("branch");
+
+    if let Some(archive_path) = matches.get_one::<PathBuf>("stack-archive") {
+        let stack = Stack::from_branch_locator(
+            &repo,
+            opt_branch,
+            InitializationPolicy::RequireInitialized,
+        )?;
+        stack.check_head_top_mismatch()?;
+        return repo
+            .stupid()
+            .bundle_create(
+                archive_path,
+                &[stack.get_stack_refname()],
+                &[stack.base().id],
+            )
+            .with_context(|| format!("creating stack archive {archive_path:?}"));
+    }
+
     let stack =
         Stack::from_branch_locator(&repo, opt_branch, InitializationPolicy::AllowUninitialized)?;
     let stupid = repo.stupid();
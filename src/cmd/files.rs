@@ -9,6 +9,7 @@ use bstr::ByteSlice;
 use clap::{Arg, ArgMatches};
 
 use crate::{
+    argset,
     ext::{CommitExtended, RepositoryExtended},
     patch::SingleRevisionSpec,
     stack::Stack,
@@ -55,6 +56,7 @@ fn make() -> clap::Command {
                 .action(clap::ArgAction::SetTrue)
                 .conflicts_with("stat"),
         )
+        .arg(argset::json_arg().conflicts_with_all(["stat", "bare"]))
 }
 
 fn run(matches: &ArgMatches) -> Result<()> {
@@ -69,6 +71,26 @@ fn run(matches: &ArgMatches) -> Result<()> {
         Rc::new(repo.head_commit()?)
     };
     let parent = commit.get_parent_commit()?;
+
+    if matches.get_flag("json") {
+        #[derive(serde::Serialize)]
+        struct File {
+            status: String,
+            path: String,
+        }
+
+        let files: Vec<File> = repo
+            .stupid()
+            .diff_tree_name_status(parent.tree_id()?.detach(), commit.tree_id()?.detach())?
+            .into_iter()
+            .map(|(status, path)| File {
+                status: status.to_str_lossy().to_string(),
+                path: path.to_str_lossy().to_string(),
+            })
+            .collect();
+        return super::json::print(&files);
+    }
+
     let mut output = repo.stupid().diff_tree_files_status(
         parent.tree_id()?.detach(),
         commit.tree_id()?.detach(),
@@ -15,7 +15,7 @@ use crate::{
     color::get_color_stdout,
     ext::RepositoryExtended,
     patch::{patchrange, PatchName, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stack::{DependencyGraph, InitializationPolicy, Stack, StackStateAccess},
     stupid::Stupid,
 };
 
@@ -67,6 +67,11 @@ fn make() -> clap::Command {
                 .value_hint(clap::ValueHint::FilePath)
                 .value_parser(clap::value_parser!(PathBuf)),
         )
+        .arg(argset::with_deps_arg().long_help(
+            "Also float the patches that depend on the floated patches, as well as \
+             any unapplied patches the floated patches depend on. See `stg deps`.",
+        ))
+        .arg(argset::ignore_deps_arg())
         .arg(argset::keep_arg())
         .arg(argset::committer_date_is_author_date_arg())
 }
@@ -98,6 +103,38 @@ fn run(matches: &ArgMatches) -> Result<()> {
         return Err(anyhow!("no patches to float"));
     }
 
+    let graph = if matches.get_flag("ignore-deps") {
+        None
+    } else {
+        // Patches below the lowest floated patch are unaffected, but any unapplied
+        // patch may be a dependency of a floated unapplied patch.
+        let start = patches
+            .iter()
+            .map(|pn| stack.index_of(pn))
+            .chain([stack.applied().len()])
+            .min()
+            .expect("chain is not empty");
+        let window = stack.applied_and_unapplied().skip(start).cloned().collect();
+        Some(DependencyGraph::new(&stack, window)?)
+    };
+
+    let patches = match &graph {
+        Some(graph) if matches.get_flag("with-deps") => graph.expand(&patches, |graph, pn| {
+            // Applied dependents would otherwise be left below the floated patch and
+            // unapplied dependencies would remain unapplied.
+            let mut related = graph.dependents(pn);
+            related.retain(|pn| stack.is_applied(pn));
+            related.extend(
+                graph
+                    .dependencies(pn)
+                    .into_iter()
+                    .filter(|pn| !stack.is_applied(pn)),
+            );
+            related
+        }),
+        _ => patches,
+    };
+
     if !keep_flag && (!noapply_flag || patches.iter().any(|pn| stack.is_applied(pn))) {
         statuses.check_index_and_worktree_clean()?;
     }
@@ -132,6 +169,10 @@ fn run(matches: &ArgMatches) -> Result<()> {
         (applied, unapplied)
     };
 
+    if let Some(graph) = &graph {
+        graph.check(&applied)?;
+    }
+
     stack
         .setup_transaction()
         .use_index_and_worktree(true)
@@ -90,3 +90,49 @@ fn run(matches: &ArgMatches) -> Result<()> {
 
     Ok(())
 }
+
+/// Push or pop patches such that only the first `num_applied` of `patches` are applied.
+///
+/// The `patches` must be visible patches in stack order, starting with the applied
+/// patches. Patches after the first `num_applied` patches are left unapplied in their
+/// original order, and patches not in `patches` are left untouched. This allows a
+/// command to successively go to different patches and eventually restore the
+/// original state.
+pub(super) fn apply_prefix(
+    repo: &gix::Repository,
+    matches: &ArgMatches,
+    patches: &[PatchName],
+    num_applied: usize,
+    output: termcolor::StandardStream,
+    reflog_msg: &str,
+) -> Result<()> {
+    let stack = Stack::current(repo, InitializationPolicy::RequireInitialized)?;
+    let current_num_applied = stack.applied().len();
+
+    if current_num_applied == num_applied {
+        return Ok(());
+    }
+
+    let allow_push_conflicts =
+        argset::resolve_allow_push_conflicts(&repo.config_snapshot(), matches);
+
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .allow_push_conflicts(allow_push_conflicts)
+        .with_output_stream(output)
+        .transact(|trans| {
+            if num_applied < current_num_applied {
+                let applied = patches[0..num_applied].to_vec();
+                let mut unapplied = patches[num_applied..current_num_applied].to_vec();
+                unapplied.extend(trans.unapplied().iter().cloned());
+                trans.reorder_patches(Some(&applied), Some(&unapplied), None)
+            } else {
+                trans.push_patches(&patches[current_num_applied..num_applied], false)?;
+                Ok(())
+            }
+        })
+        .execute(reflog_msg)?;
+
+    Ok(())
+}
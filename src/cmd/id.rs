@@ -40,6 +40,7 @@ fn make() -> clap::Command {
                 .value_parser(clap::value_parser!(SingleRevisionSpec))
                 .help("StGit revision"),
         )
+        .arg(argset::json_arg())
 }
 
 fn run(matches: &ArgMatches) -> Result<()> {
@@ -50,12 +51,29 @@ fn run(matches: &ArgMatches) -> Result<()> {
         InitializationPolicy::AllowUninitialized,
     )?;
 
-    let oid = matches
+    let (oid, kind) = matches
         .get_one::<SingleRevisionSpec>("stgit-revision")
-        .map(|spec| spec.resolve_object(&repo, &stack).map(|object| object.id))
+        .map(|spec| {
+            spec.resolve_object(&repo, &stack)
+                .map(|object| (object.id, object.kind))
+        })
         .transpose()?
-        .unwrap_or_else(|| stack.get_branch_head().id);
+        .unwrap_or_else(|| (stack.get_branch_head().id, gix::object::Kind::Commit));
 
-    println!("{oid}");
-    Ok(())
+    if matches.get_flag("json") {
+        #[derive(serde::Serialize)]
+        struct Object {
+            id: String,
+            #[serde(rename = "type")]
+            kind: String,
+        }
+
+        super::json::print(&Object {
+            id: oid.to_string(),
+            kind: kind.to_string(),
+        })
+    } else {
+        println!("{oid}");
+        Ok(())
+    }
 }
@@ -5,6 +5,7 @@
 use std::{
     io::Read,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use anyhow::{anyhow, Context, Result};
@@ -13,11 +14,15 @@ use clap::{Arg, ArgGroup};
 
 use crate::{
     color::get_color_stdout,
-    ext::{RepositoryExtended, TimeExtended},
+    ext::{CommitExtended, RepositoryExtended, TimeExtended},
     patch::{patchedit, PatchName},
     print_info_message,
-    stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess},
-    stupid::Stupid,
+    stack::{
+        state_refname_from_branch_name, InitializationPolicy, Stack, StackAccess, StackState,
+        StackStateAccess, Step,
+    },
+    stupid::{Stupid, StupidContext},
+    wrap::{Message, PartialRefName},
 };
 
 pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
@@ -50,7 +55,12 @@ fn make() -> clap::Command {
              of the patch that are  applicable, leave the rejected hunks in \
              corresponding *.rej files, and add an empty patch to the stack.\n\
              \n\
-             The patch description must be separated from the diff with a \"---\" line.",
+             The patch description must be separated from the diff with a \"---\" line.\n\
+             \n\
+             When importing a series stops because a patch does not apply cleanly, \
+             fix up the patch's changes in the index and use '--continue' to import \
+             the remaining patches. Use '--skip' to delete the patch that did not \
+             apply or '--abort' to restore the stack to its state before the import.",
         )
         .override_usage(super::make_usage(
             "stg import",
@@ -64,6 +74,7 @@ fn make() -> clap::Command {
                     "[OPTIONS] -u -m <mail-url>",
                     "[OPTIONS] -u -M <mbox-url>",
                     "[OPTIONS] -u -S <series-url>",
+                    "--continue | --skip | --abort",
                 ]
             } else {
                 &[
@@ -71,6 +82,7 @@ fn make() -> clap::Command {
                     "[OPTIONS] -m [<mail-path>|<Maildir-path>]",
                     "[OPTIONS] -M [<mbox-path>]",
                     "[OPTIONS] -S [<series-path>]",
+                    "--continue | --skip | --abort",
                 ]
             },
         ))
@@ -108,7 +120,84 @@ fn make() -> clap::Command {
                 .long_help("Import patch series from a series file are tar archive.")
                 .action(clap::ArgAction::SetTrue),
         )
-        .group(ArgGroup::new("whence").args(["mail", "mbox", "series"]));
+        .arg(
+            Arg::new("stack-archive")
+                .long("stack-archive")
+                .help("Recreate a stack from a stack archive")
+                .long_help(
+                    "Recreate a stack from a bundle file created with 'stg export \
+                     --stack-archive'. A new branch is created and checked out with the \
+                     archived stack's applied, unapplied, and hidden patches, patch \
+                     commits, and stack history. The branch is named after the \
+                     archived branch unless '--branch-name' is given. The stack's base \
+                     commit must already be present in the repository.",
+                )
+                .action(clap::ArgAction::SetTrue)
+                .requires("source"),
+        )
+        .arg(
+            Arg::new("branch-name")
+                .long("branch-name")
+                .help("Name of the branch to create for a stack archive")
+                .value_name("branch")
+                .value_parser(clap::value_parser!(PartialRefName))
+                .requires("stack-archive"),
+        )
+        .group(ArgGroup::new("whence").args(["mail", "mbox", "series", "stack-archive"]))
+        .arg(
+            Arg::new("thread")
+                .long("thread")
+                .help("Reconstruct the patch series from a mail thread")
+                .long_help(
+                    "Reconstruct the patch series from a mail thread when importing \
+                     with '--mail' or '--mbox'.\n\
+                     \n\
+                     Instead of importing every message in mailbox order, the \
+                     \"[PATCH vN M/T]\" subject tags and the \"In-Reply-To\" and \
+                     \"References\" headers are used to find the latest version of the \
+                     series for which all patches are present. Its patches are \
+                     imported in M/T order. Replies and messages without a \"[PATCH]\" \
+                     tag are ignored. It is an error if no version of the series is \
+                     complete.",
+                )
+                .action(clap::ArgAction::SetTrue)
+                .requires("whence")
+                .conflicts_with_all(["series", "stack-archive"]),
+        )
+        .arg(
+            Arg::new("describe-branch")
+                .long("describe-branch")
+                .help("Use the cover letter as the branch description")
+                .long_help(
+                    "Set the branch description, as with 'stg branch --describe', from \
+                     the subject and introductory text of the series' cover letter. \
+                     The description is unchanged if there is no cover letter.",
+                )
+                .action(clap::ArgAction::SetTrue)
+                .requires("thread"),
+        )
+        .arg(
+            Arg::new("collect-trailers")
+                .long("collect-trailers")
+                .help("Add trailers from review replies to existing patches")
+                .long_help(
+                    "Instead of importing patches, collect \"Acked-by\", \
+                     \"Reviewed-by\", \"Tested-by\", \"Reported-by\", and \
+                     \"Suggested-by\" trailers from the replies in the mail or mbox and \
+                     add them to the matching patches in the stack.\n\
+                     \n\
+                     A reply applies to the patch message it replies to, directly or \
+                     via other replies. Replies to a cover letter apply to all patches \
+                     of the series. A patch matches when its \"Message-ID\" or \
+                     \"Link\" trailer refers to the patch message (see '--message-id') \
+                     or, failing that, when its subject is the same as the patch \
+                     message's subject. Trailers already in a patch's message are not \
+                     added again.",
+                )
+                .action(clap::ArgAction::SetTrue)
+                .requires("whence")
+                .conflicts_with_all(["series", "stack-archive", "thread"]),
+        );
 
     let app = if cfg!(feature = "import-url") {
         app.arg(
@@ -117,7 +206,8 @@ fn make() -> clap::Command {
                 .short('u')
                 .help("Retrieve source from a url instead of local file")
                 .action(clap::ArgAction::SetTrue)
-                .requires("source"),
+                .requires("source")
+                .conflicts_with("stack-archive"),
         )
     } else {
         app
@@ -222,11 +312,27 @@ fn make() -> clap::Command {
                 )
                 .action(clap::ArgAction::SetTrue),
         );
-    patchedit::add_args(app, false, false)
+    let app = patchedit::add_args(app, false, false);
+    super::sequence::add_args(app)
 }
 
 fn run(matches: &clap::ArgMatches) -> Result<()> {
+    if let Some(resume) = super::sequence::get_resume(matches) {
+        return super::sequence::resume(matches, STGIT_COMMAND.name, resume);
+    }
+
     let repo = gix::Repository::open()?;
+
+    if matches.get_flag("stack-archive") {
+        let source_path = matches
+            .get_one::<PathBuf>("source")
+            .expect("source is required with --stack-archive");
+        repo.stupid()
+            .statuses(None)?
+            .check_index_and_worktree_clean()?;
+        return import_stack_archive(&repo, matches, source_path);
+    }
+
     let stack = Stack::current(&repo, InitializationPolicy::AutoInitialize)?;
     let stupid = repo.stupid();
 
@@ -247,7 +353,9 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
     if cfg!(feature = "import-url") && matches.get_flag("url") {
         import_url(stack, matches)
     } else if matches.get_flag("series") {
-        import_series(stack, matches, source_path.as_deref())
+        import_series(stack, matches, source_path.as_deref(), true)
+    } else if matches.get_flag("collect-trailers") {
+        collect_trailers(stack, matches, source_path.as_deref())
     } else if matches.get_flag("mail") || matches.get_flag("mbox") {
         import_mail(stack, matches, source_path.as_deref())
     } else {
@@ -306,7 +414,7 @@ fn import_url(stack: Stack, matches: &clap::ArgMatches) -> Result<()> {
     }
 
     if matches.get_flag("series") {
-        import_series(stack, matches, Some(download_path.as_path()))
+        import_series(stack, matches, Some(download_path.as_path()), false)
     } else if matches.get_flag("mail") || matches.get_flag("mbox") {
         import_mail(stack, matches, Some(download_path.as_path()))
     } else {
@@ -321,7 +429,7 @@ fn import_tgz_series(stack: Stack, matches: &clap::ArgMatches, source_path: &Pat
     let temp_dir = tempfile::tempdir()?;
     archive.unpack(temp_dir.path())?;
     let series_path = find_series_path(temp_dir.path())?;
-    import_series(stack, matches, Some(series_path.as_path()))
+    import_series(stack, matches, Some(series_path.as_path()), false)
 }
 
 fn import_tbz2_series(stack: Stack, matches: &clap::ArgMatches, source_path: &Path) -> Result<()> {
@@ -330,7 +438,7 @@ fn import_tbz2_series(stack: Stack, matches: &clap::ArgMatches, source_path: &Pa
     let temp_dir = tempfile::tempdir()?;
     archive.unpack(temp_dir.path())?;
     let series_path = find_series_path(temp_dir.path())?;
-    import_series(stack, matches, Some(series_path.as_path()))
+    import_series(stack, matches, Some(series_path.as_path()), false)
 }
 
 fn import_tar_series(stack: Stack, matches: &clap::ArgMatches, source_path: &Path) -> Result<()> {
@@ -339,13 +447,19 @@ fn import_tar_series(stack: Stack, matches: &clap::ArgMatches, source_path: &Pat
     let temp_dir = tempfile::tempdir()?;
     archive.unpack(temp_dir.path())?;
     let series_path = find_series_path(temp_dir.path())?;
-    import_series(stack, matches, Some(series_path.as_path()))
+    import_series(stack, matches, Some(series_path.as_path()), false)
 }
 
+/// Import the patches from a series file.
+///
+/// Importing a resumable series stops if a patch does not apply cleanly, allowing the
+/// import to be continued with `stg import --continue`. Series extracted to temporary
+/// locations, i.e. from archives or urls, are not resumable.
 fn import_series(
     stack: Stack,
     matches: &clap::ArgMatches,
     source_path: Option<&Path>,
+    resumable: bool,
 ) -> Result<()> {
     let series = if let Some(source_path) = source_path {
         if let Some(filename) = source_path.file_name() {
@@ -367,7 +481,7 @@ fn import_series(
         buf
     };
 
-    let mut stack = stack;
+    let mut steps = Vec::new();
 
     for line in series.lines() {
         let line = line
@@ -407,9 +521,38 @@ fn import_series(
             None
         };
 
-        stack = import_file(stack, matches, Some(patch_path.as_path()), strip_level)?;
+        steps.push((patch_path, strip_level));
+    }
+
+    if resumable {
+        let mut sequence = super::sequence::begin(&stack, STGIT_COMMAND.name)?;
+        sequence.args = super::sequence::explicit_options(&make(), matches);
+        for (path, strip_level) in steps {
+            let path = if path.is_relative() {
+                std::env::current_dir()?.join(path)
+            } else {
+                path
+            };
+            sequence.todo.push(Step::Import { path, strip_level });
+        }
+        super::sequence::run(stack, matches, sequence)
+    } else {
+        let mut stack = stack;
+        for (path, strip_level) in steps {
+            stack = import_file(stack, matches, Some(path.as_path()), strip_level)?;
+        }
+        Ok(())
     }
-    Ok(())
+}
+
+/// Import a patch file from a series.
+pub(super) fn import_series_patch<'repo>(
+    stack: Stack<'repo>,
+    matches: &clap::ArgMatches,
+    path: &Path,
+    strip_level: Option<usize>,
+) -> Result<Stack<'repo>> {
+    import_file(stack, matches, Some(path), strip_level)
 }
 
 fn find_series_path(base: &Path) -> Result<PathBuf> {
@@ -438,9 +581,31 @@ fn import_mail(stack: Stack, matches: &clap::ArgMatches, source_path: Option<&Pa
     let message_id = use_message_id(matches, &stack.repo.config_snapshot());
     let stupid = stack.repo.stupid();
     let num_patches = stupid.mailsplit(source_path, out_dir.path(), keep_cr, missing_from_ok)?;
+    let patch_paths: Vec<PathBuf> = (1..=num_patches)
+        .map(|i| out_dir.path().join(format!("{i:04}")))
+        .collect();
+
+    let (patch_paths, cover_letter) = if matches.get_flag("thread") {
+        let series = super::mailthread::reconstruct(&patch_paths)?;
+        for incomplete in &series.incomplete {
+            print_info_message(matches, &format!("Skipping {incomplete}"));
+        }
+        print_info_message(
+            matches,
+            &format!(
+                "Importing {} patch{} from v{}",
+                series.patches.len(),
+                if series.patches.len() == 1 { "" } else { "es" },
+                series.version
+            ),
+        );
+        (series.patches, series.cover_letter)
+    } else {
+        (patch_paths, None)
+    };
+
     let mut stack = stack;
-    for i in 1..=num_patches {
-        let patch_path = out_dir.path().join(format!("{i:04}"));
+    for patch_path in patch_paths {
         let patch_file = std::fs::File::open(patch_path)?;
         let (mailinfo, message, diff) = stupid.mailinfo(Some(patch_file), message_id)?;
         let headers = Headers::parse_mailinfo(mailinfo.as_bstr()).unwrap_or_default();
@@ -454,9 +619,267 @@ fn import_mail(stack: Stack, matches: &clap::ArgMatches, source_path: Option<&Pa
             None,
         )?;
     }
+
+    if matches.get_flag("describe-branch") {
+        if let Some(cover_letter) = cover_letter {
+            let description = cover_letter_description(&stupid, &cover_letter)?;
+            let branchname = PartialRefName::from_str(stack.get_branch_name())?;
+            super::branch::set_description(stack.repo, &branchname, &description)?;
+        } else {
+            print_info_message(
+                matches,
+                "No cover letter found; branch description unchanged",
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a new branch and stack from a stack archive bundle.
+fn import_stack_archive(
+    repo: &gix::Repository,
+    matches: &clap::ArgMatches,
+    archive_path: &Path,
+) -> Result<()> {
+    let stupid = repo.stupid();
+    let (state_id, archived_branchname) = stupid
+        .bundle_list_heads(archive_path)?
+        .into_iter()
+        .find_map(|(oid, refname)| {
+            refname
+                .strip_prefix(b"refs/stacks/")
+                .and_then(|name| name.to_str().ok())
+                .map(|name| (oid, name.to_string()))
+        })
+        .ok_or_else(|| anyhow!("{archive_path:?} is not a stack archive"))?;
+
+    let branchname = if let Some(branchname) = matches.get_one::<PartialRefName>("branch-name") {
+        branchname.clone()
+    } else {
+        PartialRefName::from_str(&archived_branchname)?
+    };
+    let branch_fullname = format!("refs/heads/{branchname}");
+    if repo.find_reference(&branch_fullname).is_ok() {
+        return Err(anyhow!("branch `{branchname}` already exists"));
+    }
+
+    stupid
+        .bundle_unbundle(archive_path)
+        .with_context(|| format!("reading stack archive {archive_path:?}"))?;
+    let state_commit = repo.find_commit(state_id)?;
+    let state = StackState::from_commit(repo, &state_commit)?;
+
+    repo.edit_reference(gix::refs::transaction::RefEdit {
+        change: gix::refs::transaction::Change::Update {
+            log: gix::refs::transaction::LogChange {
+                mode: gix::refs::transaction::RefLog::AndReference,
+                force_create_reflog: false,
+                message: format!("import: stack archive of {archived_branchname}").into(),
+            },
+            expected: gix::refs::transaction::PreviousValue::MustNotExist,
+            new: gix::refs::Target::Object(state.head().id),
+        },
+        name: gix::refs::FullName::try_from(branch_fullname.as_str())?,
+        deref: false,
+    })?;
+    let stack_refname = state_refname_from_branch_name(branchname.as_ref());
+    repo.reference(
+        stack_refname.as_str(),
+        state_id,
+        gix::refs::transaction::PreviousValue::MustNotExist,
+        "import: stack archive",
+    )?;
+
+    let result =
+        Stack::from_branch_name(repo, &branchname, InitializationPolicy::RequireInitialized)
+            .and_then(|_| stupid.checkout(branchname.as_ref()));
+    if result.is_err() {
+        if let Ok(reference) = repo.find_reference(&branch_fullname) {
+            reference.delete().ok();
+        }
+        if let Ok(reference) = repo.find_reference(&stack_refname) {
+            reference.delete().ok();
+        }
+    }
+    result
+}
+
+/// Add trailers collected from review replies in a mailbox to the matching patches.
+fn collect_trailers(
+    stack: Stack,
+    matches: &clap::ArgMatches,
+    source_path: Option<&Path>,
+) -> Result<()> {
+    let out_dir = tempfile::tempdir()?;
+    let stupid = stack.repo.stupid();
+    let num_mails = stupid.mailsplit(
+        source_path,
+        out_dir.path(),
+        matches.get_flag("keep-cr"),
+        matches.get_flag("mail"),
+    )?;
+    let mail_paths: Vec<PathBuf> = (1..=num_mails)
+        .map(|i| out_dir.path().join(format!("{i:04}")))
+        .collect();
+    let reviews = super::mailthread::collect_trailers(&stupid, &mail_paths)?;
+
+    let patchnames: Vec<&PatchName> = stack.applied().iter().chain(stack.unapplied()).collect();
+    let mut updates: Vec<(PatchName, gix::ObjectId)> = Vec::new();
+    for review in &reviews {
+        let by_message_id = review.message_id.as_deref().and_then(|message_id| {
+            patchnames.iter().find(|patchname| {
+                let commit = stack.get_patch_commit(patchname);
+                patch_message_ids(&commit.message_ex()).any(|id| id == message_id)
+            })
+        });
+        let Some(patchname) = by_message_id.or_else(|| {
+            patchnames.iter().find(|patchname| {
+                let commit = stack.get_patch_commit(patchname);
+                commit
+                    .message_ex()
+                    .decode()
+                    .is_ok_and(|message| message.lines().next() == Some(review.subject.as_str()))
+            })
+        }) else {
+            print_info_message(
+                matches,
+                &format!("No patch matches `{}`; trailers ignored", review.subject),
+            );
+            continue;
+        };
+
+        let commit = if let Some((_, commit_id)) = updates.iter().find(|(pn, _)| pn == *patchname) {
+            stack.repo.find_commit(*commit_id)?
+        } else {
+            stack.get_patch_commit(patchname).as_ref().clone()
+        };
+        let message = commit.message_ex();
+        let existing: Vec<String> = message
+            .decode()?
+            .lines()
+            .map(|line| line.trim().to_ascii_lowercase())
+            .collect();
+        let trailers: Vec<(&str, &str)> = review
+            .trailers
+            .iter()
+            .filter(|(trailer, value)| {
+                !existing.contains(&format!("{trailer}: {value}").to_ascii_lowercase())
+            })
+            .map(|(trailer, value)| (trailer.as_str(), value.as_str()))
+            .collect();
+        if trailers.is_empty() {
+            continue;
+        }
+
+        let message = patchedit::add_trailers(stack.repo, message, trailers)?;
+        let commit_id = stack.repo.commit_ex(
+            &commit.author_strict()?,
+            stack.repo.get_committer()?,
+            &message,
+            commit.tree_id()?.detach(),
+            [commit.get_parent_commit()?.id],
+        )?;
+        if let Some(update) = updates.iter_mut().find(|(pn, _)| pn == *patchname) {
+            update.1 = commit_id;
+        } else {
+            updates.push(((*patchname).clone(), commit_id));
+        }
+    }
+
+    if updates.is_empty() {
+        print_info_message(matches, "No new trailers to add");
+        return Ok(());
+    }
+
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| {
+            let popped = if let Some(pos) = trans
+                .applied()
+                .iter()
+                .position(|pn| updates.iter().any(|(patchname, _)| patchname == pn))
+            {
+                let to_pop = trans.applied()[pos + 1..].to_vec();
+                let popped_extra = trans.pop_patches(|pn| to_pop.contains(pn))?;
+                assert!(popped_extra.is_empty());
+                to_pop
+            } else {
+                vec![]
+            };
+
+            for (patchname, commit_id) in &updates {
+                trans.update_patch(patchname, *commit_id)?;
+            }
+
+            trans.push_patches(&popped, false)
+        })
+        .execute("import: collect trailers")?;
+
     Ok(())
 }
 
+/// Get the message ids from a patch's `Message-ID` and `Link` trailers.
+///
+/// `Link` trailers, such as those added by b4, refer to the message id as the last
+/// component of an archive URL.
+fn patch_message_ids<'a>(message: &'a Message) -> impl Iterator<Item = String> + 'a {
+    message.raw_bytes().lines().filter_map(|line| {
+        let (key, value) = line.to_str().ok()?.split_once(':')?;
+        let value = value.trim();
+        if key.eq_ignore_ascii_case("message-id") {
+            Some(
+                value
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string(),
+            )
+        } else if key.eq_ignore_ascii_case("link") {
+            value
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .map(ToString::to_string)
+        } else {
+            None
+        }
+    })
+}
+
+/// Get branch description from a cover letter's subject and introductory text.
+///
+/// The introductory text ends before the shortlog (e.g. "A U Thor (3):") that `git
+/// format-patch` adds to cover letters. Unfilled "*** BLURB HERE ***" text is omitted.
+fn cover_letter_description(stupid: &StupidContext, path: &Path) -> Result<String> {
+    let (mailinfo, message, _) = stupid.mailinfo(Some(std::fs::File::open(path)?), false)?;
+    let headers = Headers::parse_mailinfo(mailinfo.as_bstr()).unwrap_or_default();
+    let mut description = headers.subject.unwrap_or_default();
+
+    let message = message.to_str_lossy();
+    let intro: Vec<&str> = message
+        .lines()
+        .take_while(|line| {
+            !line
+                .strip_suffix("):")
+                .and_then(|rest| rest.rsplit_once(" ("))
+                .is_some_and(|(name, count)| {
+                    !name.is_empty()
+                        && !count.is_empty()
+                        && count.bytes().all(|b| b.is_ascii_digit())
+                })
+        })
+        .collect();
+    let intro = intro.join("\n");
+    let intro = intro.trim();
+    if !intro.is_empty() && intro != "*** BLURB HERE ***" {
+        description.push_str("\n\n");
+        description.push_str(intro);
+    }
+    Ok(description)
+}
+
 fn read_gz(source_file: std::fs::File, content: &mut Vec<u8>) -> Result<()> {
     flate2::read::GzDecoder::new(source_file).read_to_end(content)?;
     Ok(())
@@ -568,7 +991,14 @@ fn create_patch<'repo>(
     let patchname = if let Some(patchname) = patchname {
         PatchName::make(patchname, false, name_len_limit)
     } else {
-        PatchName::make(&message, true, name_len_limit)
+        let existing_patchnames: Vec<&PatchName> = stack.all_patches().collect();
+        PatchName::generate(
+            stack.repo,
+            &message,
+            true,
+            name_len_limit,
+            &existing_patchnames,
+        )?
     };
 
     let ignore_flag = matches.get_flag("ignore");
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! JSON output for inspection commands.
+//!
+//! The objects defined here form a scripting interface and are documented in the JSON
+//! OUTPUT section of stg(1). Fields may be added to these objects, but existing fields
+//! must not be removed or have their meaning changed.
+
+use std::io::Write;
+
+use anyhow::Result;
+use bstr::ByteSlice;
+
+use crate::{ext::CommitExtended, patch::PatchName, stack::StackAccess};
+
+/// Print value as pretty JSON to stdout.
+pub(super) fn print<T: serde::Serialize>(value: &T) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Identity and timestamp from a commit's author or committer.
+#[derive(serde::Serialize)]
+pub(super) struct Signature {
+    name: String,
+    email: String,
+    date: String,
+}
+
+impl From<gix::actor::SignatureRef<'_>> for Signature {
+    fn from(sig: gix::actor::SignatureRef<'_>) -> Self {
+        Self {
+            name: sig.name.to_str_lossy().to_string(),
+            email: sig.email.to_str_lossy().to_string(),
+            date: sig.time.format(gix::date::time::format::ISO8601_STRICT),
+        }
+    }
+}
+
+/// Whether a patch is applied, unapplied, or hidden.
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum PatchStatus {
+    Applied,
+    Unapplied,
+    Hidden,
+}
+
+/// A patch in a stack.
+#[derive(serde::Serialize)]
+pub(super) struct Patch {
+    name: String,
+    branch: String,
+    status: PatchStatus,
+    top: bool,
+    commit: String,
+    author: Signature,
+    subject: String,
+    empty: bool,
+    index: usize,
+    offset: isize,
+}
+
+impl Patch {
+    /// Describe patch in the given stack.
+    pub(super) fn new<'repo>(
+        stack: &impl StackAccess<'repo>,
+        patchname: &PatchName,
+    ) -> Result<Self> {
+        let top_patchname = stack.applied().last();
+        let status = if stack.is_applied(patchname) {
+            PatchStatus::Applied
+        } else if stack.is_unapplied(patchname) {
+            PatchStatus::Unapplied
+        } else {
+            PatchStatus::Hidden
+        };
+        let commit = stack.get_patch_commit(patchname);
+        let commit_ref = commit.decode()?;
+        Ok(Self {
+            name: patchname.to_string(),
+            branch: stack.get_branch_name().to_string(),
+            status,
+            top: Some(patchname) == top_patchname,
+            commit: commit.id.to_string(),
+            author: commit_ref.author().into(),
+            subject: commit_ref.message_summary().to_str_lossy().to_string(),
+            empty: commit.is_no_change()?,
+            index: stack.index_of(patchname),
+            offset: stack.distance_from(patchname, top_patchname),
+        })
+    }
+}
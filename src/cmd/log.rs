@@ -2,15 +2,27 @@
 
 //! `stg log` implementation.
 
+use std::io::Write;
+
 use anyhow::{anyhow, Result};
-use clap::{Arg, ArgMatches};
+use bstr::ByteSlice;
+use clap::{builder::ValueParser, Arg, ArgGroup, ArgMatches};
+use is_terminal::IsTerminal;
 
+use super::log_browser::{self, Action};
 use crate::{
     argset,
     branchloc::BranchLocator,
+    color::get_color_stdout,
+    ext::CommitExtended,
     ext::RepositoryExtended,
-    patch::{patchrange, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackAccess},
+    ext::TimeExtended,
+    patch::{patchrange, PatchName, PatchRange, RangeConstraint},
+    print_info_message,
+    stack::{
+        delete_stale_patch_refs, patch_versions, InitializationPolicy, Stack, StackAccess,
+        StackState, StackStateAccess, StateHistory,
+    },
     stupid::Stupid,
 };
 
@@ -32,13 +44,37 @@ fn make() -> clap::Command {
              through historical stack states. The 'stg reset' command may be used to \
              reset the stack directly to a historic state.\n\
              \n\
+             The '--interactive' option opens a terminal browser of the stack \
+             history. Selecting a history entry shows how that stack state differs \
+             from the current stack: which patches were added, deleted, reordered, or \
+             modified since, along with the diff of each patch. From the browser, the \
+             stack may be reset to the selected state with 'r', or a single patch \
+             restored from the selected state with 'p', like with 'stg reset'.\n\
+             \n\
+             The '--versions' option lists the distinct versions each patch has had \
+             over the course of the stack's history, oldest first. Versions are \
+             numbered from 1 and may be compared using 'stg range-diff'.\n\
+             \n\
              The '--clear' option may be used to delete the stack's change history. \
              Undo and redo are unavailable on a stack without change history. Clearing \
-             the stack state history cannot be undone.",
+             the stack state history cannot be undone.\n\
+             \n\
+             The '--compact' option may be used to drop old entries from the stack's \
+             change history while retaining recent entries, either the '--keep' \
+             newest entries or the entries newer than the '--older-than' date. Undo \
+             and redo remain available within the retained history. Patch refs left \
+             behind by stacks that no longer exist, e.g. due to a branch being \
+             deleted with 'git branch -D', are also removed. Compacting the stack \
+             state history cannot be undone.",
         )
         .override_usage(super::make_usage(
             "stg log",
-            &["[OPTIONS] [--] [patch]...", "--clear"],
+            &[
+                "[OPTIONS] [--] [patch]...",
+                "--interactive",
+                "--clear",
+                "--compact (--keep <n> | --older-than <date>)",
+            ],
         ))
         .arg(
             Arg::new("patchranges-all")
@@ -71,13 +107,36 @@ fn make() -> clap::Command {
                 .help("Show using full commit log format")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("versions")
+                .long("versions")
+                .short('V')
+                .help("List the distinct versions of patches")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["diff", "number", "full"]),
+        )
         .arg(
             Arg::new("graphical")
                 .long("graphical")
                 .short('g')
                 .help("Run gitk instead of printing to stdout")
                 .action(clap::ArgAction::SetTrue)
-                .conflicts_with_all(["diff", "number", "full"]),
+                .conflicts_with_all(["diff", "number", "full", "versions"]),
+        )
+        .arg(
+            Arg::new("interactive")
+                .long("interactive")
+                .short('i')
+                .help("Browse the stack history interactively")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "patchranges-all",
+                    "diff",
+                    "number",
+                    "full",
+                    "versions",
+                    "graphical",
+                ]),
         )
         .arg(
             Arg::new("clear")
@@ -85,7 +144,71 @@ fn make() -> clap::Command {
                 .help("Clear the stack history")
                 // .exclusive(true),
                 .action(clap::ArgAction::SetTrue)
-                .conflicts_with_all(["patchranges-all", "diff", "number", "full", "graphical"]),
+                .conflicts_with_all([
+                    "patchranges-all",
+                    "diff",
+                    "number",
+                    "full",
+                    "versions",
+                    "graphical",
+                    "interactive",
+                ]),
+        )
+        .arg(
+            Arg::new("compact")
+                .long("compact")
+                .help("Drop old entries from the stack history")
+                .action(clap::ArgAction::SetTrue)
+                .requires("compact-limit")
+                .conflicts_with_all([
+                    "patchranges-all",
+                    "diff",
+                    "number",
+                    "full",
+                    "versions",
+                    "graphical",
+                    "interactive",
+                    "clear",
+                ]),
+        )
+        .arg(
+            Arg::new("keep")
+                .long("keep")
+                .help("Keep the <n> newest stack history entries")
+                .value_name("n")
+                .requires("compact")
+                .value_parser(parse_keep),
+        )
+        .arg(
+            Arg::new("older-than")
+                .long("older-than")
+                .help("Drop stack history entries older than <date>")
+                .value_name("date")
+                .requires("compact")
+                .value_parser(ValueParser::new(gix::date::Time::parse_time))
+                .value_hint(clap::ValueHint::Other),
+        )
+        .group(
+            ArgGroup::new("compact-limit")
+                .args(["keep", "older-than"])
+                .multiple(false),
+        )
+        .arg(
+            argset::json_arg()
+                .conflicts_with_all([
+                    "diff",
+                    "full",
+                    "versions",
+                    "graphical",
+                    "interactive",
+                    "clear",
+                    "compact",
+                ])
+                .long_help(
+                    "Output the stack history as a JSON array of stack log entry \
+                     objects, newest first. Stack log entry objects are described in \
+                     the JSON OUTPUT section of stg(1).",
+                ),
         )
 }
 
@@ -99,6 +222,25 @@ fn run(matches: &ArgMatches) -> Result<()> {
 
     if matches.get_flag("clear") {
         stack.clear_state_log("clear log")
+    } else if matches.get_flag("compact") {
+        compact(matches, &mut stack)
+    } else if matches.get_flag("interactive") {
+        interactive(matches, stack)
+    } else if matches.get_flag("versions") {
+        let patchnames =
+            if let Some(range_specs) = matches.get_many::<PatchRange>("patchranges-all") {
+                patchrange::resolve_names(&stack, range_specs, RangeConstraint::All)?
+            } else {
+                stack.all_patches().cloned().collect()
+            };
+        show_versions(&stack, &patchnames)
+    } else if matches.get_flag("json") {
+        let patchnames = matches
+            .get_many::<PatchRange>("patchranges-all")
+            .map(|range_specs| patchrange::resolve_names(&stack, range_specs, RangeConstraint::All))
+            .transpose()?;
+        let num_entries = matches.get_one::<usize>("number").copied();
+        show_json(&stack, patchnames.as_deref(), num_entries)
     } else {
         let pathspecs: Option<Vec<String>> =
             if let Some(range_specs) = matches.get_many::<PatchRange>("patchranges-all") {
@@ -138,3 +280,164 @@ fn run(matches: &ArgMatches) -> Result<()> {
         }
     }
 }
+
+fn interactive(matches: &ArgMatches, stack: Stack) -> Result<()> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err(anyhow!("--interactive requires a terminal"));
+    }
+
+    if let Some(action) = log_browser::browse(&stack)? {
+        perform_action(matches, stack, action)?;
+    }
+    Ok(())
+}
+
+/// Perform an action selected in the stack log browser.
+///
+/// The stack is reset to the action's stack state, either entirely or only for the
+/// patch being restored, like `stg reset` does.
+fn perform_action(matches: &ArgMatches, stack: Stack, action: Action) -> Result<()> {
+    let (state_commit_id, patchname) = match action {
+        Action::Reset(state_commit_id) => (state_commit_id, None),
+        Action::RestorePatch(state_commit_id, patchname) => (state_commit_id, Some(patchname)),
+    };
+
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| {
+            let commit = trans.repo().find_commit(state_commit_id)?;
+            let reset_state = StackState::from_commit(trans.repo(), &commit)?;
+            if let Some(patchname) = patchname {
+                trans.reset_to_state_partially(&reset_state, &[patchname])
+            } else {
+                trans.reset_to_state(reset_state)
+            }
+        })
+        .execute("reset")?;
+    Ok(())
+}
+
+fn parse_keep(s: &str) -> Result<usize> {
+    match argset::parse_usize(s)? {
+        0 => Err(anyhow!("at least one stack history entry must be kept")),
+        n => Ok(n),
+    }
+}
+
+fn compact(matches: &ArgMatches, stack: &mut Stack) -> Result<()> {
+    let keep = if let Some(keep) = matches.get_one::<usize>("keep") {
+        *keep
+    } else {
+        let cutoff = matches
+            .get_one::<gix::date::Time>("older-than")
+            .expect("compact-limit group is required");
+        let mut keep = 0;
+        for entry in StateHistory::from_stack(stack)? {
+            let (state_commit, _) = entry?;
+            if state_commit.time()?.seconds < cutoff.seconds {
+                break;
+            }
+            keep += 1;
+        }
+        keep
+    };
+
+    let num_dropped = stack.compact_state_log(keep, "compact log")?;
+    if num_dropped > 0 {
+        print_info_message(
+            matches,
+            &format!(
+                "Dropped {num_dropped} stack history entr{}",
+                if num_dropped == 1 { "y" } else { "ies" }
+            ),
+        );
+    } else {
+        print_info_message(matches, "No stack history entries to drop");
+    }
+
+    for refname in delete_stale_patch_refs(stack.repo)? {
+        print_info_message(matches, &format!("Deleted stale patch ref `{refname}`"));
+    }
+    Ok(())
+}
+
+fn show_json(
+    stack: &Stack,
+    patchnames: Option<&[PatchName]>,
+    num_entries: Option<usize>,
+) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct Entry {
+        commit: String,
+        date: String,
+        summary: String,
+        message: String,
+        head: String,
+        applied: Vec<String>,
+        unapplied: Vec<String>,
+        hidden: Vec<String>,
+    }
+
+    let states = StateHistory::from_stack(stack)?.collect::<Result<Vec<_>>>()?;
+    let mut entries = Vec::new();
+
+    for (i, (state_commit, state)) in states.iter().enumerate() {
+        if num_entries.is_some_and(|n| entries.len() >= n) {
+            break;
+        }
+
+        if let Some(patchnames) = patchnames {
+            // Only entries where one of the patches changed are shown.
+            let patch_commit_id = |state: &StackState, patchname: &PatchName| {
+                state
+                    .has_patch(patchname)
+                    .then(|| state.get_patch_commit_id(patchname))
+            };
+            let prev_state = states.get(i + 1).map(|(_, state)| state);
+            if !patchnames.iter().any(|pn| {
+                patch_commit_id(state, pn)
+                    != prev_state.and_then(|prev_state| patch_commit_id(prev_state, pn))
+            }) {
+                continue;
+            }
+        }
+
+        let commit_ref = state_commit.decode()?;
+        let names =
+            |patchnames: &[PatchName]| patchnames.iter().map(PatchName::to_string).collect();
+        entries.push(Entry {
+            commit: state_commit.id.to_string(),
+            date: commit_ref
+                .committer()
+                .time
+                .format(gix::date::time::format::ISO8601_STRICT),
+            summary: commit_ref.message_summary().to_str_lossy().to_string(),
+            message: commit_ref.message.to_str_lossy().to_string(),
+            head: state.head().id.to_string(),
+            applied: names(state.applied()),
+            unapplied: names(state.unapplied()),
+            hidden: names(state.hidden()),
+        });
+    }
+
+    super::json::print(&entries)
+}
+
+fn show_versions(stack: &Stack, patchnames: &[PatchName]) -> Result<()> {
+    let states = StateHistory::from_stack(stack)?.collect::<Result<Vec<_>>>()?;
+    let mut stdout = std::io::stdout().lock();
+    for patchname in patchnames {
+        let versions = patch_versions(&states, patchname);
+        for (i, commit) in versions.iter().enumerate() {
+            let number = i + 1;
+            let short_id = commit.id().shorten_or_id();
+            let message = commit.message_ex();
+            let message = message.decode()?;
+            let subject = message.lines().next().unwrap_or_default();
+            writeln!(stdout, "{patchname}  v{number}  {short_id}  {subject}")?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,690 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Interactive stack log browser used by `stg log --interactive`.
+//!
+//! The stack state log entries are listed newest first. Opening an entry compares that
+//! stack state with the current stack state and the diff of any patch in the comparison
+//! may then be viewed. The stack may be reset to the selected state, or a single patch
+//! restored from it, with a single keystroke.
+
+use std::{io::Write, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use bstr::ByteSlice;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    terminal,
+};
+
+use crate::{
+    ext::CommitExtended,
+    patch::PatchName,
+    stack::{Stack, StackState, StackStateAccess, StateHistory},
+    stupid::Stupid,
+};
+
+/// Operation selected while browsing the stack log.
+#[derive(Debug, PartialEq)]
+pub(super) enum Action {
+    /// Reset the stack to the stack state with the given state commit id.
+    Reset(gix::ObjectId),
+
+    /// Restore a single patch from the stack state with the given state commit id.
+    RestorePatch(gix::ObjectId, PatchName),
+}
+
+/// Browse the stack's state log in the terminal.
+///
+/// Returns the action selected by the user, if any. The action is not performed by the
+/// browser; the terminal is restored before returning so that the caller may report
+/// the action's outcome as usual.
+pub(super) fn browse(stack: &Stack) -> Result<Option<Action>> {
+    let states = StateHistory::from_stack(stack)?.collect::<Result<Vec<_>>>()?;
+    let mut entries = Vec::with_capacity(states.len());
+    for (state_commit, _) in &states {
+        let commit_ref = state_commit.decode()?;
+        entries.push(Entry {
+            state_commit_id: state_commit.id,
+            summary: format!(
+                "{}  {}  {}",
+                state_commit.id().shorten_or_id(),
+                commit_ref
+                    .committer()
+                    .time
+                    .format(gix::date::time::format::ISO8601),
+                commit_ref.message_summary().to_str_lossy(),
+            ),
+        });
+    }
+
+    let mut browser = Browser::new(entries);
+    let _guard = TerminalGuard::new()?;
+    let mut stdout = std::io::stdout().lock();
+
+    loop {
+        let (width, height) = terminal_size();
+        browser.draw(&mut stdout, width, height)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        match browser.handle_key(key, usize::from(height).saturating_sub(1)) {
+            Step::Continue => {}
+            Step::Quit => return Ok(None),
+            Step::Act(action) => return Ok(Some(action)),
+            Step::OpenState(pos) => {
+                let (state_commit, state) = &states[pos];
+                browser.state = Some(compare_states(stack, state_commit, state, pos)?);
+            }
+            Step::OpenDiff(pos, patchname) => {
+                let (_, state) = &states[pos];
+                browser.diff = Some(patch_diff(stack, state, &patchname)?);
+            }
+        }
+    }
+}
+
+/// Puts the terminal in raw mode using the alternate screen for as long as it lives.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = Self;
+        crossterm::execute!(
+            std::io::stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide
+        )?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        crossterm::execute!(
+            std::io::stdout(),
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        )
+        .ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+/// Get the terminal size, falling back to a conventional size if it is unknown.
+fn terminal_size() -> (u16, u16) {
+    match terminal::size() {
+        Ok((width, height)) if width > 0 && height > 1 => (width, height),
+        _ => (80, 24),
+    }
+}
+
+/// A stack log entry as listed by the browser.
+struct Entry {
+    state_commit_id: gix::ObjectId,
+    summary: String,
+}
+
+/// Comparison of a historical stack state with the current stack state.
+struct StateView {
+    /// Position of the compared stack state in the list of entries.
+    pos: usize,
+    header: Vec<String>,
+    rows: Vec<PatchRow>,
+    cursor: usize,
+}
+
+/// How a patch in a historical stack state compares with the current stack state.
+struct PatchRow {
+    patchname: PatchName,
+
+    /// Whether the patch exists in the historical stack state.
+    in_state: bool,
+
+    /// Status of the patch in the historical state: `+`, `-`, `!`, or blank if absent.
+    status: char,
+
+    change: String,
+}
+
+/// Diff of a single patch.
+struct DiffView {
+    title: String,
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+/// Outcome of handling a keystroke.
+#[derive(Debug, PartialEq)]
+enum Step {
+    Continue,
+    Quit,
+    Act(Action),
+    OpenState(usize),
+    OpenDiff(usize, PatchName),
+}
+
+struct Browser {
+    entries: Vec<Entry>,
+    cursor: usize,
+    top: usize,
+    state: Option<StateView>,
+    diff: Option<DiffView>,
+}
+
+impl Browser {
+    fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            entries,
+            cursor: 0,
+            top: 0,
+            state: None,
+            diff: None,
+        }
+    }
+
+    /// Update the browser according to a keystroke.
+    ///
+    /// The `page` size is the number of lines available to the current view.
+    fn handle_key(&mut self, key: KeyEvent, page: usize) -> Step {
+        if key.kind == KeyEventKind::Release {
+            return Step::Continue;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Step::Quit;
+        }
+
+        if let Some(diff) = self.diff.as_mut() {
+            let max_scroll = diff.lines.len().saturating_sub(page.saturating_sub(1));
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => diff.scroll = diff.scroll.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    diff.scroll = (diff.scroll + 1).min(max_scroll);
+                }
+                KeyCode::PageUp | KeyCode::Char('b') => {
+                    diff.scroll = diff.scroll.saturating_sub(page);
+                }
+                KeyCode::PageDown | KeyCode::Char(' ') => {
+                    diff.scroll = (diff.scroll + page).min(max_scroll);
+                }
+                KeyCode::Left | KeyCode::Esc | KeyCode::Char('h' | 'q') => self.diff = None,
+                _ => {}
+            }
+            Step::Continue
+        } else if let Some(state) = self.state.as_mut() {
+            let state_commit_id = self.entries[state.pos].state_commit_id;
+            let row = state.rows.get(state.cursor);
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => {
+                    state.cursor = state.cursor.saturating_sub(1);
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    state.cursor = (state.cursor + 1).min(state.rows.len().saturating_sub(1));
+                }
+                KeyCode::Enter | KeyCode::Right | KeyCode::Char('l' | 'd') => {
+                    if let Some(row) = row {
+                        return Step::OpenDiff(state.pos, row.patchname.clone());
+                    }
+                }
+                KeyCode::Char('r') => return Step::Act(Action::Reset(state_commit_id)),
+                KeyCode::Char('p') => {
+                    if let Some(row) = row.filter(|row| row.in_state) {
+                        return Step::Act(Action::RestorePatch(
+                            state_commit_id,
+                            row.patchname.clone(),
+                        ));
+                    }
+                }
+                KeyCode::Left | KeyCode::Esc | KeyCode::Char('h' | 'q') => self.state = None,
+                _ => {}
+            }
+            Step::Continue
+        } else {
+            let last = self.entries.len().saturating_sub(1);
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => self.cursor = (self.cursor + 1).min(last),
+                KeyCode::PageUp | KeyCode::Char('b') => {
+                    self.cursor = self.cursor.saturating_sub(page);
+                }
+                KeyCode::PageDown | KeyCode::Char(' ') => {
+                    self.cursor = (self.cursor + page).min(last);
+                }
+                KeyCode::Home | KeyCode::Char('g') => self.cursor = 0,
+                KeyCode::End | KeyCode::Char('G') => self.cursor = last,
+                KeyCode::Enter | KeyCode::Right | KeyCode::Char('l')
+                    if !self.entries.is_empty() =>
+                {
+                    return Step::OpenState(self.cursor);
+                }
+                KeyCode::Char('r') => {
+                    if let Some(entry) = self.entries.get(self.cursor) {
+                        return Step::Act(Action::Reset(entry.state_commit_id));
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Step::Quit,
+                _ => {}
+            }
+            Step::Continue
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write, width: u16, height: u16) -> Result<()> {
+        let width = usize::from(width);
+        let body_height = usize::from(height) - 1;
+        queue!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+
+        let help = if let Some(diff) = self.diff.as_ref() {
+            print_line(out, &diff.title, width, Style::Title)?;
+            for line in diff.lines.iter().skip(diff.scroll).take(body_height - 1) {
+                let style = if line.starts_with('+') {
+                    Style::Added
+                } else if line.starts_with('-') {
+                    Style::Removed
+                } else if line.starts_with("@@") {
+                    Style::Hunk
+                } else {
+                    Style::Plain
+                };
+                print_line(out, line, width, style)?;
+            }
+            "j/k: scroll  space/b: page  q: back"
+        } else if let Some(state) = self.state.as_ref() {
+            for line in &state.header {
+                print_line(out, line, width, Style::Title)?;
+            }
+            let rows_height = body_height.saturating_sub(state.header.len());
+            let name_width = state
+                .rows
+                .iter()
+                .map(|row| row.patchname.len())
+                .max()
+                .unwrap_or_default();
+            let top = (state.cursor + 1).saturating_sub(rows_height);
+            for (i, row) in state.rows.iter().enumerate().skip(top).take(rows_height) {
+                let line = format!(
+                    "{} {:name_width$}  {}",
+                    row.status, row.patchname, row.change
+                );
+                let style = if i == state.cursor {
+                    Style::Selected
+                } else {
+                    Style::Plain
+                };
+                print_line(out, &line, width, style)?;
+            }
+            "j/k: move  enter: diff  r: reset to state  p: restore patch  q: back"
+        } else {
+            if self.cursor < self.top {
+                self.top = self.cursor;
+            } else if self.cursor >= self.top + body_height {
+                self.top = self.cursor + 1 - body_height;
+            }
+            for (i, entry) in self
+                .entries
+                .iter()
+                .enumerate()
+                .skip(self.top)
+                .take(body_height)
+            {
+                let style = if i == self.cursor {
+                    Style::Selected
+                } else {
+                    Style::Plain
+                };
+                print_line(out, &entry.summary, width, style)?;
+            }
+            "j/k: move  enter: show state  r: reset to state  q: quit"
+        };
+
+        queue!(out, cursor::MoveTo(0, height - 1))?;
+        queue!(
+            out,
+            SetAttribute(Attribute::Reverse),
+            Print(truncate(help, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Style {
+    Plain,
+    Title,
+    Selected,
+    Added,
+    Removed,
+    Hunk,
+}
+
+fn print_line(out: &mut impl Write, line: &str, width: usize, style: Style) -> Result<()> {
+    let line = truncate(line, width);
+    match style {
+        Style::Plain => queue!(out, Print(line))?,
+        Style::Title => queue!(
+            out,
+            SetAttribute(Attribute::Bold),
+            Print(line),
+            SetAttribute(Attribute::Reset)
+        )?,
+        Style::Selected => queue!(
+            out,
+            SetAttribute(Attribute::Reverse),
+            Print(format!("{line:width$}")),
+            SetAttribute(Attribute::Reset)
+        )?,
+        Style::Added | Style::Removed | Style::Hunk => {
+            let color = match style {
+                Style::Added => Color::Green,
+                Style::Removed => Color::Red,
+                _ => Color::Cyan,
+            };
+            queue!(out, SetForegroundColor(color), Print(line), ResetColor)?;
+        }
+    }
+    queue!(out, cursor::MoveToNextLine(1))?;
+    Ok(())
+}
+
+/// Truncate line to the given width, expanding tabs such that the width is respected.
+fn truncate(line: &str, width: usize) -> String {
+    line.replace('\t', "        ").chars().take(width).collect()
+}
+
+/// Get patch status character, as used by `stg series`, for a patch in a stack state.
+fn patch_status<'repo>(state: &impl StackStateAccess<'repo>, patchname: &PatchName) -> char {
+    if state.applied().contains(patchname) {
+        '+'
+    } else if state.unapplied().contains(patchname) {
+        '-'
+    } else {
+        '!'
+    }
+}
+
+/// Compare a historical stack state with the current stack state.
+fn compare_states(
+    stack: &Stack,
+    state_commit: &Rc<gix::Commit>,
+    state: &StackState,
+    pos: usize,
+) -> Result<StateView> {
+    let commit_ref = state_commit.decode()?;
+    let mut header = vec![
+        format!(
+            "State {}  {}",
+            state_commit.id().shorten_or_id(),
+            commit_ref
+                .committer()
+                .time
+                .format(gix::date::time::format::ISO8601),
+        ),
+        format!("    {}", commit_ref.message_summary().to_str_lossy()),
+    ];
+    if state.head().id != stack.head().id {
+        header.push(format!(
+            "Branch head {} differs from current head {}",
+            state.head().id().shorten_or_id(),
+            stack.head().id().shorten_or_id(),
+        ));
+    }
+    let common_applied = |a: &[PatchName], b: &[PatchName]| -> Vec<PatchName> {
+        a.iter().filter(|pn| b.contains(pn)).cloned().collect()
+    };
+    if common_applied(state.applied(), stack.applied())
+        != common_applied(stack.applied(), state.applied())
+    {
+        header.push("Applied patches have been reordered since this state".to_string());
+    }
+    header.push(String::new());
+
+    let status_word = |status: char| match status {
+        '+' => "applied",
+        '-' => "unapplied",
+        _ => "hidden",
+    };
+
+    let mut rows = Vec::new();
+    for patchname in state.all_patches() {
+        let status = patch_status(state, patchname);
+        let change = if !stack.has_patch(patchname) {
+            "deleted since".to_string()
+        } else {
+            let mut changes = Vec::new();
+            if stack.get_patch_commit_id(patchname) != state.get_patch_commit_id(patchname) {
+                changes.push("modified since".to_string());
+            }
+            let current_status = patch_status(stack, patchname);
+            if current_status != status {
+                changes.push(format!("now {}", status_word(current_status)));
+            }
+            changes.join(", ")
+        };
+        rows.push(PatchRow {
+            patchname: patchname.clone(),
+            in_state: true,
+            status,
+            change,
+        });
+    }
+    for patchname in stack.all_patches().filter(|pn| !state.has_patch(pn)) {
+        rows.push(PatchRow {
+            patchname: patchname.clone(),
+            in_state: false,
+            status: ' ',
+            change: "not in this state".to_string(),
+        });
+    }
+
+    Ok(StateView {
+        pos,
+        header,
+        rows,
+        cursor: 0,
+    })
+}
+
+/// Get the diff of a patch for display.
+///
+/// A patch that has been modified since the historical stack state is shown as a
+/// range-diff from its current version to its version in the historical state.
+/// Otherwise, the patch's diff in the historical state, or the current stack if the
+/// patch is not in the historical state, is shown.
+fn patch_diff(stack: &Stack, state: &StackState, patchname: &PatchName) -> Result<DiffView> {
+    let stupid = stack.repo.stupid();
+    let range = |commit: &gix::Commit| -> Result<(gix::ObjectId, gix::ObjectId)> {
+        Ok((
+            commit
+                .parent_ids()
+                .next()
+                .ok_or_else(|| anyhow!("patch `{patchname}` commit has no parent"))?
+                .detach(),
+            commit.id,
+        ))
+    };
+
+    let (title, diff) = if state.has_patch(patchname)
+        && stack.has_patch(patchname)
+        && state.get_patch_commit_id(patchname) != stack.get_patch_commit_id(patchname)
+    {
+        (
+            format!("Patch `{patchname}`: current version compared with this state"),
+            stupid.range_diff(
+                range(stack.get_patch_commit(patchname))?,
+                range(state.get_patch_commit(patchname))?,
+                false,
+                std::iter::empty::<&str>(),
+            )?,
+        )
+    } else {
+        let (title, commit) = if state.has_patch(patchname) {
+            (
+                format!("Patch `{patchname}` in this state"),
+                state.get_patch_commit(patchname),
+            )
+        } else {
+            (
+                format!("Patch `{patchname}` in current stack"),
+                stack.get_patch_commit(patchname),
+            )
+        };
+        let parent_tree_id = commit.get_parent_commit()?.tree_id()?.detach();
+        let mut diff = commit.message_raw()?.to_owned();
+        diff.push(b'\n');
+        diff.extend_from_slice(&stupid.diff_tree_patch(
+            parent_tree_id,
+            commit.tree_id()?.detach(),
+            None::<Vec<&str>>,
+            false,
+            ["--stat", "--summary"],
+        )?);
+        (title, diff)
+    };
+
+    Ok(DiffView {
+        title,
+        lines: diff
+            .lines()
+            .map(|line| line.to_str_lossy().to_string())
+            .collect(),
+        scroll: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    use super::{Action, Browser, DiffView, Entry, PatchRow, StateView, Step};
+    use crate::patch::PatchName;
+
+    fn browser(num_entries: u8) -> Browser {
+        Browser::new(
+            (0..num_entries)
+                .map(|i| Entry {
+                    state_commit_id: gix::ObjectId::from_bytes_or_panic(&[i; 20]),
+                    summary: format!("entry {i}"),
+                })
+                .collect(),
+        )
+    }
+
+    fn key(browser: &mut Browser, code: KeyCode) -> Step {
+        browser.handle_key(KeyEvent::from(code), 10)
+    }
+
+    fn state_view(pos: usize) -> StateView {
+        let row = |name: &str, in_state: bool| PatchRow {
+            patchname: PatchName::from_str(name).unwrap(),
+            in_state,
+            status: if in_state { '+' } else { ' ' },
+            change: String::new(),
+        };
+        StateView {
+            pos,
+            header: vec![],
+            rows: vec![row("p0", true), row("p1", false)],
+            cursor: 0,
+        }
+    }
+
+    #[test]
+    fn list_navigation() {
+        let mut browser = browser(3);
+        assert_eq!(key(&mut browser, KeyCode::Up), Step::Continue);
+        assert_eq!(browser.cursor, 0);
+        key(&mut browser, KeyCode::Char('j'));
+        key(&mut browser, KeyCode::Down);
+        key(&mut browser, KeyCode::Down);
+        assert_eq!(browser.cursor, 2);
+        key(&mut browser, KeyCode::Char('g'));
+        assert_eq!(browser.cursor, 0);
+        key(&mut browser, KeyCode::PageDown);
+        assert_eq!(browser.cursor, 2);
+        assert_eq!(key(&mut browser, KeyCode::Enter), Step::OpenState(2));
+        assert_eq!(key(&mut browser, KeyCode::Char('q')), Step::Quit);
+    }
+
+    #[test]
+    fn reset_from_list() {
+        let mut browser = browser(3);
+        key(&mut browser, KeyCode::Char('j'));
+        assert_eq!(
+            key(&mut browser, KeyCode::Char('r')),
+            Step::Act(Action::Reset(gix::ObjectId::from_bytes_or_panic(&[1; 20])))
+        );
+    }
+
+    #[test]
+    fn empty_list() {
+        let mut browser = browser(0);
+        assert_eq!(key(&mut browser, KeyCode::Down), Step::Continue);
+        assert_eq!(key(&mut browser, KeyCode::Enter), Step::Continue);
+        assert_eq!(key(&mut browser, KeyCode::Char('r')), Step::Continue);
+    }
+
+    #[test]
+    fn state_view_actions() {
+        let mut browser = browser(3);
+        browser.state = Some(state_view(1));
+        let state_commit_id = gix::ObjectId::from_bytes_or_panic(&[1; 20]);
+        let p0 = PatchName::from_str("p0").unwrap();
+        let p1 = PatchName::from_str("p1").unwrap();
+
+        assert_eq!(
+            key(&mut browser, KeyCode::Enter),
+            Step::OpenDiff(1, p0.clone())
+        );
+        assert_eq!(
+            key(&mut browser, KeyCode::Char('p')),
+            Step::Act(Action::RestorePatch(state_commit_id, p0))
+        );
+        key(&mut browser, KeyCode::Down);
+        key(&mut browser, KeyCode::Down);
+        assert_eq!(browser.state.as_ref().unwrap().cursor, 1);
+        assert_eq!(key(&mut browser, KeyCode::Char('d')), Step::OpenDiff(1, p1));
+
+        // Patches that are not in the state cannot be restored from it.
+        assert_eq!(key(&mut browser, KeyCode::Char('p')), Step::Continue);
+        assert_eq!(
+            key(&mut browser, KeyCode::Char('r')),
+            Step::Act(Action::Reset(state_commit_id))
+        );
+
+        assert_eq!(key(&mut browser, KeyCode::Char('q')), Step::Continue);
+        assert!(browser.state.is_none());
+    }
+
+    #[test]
+    fn diff_scrolling() {
+        let mut browser = browser(1);
+        browser.state = Some(state_view(0));
+        browser.diff = Some(DiffView {
+            title: String::new(),
+            lines: (0..25).map(|i| i.to_string()).collect(),
+            scroll: 0,
+        });
+        key(&mut browser, KeyCode::Char('k'));
+        assert_eq!(browser.diff.as_ref().unwrap().scroll, 0);
+        key(&mut browser, KeyCode::Char(' '));
+        assert_eq!(browser.diff.as_ref().unwrap().scroll, 10);
+        key(&mut browser, KeyCode::PageDown);
+        key(&mut browser, KeyCode::PageDown);
+        assert_eq!(browser.diff.as_ref().unwrap().scroll, 16);
+        assert_eq!(key(&mut browser, KeyCode::Esc), Step::Continue);
+        assert!(browser.diff.is_none());
+        assert!(browser.state.is_some());
+    }
+}
@@ -0,0 +1,496 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Reconstruct a patch series from a mail thread.
+//!
+//! Mailing list archives commonly interleave multiple versions of a patch series along
+//! with cover letters and replies. The functions in this module use the `[PATCH vN M/T]`
+//! subject tags and the `In-Reply-To` and `References` headers of each message to find
+//! the latest complete version of a series and order its patches, and to collect review
+//! trailers from replies to the patch messages.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use bstr::ByteSlice;
+
+use crate::stupid::StupidContext;
+
+/// Trailers collected from review replies.
+const REVIEW_TRAILERS: [&str; 5] = [
+    "Acked-by",
+    "Reviewed-by",
+    "Tested-by",
+    "Reported-by",
+    "Suggested-by",
+];
+
+/// A patch series reconstructed from a mail thread.
+pub(super) struct Series {
+    /// Version of the series; i.e. `N` from `[PATCH vN M/T]`.
+    pub(super) version: usize,
+
+    /// Path to the cover letter message (i.e. `0/T`), if any.
+    pub(super) cover_letter: Option<PathBuf>,
+
+    /// Paths to the patch messages, ordered `1/T` through `T/T`.
+    pub(super) patches: Vec<PathBuf>,
+
+    /// Newer versions of the series that were skipped because they are incomplete.
+    pub(super) incomplete: Vec<String>,
+}
+
+/// Trailers collected from the replies to one patch message.
+pub(super) struct PatchReview {
+    /// Message id of the reviewed patch message, if known.
+    pub(super) message_id: Option<String>,
+
+    /// Subject of the reviewed patch without `Re:` prefixes or `[PATCH]` tags.
+    pub(super) subject: String,
+
+    /// Collected `(trailer, value)` pairs, in mailbox order and without duplicates.
+    pub(super) trailers: Vec<(String, String)>,
+}
+
+/// Position of a message within a patch series, from its subject tag.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PatchTag {
+    version: usize,
+    number: usize,
+    total: usize,
+}
+
+/// The headers of a message relevant to threading.
+struct Mail {
+    path: PathBuf,
+    subject: String,
+    tag: Option<PatchTag>,
+    message_id: Option<String>,
+    parent: Option<String>,
+    references: Vec<String>,
+}
+
+/// A `[PATCH]` message; i.e. a [`Mail`] with a subject tag.
+struct PatchMail<'a> {
+    mail: &'a Mail,
+    tag: PatchTag,
+}
+
+/// Reconstruct the latest complete patch series from messages split from a mailbox.
+///
+/// The messages must be provided in mailbox order. Replies and messages without a
+/// `[PATCH]` subject tag are ignored. When a message for the same position in the
+/// series appears more than once, the last one is used.
+pub(super) fn reconstruct(paths: &[PathBuf]) -> Result<Series> {
+    let mails = paths
+        .iter()
+        .map(|path| read_mail(path))
+        .collect::<Result<Vec<_>>>()?;
+    let mut versions: BTreeMap<usize, Vec<PatchMail>> = BTreeMap::new();
+    for mail in &mails {
+        if let Some(tag) = mail.tag {
+            versions
+                .entry(tag.version)
+                .or_default()
+                .push(PatchMail { mail, tag });
+        }
+    }
+
+    let mut incomplete = Vec::new();
+    for (version, mails) in versions.iter().rev() {
+        let (cover_letter, patches, total) = thread_members(mails);
+        let missing: Vec<String> = (1..=total)
+            .filter(|number| !patches.contains_key(number))
+            .map(|number| format!("{number}/{total}"))
+            .collect();
+        if missing.is_empty() {
+            return Ok(Series {
+                version: *version,
+                cover_letter,
+                patches: patches.into_values().collect(),
+                incomplete,
+            });
+        }
+        incomplete.push(format!(
+            "v{version} is incomplete: missing {}",
+            missing.join(", ")
+        ));
+    }
+
+    if let Some(latest) = incomplete.first() {
+        Err(anyhow!("patch series {latest}"))
+    } else {
+        Err(anyhow!("no `[PATCH]` messages found"))
+    }
+}
+
+/// Select the messages of one version that belong to the same thread.
+///
+/// The thread is rooted at the last cover letter of the version or, without a cover
+/// letter, the last first patch. Messages are thread members if they reference the root
+/// or another member. All messages are members when the messages are not threaded.
+///
+/// Returns the cover letter path, the member patch paths keyed by their number, and the
+/// number of patches in the series.
+fn thread_members(mails: &[PatchMail]) -> (Option<PathBuf>, BTreeMap<usize, PathBuf>, usize) {
+    let root = mails
+        .iter()
+        .rev()
+        .find(|mail| mail.tag.number == 0)
+        .or_else(|| mails.iter().rev().find(|mail| mail.tag.number == 1))
+        .unwrap_or_else(|| mails.last().expect("versions have at least one message"));
+
+    let is_threaded =
+        root.mail.message_id.is_some() && mails.iter().any(|mail| !mail.mail.references.is_empty());
+    let mut is_member: Vec<bool> = mails
+        .iter()
+        .map(|mail| std::ptr::eq(mail, root) || !is_threaded)
+        .collect();
+    loop {
+        let member_ids: Vec<&str> = mails
+            .iter()
+            .zip(is_member.iter())
+            .filter_map(|(mail, is_member)| mail.mail.message_id.as_deref().filter(|_| *is_member))
+            .collect();
+        let mut changed = false;
+        for (mail, is_member) in mails.iter().zip(is_member.iter_mut()) {
+            if !*is_member
+                && mail
+                    .mail
+                    .references
+                    .iter()
+                    .any(|reference| member_ids.contains(&reference.as_str()))
+            {
+                *is_member = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let total = root.tag.total;
+    let mut cover_letter = None;
+    let mut patches = BTreeMap::new();
+    for (mail, _) in mails.iter().zip(is_member).filter(|(_, member)| *member) {
+        if mail.tag.number == 0 {
+            cover_letter = Some(mail.mail.path.clone());
+        } else if mail.tag.number <= total {
+            patches.insert(mail.tag.number, mail.mail.path.clone());
+        }
+    }
+    (cover_letter, patches, total)
+}
+
+/// Collect review trailers from replies in messages split from a mailbox.
+///
+/// Trailers such as `Reviewed-by:` are collected from the unquoted body text of each
+/// reply. A reply applies to the `[PATCH]` message it (transitively) replies to. Replies
+/// to a cover letter apply to every patch of that series in the mailbox. When the
+/// reviewed patch message is not in the mailbox, the reply's `In-Reply-To` id and
+/// subject identify the patch.
+pub(super) fn collect_trailers(
+    stupid: &StupidContext,
+    paths: &[PathBuf],
+) -> Result<Vec<PatchReview>> {
+    let mails = paths
+        .iter()
+        .map(|path| read_mail(path))
+        .collect::<Result<Vec<_>>>()?;
+    let mut reviews: Vec<PatchReview> = Vec::new();
+
+    for reply in mails.iter().filter(|mail| mail.tag.is_none()) {
+        let (_, body, _) = stupid.mailinfo(Some(std::fs::File::open(&reply.path)?), false)?;
+        let trailers = parse_review_trailers(&body.to_str_lossy());
+        if trailers.is_empty() {
+            continue;
+        }
+        for (message_id, subject) in review_targets(&mails, reply) {
+            let index = if let Some(index) = reviews
+                .iter()
+                .position(|review| review.message_id == message_id && review.subject == subject)
+            {
+                index
+            } else {
+                reviews.push(PatchReview {
+                    message_id,
+                    subject,
+                    trailers: Vec::new(),
+                });
+                reviews.len() - 1
+            };
+            let review = &mut reviews[index];
+            for trailer in &trailers {
+                if !review.trailers.contains(trailer) {
+                    review.trailers.push(trailer.clone());
+                }
+            }
+        }
+    }
+
+    Ok(reviews)
+}
+
+/// Find the message ids and subjects of the patches a reply applies to.
+fn review_targets(mails: &[Mail], reply: &Mail) -> Vec<(Option<String>, String)> {
+    let find_mail = |message_id: &str| {
+        mails
+            .iter()
+            .find(|mail| mail.message_id.as_deref() == Some(message_id))
+    };
+    let target = |mail: &Mail| (mail.message_id.clone(), strip_subject(&mail.subject));
+
+    let mut current = reply;
+    // Bound the walk up the thread in case of reference cycles.
+    for _ in 0..mails.len() {
+        let Some(parent) = current.parent.as_deref().and_then(find_mail) else {
+            break;
+        };
+        match parent.tag {
+            Some(tag) if tag.number == 0 => {
+                return mails
+                    .iter()
+                    .filter(|mail| {
+                        mail.tag.is_some_and(|tag| tag.number > 0)
+                            && parent
+                                .message_id
+                                .as_ref()
+                                .is_some_and(|id| mail.references.contains(id))
+                    })
+                    .map(target)
+                    .collect();
+            }
+            Some(_) => return vec![target(parent)],
+            None => current = parent,
+        }
+    }
+
+    vec![(current.parent.clone(), strip_subject(&current.subject))]
+}
+
+/// Get the review trailers from the unquoted lines of a message body.
+fn parse_review_trailers(body: &str) -> Vec<(String, String)> {
+    let mut trailers = Vec::new();
+    for line in body.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            if let Some(trailer) = REVIEW_TRAILERS
+                .iter()
+                .find(|trailer| trailer.eq_ignore_ascii_case(key))
+            {
+                let trailer = (trailer.to_string(), value.to_string());
+                if !value.is_empty() && !trailers.contains(&trailer) {
+                    trailers.push(trailer);
+                }
+            }
+        }
+    }
+    trailers
+}
+
+/// Remove `Re:` prefixes and `[...]` tags from a subject.
+fn strip_subject(subject: &str) -> String {
+    let mut rest = subject.trim();
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        if lower.starts_with("re:") || lower.starts_with("aw:") {
+            rest = rest[3..].trim_start();
+        } else if let Some((_, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            rest = after.trim_start();
+        } else {
+            return rest.to_string();
+        }
+    }
+}
+
+/// Read the threading headers of a message.
+///
+/// The message's parent is its first `In-Reply-To` id or, failing that, its last
+/// `References` id.
+fn read_mail(path: &Path) -> Result<Mail> {
+    let content = std::fs::read(path)?;
+    let mut subject = String::new();
+    let mut message_id = None;
+    let mut in_reply_to = Vec::new();
+    let mut references = Vec::new();
+
+    for (name, value) in unfolded_headers(&content) {
+        if name.eq_ignore_ascii_case("subject") {
+            subject = value;
+        } else if name.eq_ignore_ascii_case("message-id") {
+            message_id = parse_message_ids(&value).into_iter().next();
+        } else if name.eq_ignore_ascii_case("in-reply-to") {
+            in_reply_to.extend(parse_message_ids(&value));
+        } else if name.eq_ignore_ascii_case("references") {
+            references.extend(parse_message_ids(&value));
+        }
+    }
+
+    let parent = in_reply_to.first().or_else(|| references.last()).cloned();
+    references.extend(in_reply_to);
+
+    Ok(Mail {
+        path: path.to_owned(),
+        tag: parse_subject_tag(&subject),
+        subject,
+        message_id,
+        parent,
+        references,
+    })
+}
+
+/// Get the header fields of a message, with folded lines joined.
+fn unfolded_headers(content: &[u8]) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if line.is_empty() {
+            break;
+        } else if line.starts_with(b" ") || line.starts_with(b"\t") {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.to_str_lossy().trim());
+            }
+        } else if let Some((name, value)) = line.split_once_str(b":") {
+            headers.push((
+                name.to_str_lossy().trim().to_string(),
+                value.to_str_lossy().trim().to_string(),
+            ));
+        }
+    }
+    headers
+}
+
+/// Get the `<...>` message ids from a header value.
+fn parse_message_ids(value: &str) -> Vec<String> {
+    value
+        .split('<')
+        .skip(1)
+        .filter_map(|part| part.split_once('>').map(|(id, _)| id.trim().to_string()))
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// Parse the `[PATCH vN M/T]` tag from a subject.
+///
+/// Returns `None` for replies and subjects without a `PATCH` tag. The version defaults
+/// to 1 and a tag without `M/T` is taken to be `1/1`.
+fn parse_subject_tag(subject: &str) -> Option<PatchTag> {
+    let mut rest = subject.trim_start();
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        if lower.starts_with("re:") || lower.starts_with("aw:") {
+            return None;
+        }
+        let inner;
+        (inner, rest) = rest.strip_prefix('[')?.split_once(']')?;
+        rest = rest.trim_start();
+
+        let tokens = inner.split_whitespace();
+        let mut is_patch = false;
+        let mut tag = PatchTag {
+            version: 1,
+            number: 1,
+            total: 1,
+        };
+        for token in tokens {
+            let upper = token.to_ascii_uppercase();
+            let token = if let Some(version) = upper.strip_prefix("PATCH") {
+                is_patch = true;
+                version
+            } else {
+                upper.as_str()
+            };
+            if let Some(version) = token.strip_prefix('V').and_then(|v| v.parse().ok()) {
+                tag.version = version;
+            } else if let Some((number, total)) = token.split_once('/') {
+                if let (Ok(number), Ok(total)) = (number.parse(), total.parse()) {
+                    tag.number = number;
+                    tag.total = total;
+                }
+            }
+        }
+        if is_patch {
+            return Some(tag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subject_tags() {
+        let tag = |version, number, total| {
+            Some(PatchTag {
+                version,
+                number,
+                total,
+            })
+        };
+        let cases = [
+            ("[PATCH] Fix things", tag(1, 1, 1)),
+            ("[PATCH 2/3] Fix things", tag(1, 2, 3)),
+            ("[PATCH v2 0/3] Cover letter", tag(2, 0, 3)),
+            ("[PATCHv3 1/2] Fix things", tag(3, 1, 2)),
+            ("[RFC PATCH v4 10/12] Fix things", tag(4, 10, 12)),
+            ("[PATCH 3/3 v2] Fix things", tag(2, 3, 3)),
+            ("[mylist] [PATCH v2 1/1] Fix things", tag(2, 1, 1)),
+            ("  [patch 1/2] lowercase", tag(1, 1, 2)),
+            ("Re: [PATCH 2/3] Fix things", None),
+            ("RE: [PATCH 2/3] Fix things", None),
+            ("[mylist] Re: [PATCH 2/3] Fix things", None),
+            ("[ANNOUNCE] Fix things", None),
+            ("Fix things", None),
+        ];
+        for (subject, expected) in cases {
+            assert_eq!(parse_subject_tag(subject), expected, "{subject}");
+        }
+    }
+
+    #[test]
+    fn review_trailers() {
+        let body = "Looks good to me.\n\
+                    \n\
+                    > Reviewed-by: Quoted Person <quoted@example.com>\n\
+                    Reviewed-by: A Reviewer <reviewer@example.com>\n\
+                    tested-by: A Tester <tester@example.com>\n\
+                    Reviewed-by: A Reviewer <reviewer@example.com>\n\
+                    Signed-off-by: Not Collected <nc@example.com>\n\
+                    Acked-by:\n";
+        assert_eq!(
+            parse_review_trailers(body),
+            vec![
+                (
+                    "Reviewed-by".to_string(),
+                    "A Reviewer <reviewer@example.com>".to_string()
+                ),
+                (
+                    "Tested-by".to_string(),
+                    "A Tester <tester@example.com>".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn stripped_subjects() {
+        assert_eq!(strip_subject("Re: [PATCH v2 2/3] Add b"), "Add b");
+        assert_eq!(strip_subject("RE: Re: [list] [PATCH] Add b"), "Add b");
+        assert_eq!(strip_subject("[PATCH 1/2] Add [x] thing"), "Add [x] thing");
+        assert_eq!(strip_subject("Add b"), "Add b");
+    }
+
+    #[test]
+    fn message_ids() {
+        assert_eq!(
+            parse_message_ids("<a@example.com> <b@example.com>"),
+            vec!["a@example.com", "b@example.com"]
+        );
+        assert_eq!(parse_message_ids("<a@example.com>"), vec!["a@example.com"]);
+        assert!(parse_message_ids("").is_empty());
+    }
+}
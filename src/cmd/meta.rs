@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg meta` implementation.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches};
+
+use crate::{
+    argset,
+    branchloc::BranchLocator,
+    color::get_color_stdout,
+    ext::RepositoryExtended,
+    patch::{LocationConstraint, PatchLocator},
+    stack::{InitializationPolicy, PatchStatus, Stack, StackStateAccess},
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "meta",
+    category: super::CommandCategory::PatchManipulation,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Show or edit patch metadata")
+        .long_about(
+            "Show or edit structured metadata associated with a patch.\n\
+             \n\
+             Each patch may have a set of free-form labels, a review status, the \
+             Message-Id of the email the patch was sent or received as, and a \
+             changelog. The metadata is recorded in the stack state and is thus \
+             versioned along with the rest of the stack; it may be inspected with \
+             `stg log <patch>` and is restored by `stg undo` and `stg reset`.\n\
+             \n\
+             Metadata follows its patch when the patch is renamed, refreshed, or \
+             rebased. Picking a patch from a stack carries its metadata to the new \
+             patch and squashing combines the metadata of the squashed patches.\n\
+             \n\
+             Without any editing options, the patch's metadata is shown.",
+        )
+        .arg(
+            Arg::new("patch")
+                .help("Patch to show or edit metadata of")
+                .long_help(
+                    "Patch to show or edit metadata of. The topmost patch is used by \
+                     default.",
+                )
+                .allow_hyphen_values(true)
+                .value_parser(clap::value_parser!(PatchLocator)),
+        )
+        .arg(argset::branch_arg())
+        .next_help_heading("Edit Options")
+        .arg(
+            Arg::new("label")
+                .long("label")
+                .short('l')
+                .help("Add <label> to the patch")
+                .long_help("Add <label> to the patch. May be used multiple times.")
+                .num_args(1)
+                .action(clap::ArgAction::Append)
+                .value_name("label")
+                .value_hint(clap::ValueHint::Other)
+                .value_parser(clap::builder::NonEmptyStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("unlabel")
+                .long("unlabel")
+                .short('L')
+                .help("Remove <label> from the patch")
+                .long_help("Remove <label> from the patch. May be used multiple times.")
+                .num_args(1)
+                .action(clap::ArgAction::Append)
+                .value_name("label")
+                .value_hint(clap::ValueHint::Other)
+                .conflicts_with("clear-labels"),
+        )
+        .arg(
+            Arg::new("clear-labels")
+                .long("clear-labels")
+                .help("Remove all labels from the patch")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("status")
+                .long("status")
+                .short('s')
+                .help("Set review status")
+                .long_help(
+                    "Set the patch's review status to one of \"draft\", \"posted\", or \
+                     \"approved\". Use \"none\" to clear the status.",
+                )
+                .num_args(1)
+                .value_name("status")
+                .value_parser(["draft", "posted", "approved", "none"]),
+        )
+        .arg(
+            Arg::new("message-id")
+                .long("message-id")
+                .short('m')
+                .help("Set the upstream Message-Id")
+                .num_args(1)
+                .value_name("id")
+                .value_hint(clap::ValueHint::Other)
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .conflicts_with("clear-message-id"),
+        )
+        .arg(
+            Arg::new("clear-message-id")
+                .long("clear-message-id")
+                .help("Remove the upstream Message-Id")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("changelog")
+                .long("changelog")
+                .short('c')
+                .help("Append <entry> to the patch's changelog")
+                .long_help(
+                    "Append <entry> to the patch's changelog. May be used multiple \
+                     times.",
+                )
+                .num_args(1)
+                .action(clap::ArgAction::Append)
+                .value_name("entry")
+                .value_hint(clap::ValueHint::Other)
+                .value_parser(clap::builder::NonEmptyStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("clear-changelog")
+                .long("clear-changelog")
+                .help("Remove all changelog entries")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("clear")
+                .long("clear")
+                .help("Remove all metadata from the patch")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "unlabel",
+                    "clear-labels",
+                    "clear-message-id",
+                    "clear-changelog",
+                ]),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::from_branch_locator(
+        &repo,
+        matches.get_one::<BranchLocator>("branch"),
+        InitializationPolicy::AllowUninitialized,
+    )?;
+
+    let patchname = if let Some(loc) = matches.get_one::<PatchLocator>("patch") {
+        loc.resolve_name(&stack)?
+            .constrain(&stack, LocationConstraint::All)?
+    } else if let Some(patchname) = stack.applied().last() {
+        patchname.clone()
+    } else {
+        return Err(super::Error::NoAppliedPatches.into());
+    };
+
+    let old_meta = stack.get_patch_meta(&patchname);
+    let mut meta = if matches.get_flag("clear") {
+        Default::default()
+    } else {
+        old_meta.clone()
+    };
+
+    if matches.get_flag("clear-labels") {
+        meta.labels.clear();
+    }
+    if let Some(labels) = matches.get_many::<String>("unlabel") {
+        for label in labels {
+            meta.labels.remove(label);
+        }
+    }
+    if let Some(labels) = matches.get_many::<String>("label") {
+        meta.labels.extend(labels.cloned());
+    }
+
+    if let Some(status) = argset::get_one_str(matches, "status") {
+        meta.status = if status == "none" {
+            None
+        } else {
+            Some(status.parse::<PatchStatus>()?)
+        };
+    }
+
+    if matches.get_flag("clear-message-id") {
+        meta.message_id = None;
+    }
+    if let Some(message_id) = matches.get_one::<String>("message-id") {
+        meta.message_id = Some(message_id.clone());
+    }
+
+    if matches.get_flag("clear-changelog") {
+        meta.changelog.clear();
+    }
+    if let Some(entries) = matches.get_many::<String>("changelog") {
+        meta.changelog.extend(entries.cloned());
+    }
+
+    let is_edit = matches.ids().any(|id| {
+        !matches!(id.as_str(), "patch" | "branch")
+            && matches.value_source(id.as_str()) == Some(clap::parser::ValueSource::CommandLine)
+    });
+
+    if !is_edit {
+        std::io::stdout().write_all(meta.format().as_bytes())?;
+        return Ok(());
+    }
+
+    if &meta == old_meta {
+        return Ok(());
+    }
+
+    stack
+        .setup_transaction()
+        .allow_conflicts(true)
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| {
+            trans.update_patch_meta(&patchname, meta);
+            Ok(())
+        })
+        .execute(&format!("meta {patchname}"))?;
+
+    Ok(())
+}
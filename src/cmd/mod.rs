@@ -7,14 +7,18 @@
 
 use clap::builder::StyledStr;
 
+pub(crate) mod absorb;
+pub(crate) mod bisect;
 pub(crate) mod branch;
 pub(crate) mod clean;
 pub(crate) mod commit;
 pub(crate) mod completion;
 pub(crate) mod delete;
+pub(crate) mod deps;
 pub(crate) mod diff;
 pub(crate) mod edit;
 pub(crate) mod email;
+pub(crate) mod exec;
 pub(crate) mod export;
 pub(crate) mod files;
 pub(crate) mod float;
@@ -24,7 +28,11 @@ pub(crate) mod hide;
 pub(crate) mod id;
 pub(crate) mod import;
 pub(crate) mod init;
+mod json;
 pub(crate) mod log;
+mod log_browser;
+mod mailthread;
+pub(crate) mod meta;
 pub(crate) mod name;
 pub(crate) mod new;
 pub(crate) mod next;
@@ -32,19 +40,25 @@ pub(crate) mod patches;
 pub(crate) mod pick;
 pub(crate) mod pop;
 pub(crate) mod prev;
+pub(crate) mod publish;
 pub(crate) mod pull;
 pub(crate) mod push;
+pub(crate) mod range_diff;
 pub(crate) mod rebase;
 pub(crate) mod redo;
 pub(crate) mod refresh;
 pub(crate) mod rename;
 pub(crate) mod repair;
 pub(crate) mod reset;
+mod sequence;
 pub(crate) mod series;
+pub(crate) mod series_version;
 pub(crate) mod show;
 pub(crate) mod sink;
 pub(crate) mod spill;
+pub(crate) mod split;
 pub(crate) mod squash;
+pub(crate) mod stack;
 pub(crate) mod sync;
 pub(crate) mod top;
 pub(crate) mod uncommit;
@@ -84,14 +98,18 @@ pub(crate) struct StGitCommand {
 /// This is used in [`crate::main`] for command line argument parsing and eventual
 /// dispatch of a subcommand.
 pub(crate) const STGIT_COMMANDS: &[StGitCommand] = &[
+    absorb::STGIT_COMMAND,
+    bisect::STGIT_COMMAND,
     branch::STGIT_COMMAND,
     clean::STGIT_COMMAND,
     commit::STGIT_COMMAND,
     completion::STGIT_COMMAND,
     delete::STGIT_COMMAND,
+    deps::STGIT_COMMAND,
     diff::STGIT_COMMAND,
     edit::STGIT_COMMAND,
     email::STGIT_COMMAND,
+    exec::STGIT_COMMAND,
     export::STGIT_COMMAND,
     files::STGIT_COMMAND,
     float::STGIT_COMMAND,
@@ -102,6 +120,7 @@ pub(crate) const STGIT_COMMANDS: &[StGitCommand] = &[
     import::STGIT_COMMAND,
     init::STGIT_COMMAND,
     log::STGIT_COMMAND,
+    meta::STGIT_COMMAND,
     name::STGIT_COMMAND,
     new::STGIT_COMMAND,
     next::STGIT_COMMAND,
@@ -110,7 +129,9 @@ pub(crate) const STGIT_COMMANDS: &[StGitCommand] = &[
     pop::STGIT_COMMAND,
     prev::STGIT_COMMAND,
     pull::STGIT_COMMAND,
+    publish::STGIT_COMMAND,
     push::STGIT_COMMAND,
+    range_diff::STGIT_COMMAND,
     rebase::STGIT_COMMAND,
     redo::STGIT_COMMAND,
     refresh::STGIT_COMMAND,
@@ -118,10 +139,13 @@ pub(crate) const STGIT_COMMANDS: &[StGitCommand] = &[
     repair::STGIT_COMMAND,
     reset::STGIT_COMMAND,
     series::STGIT_COMMAND,
+    series_version::STGIT_COMMAND,
     show::STGIT_COMMAND,
     sink::STGIT_COMMAND,
     spill::STGIT_COMMAND,
+    split::STGIT_COMMAND,
     squash::STGIT_COMMAND,
+    stack::STGIT_COMMAND,
     sync::STGIT_COMMAND,
     top::STGIT_COMMAND,
     uncommit::STGIT_COMMAND,
@@ -51,6 +51,7 @@ fn make() -> clap::Command {
                 .action(clap::ArgAction::SetTrue),
         )
         .arg(argset::diff_opts_arg())
+        .arg(argset::json_arg().conflicts_with("diff"))
 }
 
 fn run(matches: &ArgMatches) -> Result<()> {
@@ -125,6 +126,14 @@ fn run(matches: &ArgMatches) -> Result<()> {
                 stdout.write_all(&diff)?;
             }
         }
+    } else if matches.get_flag("json") {
+        let patches = stack
+            .applied()
+            .iter()
+            .filter(|pn| revs.contains(&stack.get_patch_commit_id(pn)))
+            .map(|pn| super::json::Patch::new(&stack, pn))
+            .collect::<Result<Vec<_>>>()?;
+        super::json::print(&patches)?;
     } else {
         for patchname in stack.applied() {
             let patch_commit = stack.get_patch_commit(patchname);
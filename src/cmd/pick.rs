@@ -20,7 +20,7 @@ use crate::{
     patch::{
         revspec, PatchName, RangeConstraint, RangeRevisionSpec, SingleRevisionSpec, StGitRevision,
     },
-    stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess},
+    stack::{InitializationPolicy, PatchMeta, Stack, StackAccess, StackStateAccess},
     stupid::Stupid,
 };
 
@@ -32,7 +32,7 @@ pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
 };
 
 fn make() -> clap::Command {
-    clap::Command::new(STGIT_COMMAND.name)
+    let app = clap::Command::new(STGIT_COMMAND.name)
         .about("Import a patch from another branch or a commit object")
         .long_about(
             "Import one or more patches from another branch or commit object into the \
@@ -47,7 +47,12 @@ fn make() -> clap::Command {
              option is a format string as may be supplied to the '--pretty' option of \
              'git show'. The default is \"format:%B%n(imported from commit %H)\", \
              which appends the commit hash of the picked commit to the patch's commit \
-             message.",
+             message.\n\
+             \n\
+             When picking several patches stops due to conflicts, resolve the \
+             conflicts and use '--continue' to push the remaining picked patches. \
+             Use '--skip' to delete the conflicting picked patch or '--abort' to \
+             restore the stack to its state before the pick.",
         )
         .override_usage(super::make_usage(
             "stg pick",
@@ -56,13 +61,14 @@ fn make() -> clap::Command {
                 "[OPTIONS] [--name NAME] [--parent COMMITTISH] <source>",
                 "[OPTIONS] --fold [--file PATH]... <source>...",
                 "[OPTIONS] --update <source>...",
+                "--continue | --skip | --abort",
             ],
         ))
         .arg(
             Arg::new("stgit-revision")
                 .help("Patch name or committish to import")
                 .value_name("source")
-                .required(true)
+                .required_unless_present("resume")
                 .num_args(1..)
                 .value_parser(clap::value_parser!(RangeRevisionSpec)),
         )
@@ -138,10 +144,15 @@ fn make() -> clap::Command {
                 .action(clap::ArgAction::Append)
                 .value_name("path")
                 .requires("fold"),
-        )
+        );
+    super::sequence::add_args(app)
 }
 
 fn run(matches: &clap::ArgMatches) -> Result<()> {
+    if let Some(resume) = super::sequence::get_resume(matches) {
+        return super::sequence::resume(matches, STGIT_COMMAND.name, resume);
+    }
+
     let repo = gix::Repository::open()?;
     let stack = Stack::current(&repo, InitializationPolicy::AutoInitialize)?;
     let ref_stack = Stack::from_branch_locator(
@@ -191,7 +202,7 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
                     .map(|rev| rev.commit)
             })
             .transpose()?;
-        pick_picks(stack, matches, opt_parent, &picks)
+        pick_picks(stack, &ref_stack, matches, opt_parent, &picks)
     }
 }
 
@@ -251,6 +262,7 @@ fn fold_picks(stack: &Stack, matches: &clap::ArgMatches, picks: &[StGitRevision]
 
 fn pick_picks(
     stack: Stack,
+    ref_stack: &Stack,
     matches: &clap::ArgMatches,
     opt_parent: Option<Rc<gix::Commit>>,
     picks: &[StGitRevision],
@@ -258,10 +270,24 @@ fn pick_picks(
     let stupid = stack.repo.stupid();
     let config = stack.repo.config_snapshot();
     let patchname_len_limit = PatchName::get_length_limit(&config);
-    let mut new_patches: Vec<(PatchName, gix::ObjectId)> = Vec::with_capacity(picks.len());
+    let mut new_patches: Vec<(PatchName, gix::ObjectId, PatchMeta)> =
+        Vec::with_capacity(picks.len());
 
     for StGitRevision { patchname, commit } in picks {
         let commit_ref = commit.decode()?;
+
+        // Metadata follows a patch picked from a stack, but not reverts.
+        let meta = match patchname {
+            Some(pn)
+                if !matches.get_flag("revert")
+                    && ref_stack.has_patch(pn)
+                    && ref_stack.get_patch_commit_id(pn) == commit.id =>
+            {
+                ref_stack.get_patch_meta(pn).clone()
+            }
+            _ => PatchMeta::default(),
+        };
+
         let mut disallow: Vec<&PatchName> = stack.all_patches().collect();
 
         let patchname = if let Some(name) = matches.get_one::<PatchName>("name") {
@@ -273,11 +299,13 @@ fn pick_picks(
                 patchname.clone()
             }
         } else {
-            PatchName::make(
+            PatchName::generate(
+                stack.repo,
                 &commit_ref.message.to_str_lossy(),
                 false,
                 patchname_len_limit,
-            )
+                &disallow,
+            )?
         }
         .uniquify(&[], &disallow);
 
@@ -338,18 +366,26 @@ fn pick_picks(
             top.tree_id()?.detach(),
             [bottom.id],
         )?;
-        new_patches.push((patchname, new_commit_id));
+        new_patches.push((patchname, new_commit_id, meta));
         disallow.push(&new_patches[new_patches.len() - 1].0);
     }
 
+    let repo = stack.repo;
+    let sequence = super::sequence::begin(&stack, STGIT_COMMAND.name)?;
+    let pushing: Vec<(PatchName, Option<gix::ObjectId>)> = new_patches
+        .iter()
+        .map(|(patchname, _, _)| (patchname.clone(), None))
+        .collect();
+
     stack
         .setup_transaction()
         .with_output_stream(get_color_stdout(matches))
         .use_index_and_worktree(true)
         .transact(|trans| {
             let mut to_push = Vec::new();
-            for (i, (patchname, commit_id)) in new_patches.iter().enumerate() {
+            for (i, (patchname, commit_id, meta)) in new_patches.iter().enumerate() {
                 trans.new_unapplied(patchname, *commit_id, i)?;
+                trans.update_patch_meta(patchname, meta.clone());
                 to_push.push(patchname);
             }
             if !matches.get_flag("noapply") {
@@ -357,6 +393,10 @@ fn pick_picks(
             }
             Ok(())
         })
-        .execute("pick")?;
+        .execute("pick")
+        .or_else(|e| {
+            super::sequence::record_conflict(repo, matches, sequence, &pushing, [], &e)?;
+            Err(e)
+        })?;
     Ok(())
 }
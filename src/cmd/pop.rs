@@ -12,7 +12,7 @@ use crate::{
     color::get_color_stdout,
     ext::RepositoryExtended,
     patch::{patchrange, PatchName, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stack::{DependencyGraph, InitializationPolicy, Stack, StackStateAccess},
     stupid::Stupid,
 };
 
@@ -85,6 +85,11 @@ fn make() -> clap::Command {
                 .help("Keep patches' modifications in index and worktree after popping")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(argset::with_deps_arg().long_help(
+            "Also pop the applied patches that depend on the popped patches. \
+             See `stg deps`.",
+        ))
+        .arg(argset::ignore_deps_arg())
         .arg(argset::keep_arg())
 }
 
@@ -143,6 +148,27 @@ fn run(matches: &ArgMatches) -> Result<()> {
 
     assert!(!patches.is_empty());
 
+    // Popping only the topmost patches cannot leave any dependent patches applied.
+    let start = stack
+        .applied()
+        .iter()
+        .position(|pn| patches.contains(pn))
+        .expect("patches are applied");
+    let graph = if matches.get_flag("ignore-deps") || stack.applied().len() - start == patches.len()
+    {
+        None
+    } else {
+        Some(DependencyGraph::new(
+            &stack,
+            stack.applied()[start..].to_vec(),
+        )?)
+    };
+
+    if let Some(graph) = graph.as_ref().filter(|_| matches.get_flag("with-deps")) {
+        let seeds: Vec<PatchName> = patches.iter().cloned().collect();
+        patches.extend(graph.expand(&seeds, DependencyGraph::dependents));
+    }
+
     let keep_flag = matches.get_flag("keep");
     let spill_flag = matches.get_flag("spill");
     repo.check_repository_state()?;
@@ -187,6 +213,10 @@ fn run(matches: &ArgMatches) -> Result<()> {
         .iter()
         .for_each(|pn| new_unapplied.push(pn.clone()));
 
+    if let Some(graph) = &graph {
+        graph.check(&new_applied)?;
+    }
+
     stack
         .setup_transaction()
         .use_index_and_worktree(!spill_flag)
@@ -0,0 +1,290 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg publish` implementation.
+
+use std::{io::Write, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches};
+
+use crate::{
+    ext::{CommitExtended, RepositoryExtended},
+    patch::PatchName,
+    print_info_message,
+    stack::{
+        published_refname_prefix, InitializationPolicy, Stack, StackAccess, StackState,
+        StackStateAccess,
+    },
+    stupid::Stupid,
+    wrap::{Message, PartialRefName},
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "publish",
+    category: super::CommandCategory::StackManipulation,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Publish the stack to a non-rewinding branch")
+        .long_about(
+            "Publish the applied patches of the current stack to a separate branch \
+             whose history is never rewritten. This allows others to merge or pull \
+             from the published branch even though the stack's patches are \
+             modified, reordered, and rebased over time. The published branch is \
+             named '<branch>.public' unless a branch name is given.\n\
+             \n\
+             The first time the stack is published, the published branch is created \
+             at the topmost applied patch. Afterwards, the published branch is \
+             updated with new commits such that its tree matches the topmost applied \
+             patch:\n\
+             \n\
+             - If the stack has been rebased, a merge commit with the new stack base \
+             is added.\n\
+             \n\
+             - Applied patches following the last published patch that apply \
+             directly on top of the published branch are added as individual \
+             commits with their original author and message.\n\
+             \n\
+             - Any remaining changes, e.g. from modified patches, are added as a \
+             single update commit.\n\
+             \n\
+             The stack state is recorded each time the stack is published. Publishing \
+             is refused if the applied patches have not changed since the last time \
+             they were published.",
+        )
+        .arg(
+            Arg::new("branch")
+                .help("Branch to publish to")
+                .value_name("branch")
+                .value_parser(clap::value_parser!(PartialRefName)),
+        )
+        .arg(
+            Arg::new("message")
+                .long("message")
+                .short('m')
+                .help("Use <message> for the update commit")
+                .value_name("message")
+                .num_args(1)
+                .value_hint(clap::ValueHint::Other),
+        )
+        .arg(
+            Arg::new("overwrite")
+                .long("overwrite")
+                .help("Reset the published branch to the topmost applied patch")
+                .long_help(
+                    "Reset the published branch to the topmost applied patch instead \
+                     of adding commits to it. This rewrites the published branch's \
+                     history.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("unpublished")
+                .long("unpublished")
+                .short('u')
+                .help("Show applied patches that have not been published")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["message", "overwrite"]),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::current(&repo, InitializationPolicy::RequireInitialized)?;
+    stack.check_head_top_mismatch()?;
+
+    let branch_name = stack.get_branch_name();
+    let public_name = if let Some(public_name) = matches.get_one::<PartialRefName>("branch") {
+        public_name.clone()
+    } else {
+        PartialRefName::from_str(&format!("{branch_name}.public"))?
+    };
+    if public_name.as_ref() == branch_name {
+        return Err(anyhow!("cannot publish to the current branch"));
+    }
+    let public_refname = gix::refs::FullName::try_from(format!("refs/heads/{public_name}"))?;
+    let record_refname = published_refname(branch_name, public_name.as_ref());
+
+    let public_head = repo
+        .try_find_reference(public_refname.as_ref())?
+        .map(|mut reference| reference.peel_to_id_in_place())
+        .transpose()?
+        .map(|id| repo.find_commit(id))
+        .transpose()?;
+
+    let top = stack.top();
+
+    if matches.get_flag("unpublished") {
+        let unpublished = if let Some(public_head) = public_head.as_ref() {
+            let public_tree_id = if is_rebased(&stack, public_head)? {
+                stack.base().tree_id()?.detach()
+            } else {
+                public_head.tree_id()?.detach()
+            };
+            unpublished_patches(&stack, public_tree_id)?.0
+        } else {
+            stack.applied().to_vec()
+        };
+        let mut stdout = std::io::stdout().lock();
+        for patchname in unpublished {
+            writeln!(stdout, "{patchname}")?;
+        }
+        return Ok(());
+    }
+
+    if let Some(worktree_path) = repo.get_other_worktree_for_branch(public_refname.as_ref())? {
+        return Err(anyhow!(
+            "branch `{public_name}` is checked out in worktree `{}`",
+            worktree_path.display()
+        ));
+    }
+
+    let last_published_top = repo
+        .try_find_reference(record_refname.as_str())?
+        .map(|mut reference| -> Result<gix::ObjectId> {
+            let commit = repo.find_commit(reference.peel_to_id_in_place()?)?;
+            Ok(StackState::from_commit(&repo, &commit)?.top().id)
+        })
+        .transpose()?;
+
+    let new_public_id = match public_head {
+        Some(public_head) if !matches.get_flag("overwrite") => {
+            if last_published_top == Some(top.id) || public_head.tree_id()? == top.tree_id()? {
+                return Err(anyhow!(
+                    "nothing to publish; `{public_name}` is up to date with the stack"
+                ));
+            }
+            let committer = repo.get_committer()?;
+            let author = repo.get_author()?;
+
+            let mut public_id = public_head.id;
+            if is_rebased(&stack, &public_head)? {
+                let base = stack.base();
+                public_id = repo.commit_ex(
+                    author,
+                    committer,
+                    &Message::from(format!("Merge {} into published stack\n", base.id)),
+                    base.tree_id()?.detach(),
+                    [public_id, base.id],
+                )?;
+            }
+            let public_tree_id = repo.find_commit(public_id)?.tree_id()?.detach();
+            let (unpublished, publishable) = unpublished_patches(&stack, public_tree_id)?;
+            for patchname in &unpublished[..publishable] {
+                let commit = stack.get_patch_commit(patchname);
+                public_id = repo.commit_ex(
+                    &commit.author_strict()?,
+                    committer,
+                    &commit.message_ex(),
+                    commit.tree_id()?.detach(),
+                    [public_id],
+                )?;
+                print_info_message(matches, &format!("Published patch `{patchname}`"));
+            }
+
+            if repo.find_commit(public_id)?.tree_id()? != top.tree_id()? {
+                let message = if let Some(message) = matches.get_one::<String>("message") {
+                    Message::from(message.as_str())
+                } else {
+                    Message::from(format!("Update from stack `{branch_name}`\n"))
+                };
+                public_id = repo.commit_ex(
+                    author,
+                    committer,
+                    &message,
+                    top.tree_id()?.detach(),
+                    [public_id],
+                )?;
+            }
+            public_id
+        }
+        _ => top.id,
+    };
+
+    repo.edit_reference(gix::refs::transaction::RefEdit {
+        change: gix::refs::transaction::Change::Update {
+            log: gix::refs::transaction::LogChange {
+                mode: gix::refs::transaction::RefLog::AndReference,
+                force_create_reflog: false,
+                message: format!("publish: from stack {branch_name}").into(),
+            },
+            expected: gix::refs::transaction::PreviousValue::Any,
+            new: gix::refs::Target::Object(new_public_id),
+        },
+        name: public_refname,
+        deref: false,
+    })?;
+
+    let state_id = repo
+        .find_reference(stack.get_stack_refname())?
+        .peel_to_id_in_place()?
+        .detach();
+    repo.reference(
+        record_refname.as_str(),
+        state_id,
+        gix::refs::transaction::PreviousValue::Any,
+        format!("publish: to {public_name}"),
+    )?;
+    print_info_message(matches, &format!("Published stack to `{public_name}`"));
+    Ok(())
+}
+
+/// Get the name of the reference recording the stack state last published to a branch.
+fn published_refname(branch_name: &str, public_name: &str) -> String {
+    format!("{}{public_name}", published_refname_prefix(branch_name))
+}
+
+/// Determine whether the stack base is missing from the published branch's history.
+///
+/// This is the case when the stack has been rebased since it was last published, in
+/// which case the new base needs to be merged into the published branch.
+fn is_rebased(stack: &Stack, public_head: &gix::Commit) -> Result<bool> {
+    let base_id = stack.base().id;
+    Ok(!stack
+        .repo
+        .stupid()
+        .merge_bases(base_id, public_head.id)?
+        .contains(&base_id))
+}
+
+/// Find the applied patches following the last published patch.
+///
+/// The last published patch is the topmost applied patch whose tree matches the tree of
+/// the published branch, `public_tree_id`. Returns the unpublished patches along with
+/// the number of those patches that apply directly, one after another, on top of the
+/// published branch.
+fn unpublished_patches(
+    stack: &Stack,
+    public_tree_id: gix::ObjectId,
+) -> Result<(Vec<PatchName>, usize)> {
+    let mut first_unpublished = None;
+    for (i, patchname) in stack.applied().iter().enumerate().rev() {
+        if stack.get_patch_commit(patchname).tree_id()? == public_tree_id {
+            first_unpublished = Some(i + 1);
+            break;
+        }
+    }
+    let first_unpublished = if let Some(i) = first_unpublished {
+        i
+    } else if stack.base().tree_id()? == public_tree_id {
+        0
+    } else {
+        return Ok((stack.applied().to_vec(), 0));
+    };
+
+    let unpublished = stack.applied()[first_unpublished..].to_vec();
+    let mut tree_id = public_tree_id;
+    let mut publishable = 0;
+    for patchname in &unpublished {
+        let commit = stack.get_patch_commit(patchname);
+        if commit.get_parent_commit()?.tree_id()? != tree_id {
+            break;
+        }
+        tree_id = commit.tree_id()?.detach();
+        publishable += 1;
+    }
+    Ok((unpublished, publishable))
+}
@@ -10,7 +10,7 @@ use crate::{
     color::get_color_stdout,
     ext::RepositoryExtended,
     patch::{patchrange, PatchName, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stack::{InitializationPolicy, Stack, StackStateAccess, Step},
     stupid::Stupid,
 };
 
@@ -22,7 +22,7 @@ pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
 };
 
 fn make() -> clap::Command {
-    clap::Command::new(STGIT_COMMAND.name)
+    let app = clap::Command::new(STGIT_COMMAND.name)
         .about("Push (apply) one or more unapplied patches")
         .long_about(
             "Push one or more unapplied patches from the series onto the stack.\n\
@@ -34,7 +34,13 @@ fn make() -> clap::Command {
              while pushing a patch, the conflicts are written to the work tree \
              and the push command halts. Conflicts may then be resolved using \
              the normal Git methods, or alternatively the push may be undone \
-             using 'stg undo'.",
+             using 'stg undo'.\n\
+             \n\
+             When pushing several patches stops due to conflicts, the remaining \
+             patches may be pushed with '--continue' once the conflicts are \
+             resolved. Alternatively, '--skip' leaves the conflicting patch \
+             unapplied and pushes the remaining patches, and '--abort' restores the \
+             stack to its state before the push.",
         )
         .override_usage(super::make_usage(
             "stg push",
@@ -42,6 +48,7 @@ fn make() -> clap::Command {
                 "[OPTIONS] [patch]...",
                 "[OPTIONS] -n <number>",
                 "[OPTIONS] --all",
+                "--continue | --skip | --abort",
             ],
         ))
         .arg(
@@ -113,10 +120,15 @@ fn make() -> clap::Command {
         .arg(argset::keep_arg())
         .arg(argset::merged_arg())
         .arg(argset::committer_date_is_author_date_arg())
-        .arg(argset::push_conflicts_arg())
+        .arg(argset::push_conflicts_arg());
+    super::sequence::add_args(app)
 }
 
 fn run(matches: &ArgMatches) -> Result<()> {
+    if let Some(resume) = super::sequence::get_resume(matches) {
+        return super::sequence::resume(matches, STGIT_COMMAND.name, resume);
+    }
+
     let repo = gix::Repository::open()?;
     let stack = Stack::current(&repo, InitializationPolicy::AllowUninitialized)?;
     let stupid = repo.stupid();
@@ -187,34 +199,39 @@ fn run(matches: &ArgMatches) -> Result<()> {
         patches.reverse();
     }
 
-    stack
-        .setup_transaction()
-        .use_index_and_worktree(true)
-        .allow_push_conflicts(allow_push_conflicts)
-        .committer_date_is_author_date(matches.get_flag("committer-date-is-author-date"))
-        .with_output_stream(get_color_stdout(matches))
-        .transact(|trans| {
-            if settree_flag {
-                for (i, patchname) in patches.iter().enumerate() {
-                    let is_last = i + 1 == patches.len();
-                    trans.push_tree(patchname, is_last)?;
+    if settree_flag || noapply_flag {
+        stack
+            .setup_transaction()
+            .use_index_and_worktree(true)
+            .committer_date_is_author_date(matches.get_flag("committer-date-is-author-date"))
+            .with_output_stream(get_color_stdout(matches))
+            .transact(|trans| {
+                if settree_flag {
+                    for (i, patchname) in patches.iter().enumerate() {
+                        let is_last = i + 1 == patches.len();
+                        trans.push_tree(patchname, is_last)?;
+                    }
+                    Ok(())
+                } else {
+                    let mut unapplied = patches.clone();
+                    unapplied.extend(
+                        trans
+                            .unapplied()
+                            .iter()
+                            .filter(|pn| !patches.contains(pn))
+                            .cloned(),
+                    );
+                    trans.reorder_patches(None, Some(&unapplied), None)
                 }
-                Ok(())
-            } else if noapply_flag {
-                let mut unapplied = patches.clone();
-                unapplied.extend(
-                    trans
-                        .unapplied()
-                        .iter()
-                        .filter(|pn| !patches.contains(pn))
-                        .cloned(),
-                );
-                trans.reorder_patches(None, Some(&unapplied), None)
-            } else {
-                trans.push_patches(&patches, merged_flag)
-            }
-        })
-        .execute("push")?;
-
-    Ok(())
+            })
+            .execute("push")?;
+        Ok(())
+    } else {
+        let mut sequence = super::sequence::begin(&stack, STGIT_COMMAND.name)?;
+        sequence.check_merged = merged_flag;
+        sequence.allow_push_conflicts = allow_push_conflicts;
+        sequence.committer_date_is_author_date = matches.get_flag("committer-date-is-author-date");
+        sequence.todo = patches.into_iter().map(Step::Push).collect();
+        super::sequence::run(stack, matches, sequence)
+    }
 }
@@ -0,0 +1,297 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg range-diff` implementation.
+
+use std::{io::Write, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use clap::{Arg, ArgMatches};
+
+use crate::{
+    argset,
+    branchloc::BranchLocator,
+    color::use_color,
+    ext::RepositoryExtended,
+    patch::PatchName,
+    stack::{
+        patch_versions, InitializationPolicy, Stack, StackAccess, StackState, StackStateAccess,
+        StateHistory,
+    },
+    stupid::Stupid,
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "range-diff",
+    category: super::CommandCategory::PatchInspection,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Compare versions of patches")
+        .long_about(
+            "Compare versions of patches recorded in the stack history.\n\
+             \n\
+             Each time a patch's commit changes, e.g. due to a refresh, edit, or \
+             rebase, a new version of the patch is recorded in the stack log. The \
+             versions of a patch may be listed with 'stg log --versions'. By default, \
+             the two most recent versions of each given patch are compared. The \
+             '--v1' and '--v2' options select other versions to compare.\n\
+             \n\
+             Alternatively, the '--from' and '--to' options compare the whole series \
+             between two stack states from the stack log. Patches are matched by name; \
+             patches only present in one of the states are reported as added or \
+             removed.\n\
+             \n\
+             The comparison of each patch is performed by git-range-diff(1).",
+        )
+        .override_usage(super::make_usage(
+            "stg range-diff",
+            &[
+                "[OPTIONS] [--v1 <n>] [--v2 <n>] [patch]...",
+                "[OPTIONS] --from <state> [--to <state>] [patch]...",
+            ],
+        ))
+        .arg(
+            Arg::new("patches")
+                .help("Patches to compare")
+                .long_help(
+                    "Patches to compare. When comparing versions, the topmost patch is \
+                     used by default. When comparing stack states, all patches in either \
+                     state are compared by default.",
+                )
+                .value_name("patch")
+                .num_args(1..)
+                .value_parser(clap::value_parser!(PatchName)),
+        )
+        .arg(argset::branch_arg())
+        .arg(
+            Arg::new("v1")
+                .long("v1")
+                .help("Older version number to compare")
+                .long_help(
+                    "Older version number to compare. Defaults to the version preceding \
+                     the newer version.",
+                )
+                .value_name("n")
+                .value_parser(argset::parse_usize),
+        )
+        .arg(
+            Arg::new("v2")
+                .long("v2")
+                .help("Newer version number to compare")
+                .long_help("Newer version number to compare. Defaults to the latest version.")
+                .value_name("n")
+                .value_parser(argset::parse_usize),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .help("Compare series from stack state <state>")
+                .long_help(
+                    "Compare the series as of stack state <state>. The state is specified \
+                     with a commit id from the stack log, which may be viewed with \
+                     'stg log'.",
+                )
+                .value_name("state")
+                .value_hint(clap::ValueHint::Other)
+                .conflicts_with_all(["v1", "v2"]),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .help("Compare series to stack state <state>")
+                .long_help(
+                    "Compare the series to stack state <state>. Defaults to the current \
+                     stack state.",
+                )
+                .value_name("state")
+                .value_hint(clap::ValueHint::Other)
+                .requires("from"),
+        )
+        .arg(argset::diff_opts_arg())
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::from_branch_locator(
+        &repo,
+        matches.get_one::<BranchLocator>("branch"),
+        InitializationPolicy::RequireInitialized,
+    )?;
+    let config = repo.config_snapshot();
+    let diff_opts = argset::get_diff_opts(matches, &config, false, false);
+    let patchnames: Option<Vec<PatchName>> = matches
+        .get_many::<PatchName>("patches")
+        .map(|pns| pns.cloned().collect());
+
+    let mut output = Vec::new();
+
+    if let Some(from) = argset::get_one_str(matches, "from") {
+        let old_state = resolve_state(&repo, from)?;
+        let new_state = if let Some(to) = argset::get_one_str(matches, "to") {
+            resolve_state(&repo, to)?
+        } else {
+            let state_commit = repo
+                .find_reference(stack.get_stack_refname())?
+                .peel_to_commit()?;
+            StackState::from_commit(&repo, &state_commit)?
+        };
+
+        compare_states(
+            &repo,
+            &old_state,
+            &new_state,
+            patchnames,
+            use_color(matches),
+            &diff_opts,
+            &mut output,
+        )?;
+    } else {
+        let patchnames = if let Some(patchnames) = patchnames {
+            patchnames
+        } else if let Some(top_patchname) = stack.applied().last() {
+            vec![top_patchname.clone()]
+        } else {
+            return Err(super::Error::NoAppliedPatches.into());
+        };
+
+        let states = StateHistory::from_stack(&stack)?.collect::<Result<Vec<_>>>()?;
+        let v1 = matches.get_one::<usize>("v1").copied();
+        let v2 = matches.get_one::<usize>("v2").copied();
+
+        for pn in &patchnames {
+            let versions = patch_versions(&states, pn);
+            if versions.is_empty() {
+                return Err(anyhow!("patch `{pn}` not found in stack history"));
+            }
+            let num_versions = versions.len();
+            let check_version = |v: usize| {
+                if (1..=num_versions).contains(&v) {
+                    Ok(v)
+                } else {
+                    Err(anyhow!(
+                        "patch `{pn}` does not have version {v}; \
+                         versions 1 through {num_versions} are available"
+                    ))
+                }
+            };
+            let v2 = check_version(v2.unwrap_or(num_versions))?;
+            let v1 = if let Some(v1) = v1 {
+                check_version(v1)?
+            } else if v2 > 1 {
+                v2 - 1
+            } else {
+                return Err(anyhow!("patch `{pn}` has only one version"));
+            };
+
+            writeln!(output, "{pn}: v{v1}..v{v2}")?;
+            range_diff(
+                &repo,
+                &versions[v1 - 1],
+                &versions[v2 - 1],
+                use_color(matches),
+                &diff_opts,
+                &mut output,
+            )?;
+        }
+    }
+
+    std::io::stdout().write_all(&output)?;
+    Ok(())
+}
+
+/// Append range-diffs of the patches in two stack states to output.
+///
+/// Patches are matched by name. Patches present in only one of the states are reported
+/// as added or removed. By default, all patches from both states are compared.
+pub(super) fn compare_states(
+    repo: &gix::Repository,
+    old_state: &StackState,
+    new_state: &StackState,
+    patchnames: Option<Vec<PatchName>>,
+    use_color: bool,
+    diff_opts: &[String],
+    output: &mut Vec<u8>,
+) -> Result<()> {
+    let patchnames = if let Some(patchnames) = patchnames {
+        for pn in &patchnames {
+            if !old_state.has_patch(pn) && !new_state.has_patch(pn) {
+                return Err(anyhow!("patch `{pn}` not found in either stack state"));
+            }
+        }
+        patchnames
+    } else {
+        let mut patchnames: Vec<PatchName> = new_state.all_patches().cloned().collect();
+        patchnames.extend(
+            old_state
+                .all_patches()
+                .filter(|pn| !new_state.has_patch(pn))
+                .cloned(),
+        );
+        patchnames
+    };
+
+    for pn in &patchnames {
+        match (old_state.has_patch(pn), new_state.has_patch(pn)) {
+            (true, true) => {
+                writeln!(output, "{pn}:")?;
+                range_diff(
+                    repo,
+                    old_state.get_patch_commit(pn),
+                    new_state.get_patch_commit(pn),
+                    use_color,
+                    diff_opts,
+                    output,
+                )?;
+            }
+            (true, false) => writeln!(output, "{pn}: removed")?,
+            (false, true) => writeln!(output, "{pn}: added")?,
+            (false, false) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a stack state committish to a [`StackState`].
+fn resolve_state<'repo>(
+    repo: &'repo gix::Repository,
+    committish: &str,
+) -> Result<StackState<'repo>> {
+    let commit = repo
+        .rev_parse_single(committish)
+        .map_err(|_| anyhow!("invalid committish `{committish}`"))?
+        .object()?
+        .peel_tags_to_end()?
+        .try_into_commit()
+        .map_err(|_| anyhow!("target `{committish}` is not a commit"))?;
+    StackState::from_commit(repo, &commit)
+        .map_err(|e| anyhow!("`{committish}` is not a stack state: {e:#}"))
+}
+
+/// Append range-diff of two patch commits to output.
+fn range_diff(
+    repo: &gix::Repository,
+    old_commit: &Rc<gix::Commit>,
+    new_commit: &Rc<gix::Commit>,
+    use_color: bool,
+    diff_opts: &[String],
+    output: &mut Vec<u8>,
+) -> Result<()> {
+    let parent_id = |commit: &gix::Commit| {
+        commit
+            .parent_ids()
+            .next()
+            .map(|id| id.detach())
+            .ok_or_else(|| anyhow!("patch commit `{}` has no parent", commit.id))
+    };
+    let diff = repo.stupid().range_diff(
+        (parent_id(old_commit)?, old_commit.id),
+        (parent_id(new_commit)?, new_commit.id),
+        use_color,
+        diff_opts,
+    )?;
+    output.extend_from_slice(&diff);
+    Ok(())
+}
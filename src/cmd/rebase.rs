@@ -14,7 +14,7 @@ use crate::{
     ext::RepositoryExtended,
     patch::{patchedit, PatchName, SingleRevisionSpec},
     print_info_message,
-    stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess},
+    stack::{InitializationPolicy, Sequence, Stack, StackAccess, StackStateAccess, Step},
     stupid::Stupid,
 };
 
@@ -26,24 +26,28 @@ pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
 };
 
 fn make() -> clap::Command {
-    clap::Command::new(STGIT_COMMAND.name)
+    let app = clap::Command::new(STGIT_COMMAND.name)
         .about("Move the stack base to another point in history")
         .long_about(
             "Pop all patches from the current stack, move the stack base to the given \
             new base and push the patches back.\n\
             \n\
             Merge conflicts may arise when patches are being pushed-back onto the \
-            stack. If this occurs, resolve the conflicts and then continue the rebase \
-            with the following sequence:\n\
+            stack. If this occurs, resolve the conflicts, add the resolved files to \
+            the index, and then continue the rebase with:\n\
             \n    \
-            stg add --update\n    \
-            stg refresh\n    \
-            stg goto top-patch\n\
+            stg rebase --continue\n\
             \n\
-            Or to skip the conflicting patch:\n\
+            Or to skip the conflicting patch, leaving it unapplied:\n\
             \n    \
-            stg undo --hard\n    \
-            stg push next-patch..top-patch\n\
+            stg rebase --skip\n\
+            \n\
+            Or to restore the stack to its state prior to the rebase:\n\
+            \n    \
+            stg rebase --abort\n\
+            \n\
+            An interactive rebase stopped by a `break` instruction or a failed \
+            `exec` instruction is also resumed with '--continue'.\n\
             ",
         )
         .arg(
@@ -87,10 +91,15 @@ fn make() -> clap::Command {
                 )
                 .action(clap::ArgAction::SetTrue),
         )
-        .arg(argset::push_conflicts_arg())
+        .arg(argset::push_conflicts_arg());
+    super::sequence::add_args(app)
 }
 
 fn run(matches: &ArgMatches) -> Result<()> {
+    if let Some(resume) = super::sequence::get_resume(matches) {
+        return super::sequence::resume(matches, STGIT_COMMAND.name, resume);
+    }
+
     let repo = gix::Repository::open()?;
     let stack = Stack::current(&repo, InitializationPolicy::RequireInitialized)?;
     let config = repo.config_snapshot();
@@ -187,6 +196,10 @@ fn run(matches: &ArgMatches) -> Result<()> {
     };
 
     let applied = stack.applied().to_vec();
+    let mut sequence = super::sequence::begin(&stack, STGIT_COMMAND.name)?;
+    sequence.check_merged = matches.get_flag("merged");
+    sequence.allow_push_conflicts = allow_push_conflicts;
+    sequence.committer_date_is_author_date = committer_date_is_author_date;
 
     stack
         .setup_transaction()
@@ -225,26 +238,11 @@ fn run(matches: &ArgMatches) -> Result<()> {
     };
 
     if matches.get_flag("interactive") {
-        interactive_pushback(
-            stack,
-            &repo,
-            &config,
-            matches,
-            &applied,
-            allow_push_conflicts,
-            committer_date_is_author_date,
-        )?;
+        interactive_pushback(stack, &repo, &config, matches, &applied, sequence)?;
     } else if !matches.get_flag("nopush") {
         stack.check_head_top_mismatch()?;
-        let check_merged = matches.get_flag("merged");
-        stack
-            .setup_transaction()
-            .use_index_and_worktree(true)
-            .allow_push_conflicts(allow_push_conflicts)
-            .committer_date_is_author_date(committer_date_is_author_date)
-            .with_output_stream(get_color_stdout(matches))
-            .transact(|trans| trans.push_patches(&applied, check_merged))
-            .execute("rebase (reapply)")?;
+        sequence.todo = applied.into_iter().map(Step::Push).collect();
+        super::sequence::run(stack, matches, sequence)?;
     }
 
     if using_stash {
@@ -325,10 +323,14 @@ const INTERACTIVE_HELP_LINES: &str = "\
 #
 #   k, keep <patch> = do not modify this patch
 #   e, edit <patch> = interactively edit this patch
+#   r, reword <patch> = edit this patch's message, but not its diff
 #   s, squash <patch> = squash patch into the previous patch
 #   f, fixup <patch> = like \"squash\", but discard this patch's commit message
 #   h, hide <patch> = hide patch
 #   d, delete <patch> = delete patch
+#   spill <patch> = delete patch, but leave its changes in the index and worktree
+#   x, exec <command> = run command using shell; stop if the command fails
+#   b, break = stop here, leaving the remaining patches unapplied
 #
 # These lines can be reordered; they are executed from top to bottom.
 #
@@ -337,19 +339,55 @@ const INTERACTIVE_HELP_LINES: &str = "\
 
 #[derive(Debug, Clone)]
 struct Instruction {
-    patchname: PatchName,
     action: Action,
     apply: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Action {
-    Keep,
-    Edit,
-    Squash,
-    Fixup,
-    Hide,
-    Delete,
+    Keep(PatchName),
+    Edit(PatchName),
+    Reword(PatchName),
+    Squash(PatchName),
+    Fixup(PatchName),
+    Hide(PatchName),
+    Delete(PatchName),
+    Spill(PatchName),
+    Exec(String),
+    Break,
+}
+
+impl Action {
+    /// Get the name of the patch the action applies to, if any.
+    fn patchname(&self) -> Option<&PatchName> {
+        match self {
+            Action::Keep(pn)
+            | Action::Edit(pn)
+            | Action::Reword(pn)
+            | Action::Squash(pn)
+            | Action::Fixup(pn)
+            | Action::Hide(pn)
+            | Action::Delete(pn)
+            | Action::Spill(pn) => Some(pn),
+            Action::Exec(_) | Action::Break => None,
+        }
+    }
+
+    /// Get the name of the action as used in the instructions file.
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Keep(_) => "keep",
+            Action::Edit(_) => "edit",
+            Action::Reword(_) => "reword",
+            Action::Squash(_) => "squash",
+            Action::Fixup(_) => "fixup",
+            Action::Hide(_) => "hide",
+            Action::Delete(_) => "delete",
+            Action::Spill(_) => "spill",
+            Action::Exec(_) => "exec",
+            Action::Break => "break",
+        }
+    }
 }
 
 fn interactive_pushback(
@@ -358,10 +396,10 @@ fn interactive_pushback(
     config: &gix::config::Snapshot,
     matches: &ArgMatches,
     previously_applied: &[PatchName],
-    allow_push_conflicts: bool,
-    committer_date_is_author_date: bool,
+    sequence: Sequence,
 ) -> Result<()> {
     let mut stack = stack;
+    let committer_date_is_author_date = sequence.committer_date_is_author_date;
 
     if stack.all_patches().next().is_none() {
         return Ok(());
@@ -383,53 +421,50 @@ fn interactive_pushback(
 
     let mut index: usize = 0;
 
+    // Patch modifications are performed first while all patches are unapplied. The
+    // exec, break, and spill instructions are handled while pushing the patches back.
     while index < instructions.len() {
         let instruction = instructions[index].clone();
-        let patchname = &instruction.patchname;
 
-        match instruction.action {
-            Action::Keep => {
+        match &instruction.action {
+            Action::Keep(_) | Action::Spill(_) | Action::Exec(_) | Action::Break => {
                 index += 1;
             }
 
-            Action::Delete => {
+            Action::Delete(_) => {
                 // Find contiguous delete instructions in order to delete in batches.
-                let mut delete_instructions: Vec<Instruction> = Vec::new();
-                while index < instructions.len() && instructions[index].action == Action::Delete {
-                    delete_instructions.push(instructions.remove(index));
+                let mut to_delete: Vec<PatchName> = Vec::new();
+                while let Some(Instruction {
+                    action: Action::Delete(patchname),
+                    ..
+                }) = instructions.get(index)
+                {
+                    to_delete.push(patchname.clone());
+                    instructions.remove(index);
                 }
-                assert!(!delete_instructions.is_empty());
-                assert!(delete_instructions
-                    .iter()
-                    .all(|inst| inst.action == Action::Delete));
-                let to_delete: Vec<&PatchName> = delete_instructions
-                    .iter()
-                    .map(|inst| &inst.patchname)
-                    .collect();
+                assert!(!to_delete.is_empty());
                 stack = stack
                     .setup_transaction()
                     .with_output_stream(get_color_stdout(matches))
                     .transact(|trans| {
-                        let popped_extra = trans.delete_patches(|pn| to_delete.contains(&pn))?;
+                        let popped_extra = trans.delete_patches(|pn| to_delete.contains(pn))?;
                         assert!(popped_extra.is_empty());
                         Ok(())
                     })
                     .execute("delete")?;
             }
 
-            Action::Hide => {
-                let mut hide_instructions: Vec<Instruction> = Vec::new();
-                while index < instructions.len() && instructions[index].action == Action::Hide {
-                    hide_instructions.push(instructions.remove(index));
+            Action::Hide(_) => {
+                let mut to_hide: Vec<PatchName> = Vec::new();
+                while let Some(Instruction {
+                    action: Action::Hide(patchname),
+                    ..
+                }) = instructions.get(index)
+                {
+                    to_hide.push(patchname.clone());
+                    instructions.remove(index);
                 }
-                assert!(!hide_instructions.is_empty());
-                assert!(hide_instructions
-                    .iter()
-                    .all(|inst| inst.action == Action::Hide));
-                let to_hide: Vec<PatchName> = hide_instructions
-                    .iter()
-                    .map(|inst| inst.patchname.clone())
-                    .collect();
+                assert!(!to_hide.is_empty());
                 stack = stack
                     .setup_transaction()
                     .with_output_stream(get_color_stdout(matches))
@@ -437,16 +472,21 @@ fn interactive_pushback(
                     .execute("hide")?;
             }
 
-            Action::Edit => {
+            Action::Edit(patchname) | Action::Reword(patchname) => {
                 let dummy_edit_command = clap::Command::new("dummy-edit");
                 let dummy_edit_command = patchedit::add_args(dummy_edit_command, false, false);
+                let edit_args: &[&str] = if matches!(instruction.action, Action::Edit(_)) {
+                    &["dummy-edit", "--edit", "--diff"]
+                } else {
+                    &["dummy-edit", "--edit"]
+                };
                 let edit_matches = dummy_edit_command
-                    .try_get_matches_from(["dummy-edit", "--edit", "--diff"])
+                    .try_get_matches_from(edit_args)
                     .expect("dummy command has valid arguments");
                 match patchedit::EditBuilder::default()
                     .original_patchname(Some(patchname))
                     .existing_patch_commit(stack.get_patch_commit(patchname))
-                    .allow_diff_edit(true)
+                    .allow_diff_edit(matches!(instruction.action, Action::Edit(_)))
                     .edit(&stack, repo, &edit_matches)?
                 {
                     patchedit::EditOutcome::TemplateSaved(_) => panic!("template save not enabled"),
@@ -472,12 +512,13 @@ fn interactive_pushback(
                                     }
                                     Ok(())
                                 })
-                                .execute(&format!("edit: {patchname}"))?;
+                                .execute(&format!("{}: {patchname}", instruction.action.name()))?;
                         }
 
                         instructions[index] = Instruction {
-                            action: Action::Keep,
-                            patchname: new_patchname.unwrap_or_else(|| patchname.clone()),
+                            action: Action::Keep(
+                                new_patchname.unwrap_or_else(|| patchname.clone()),
+                            ),
                             apply: instruction.apply,
                         };
 
@@ -486,49 +527,44 @@ fn interactive_pushback(
                 }
             }
 
-            Action::Squash | Action::Fixup => {
-                let action_str = match instruction.action {
-                    Action::Squash => "squash",
-                    Action::Fixup => "fixup",
-                    _ => panic!("only squash and fixup expected"),
-                };
+            Action::Squash(patchname) | Action::Fixup(patchname) => {
+                let action_str = instruction.action.name();
+                let is_fixup = matches!(instruction.action, Action::Fixup(_));
 
-                let squash_patchnames: Vec<PatchName> = if index > 0 {
-                    let mut patchnames: Vec<PatchName> =
-                        vec![instructions[index - 1].patchname.clone()];
-                    while index < instructions.len()
-                        && instructions[index].action == instruction.action
-                    {
-                        let Instruction { patchname, .. } = instructions.remove(index);
-                        patchnames.push(patchname);
-                    }
-                    patchnames
-                } else {
+                let Some(target_patchname) = index
+                    .checked_sub(1)
+                    .and_then(|prev_index| instructions[prev_index].action.patchname())
+                    .cloned()
+                else {
                     return Err(anyhow!(
                         "cannot {action_str} `{patchname}`: no preceding patch"
                     ));
                 };
 
-                let target_patchname = &squash_patchnames[0];
+                let mut squash_patchnames: Vec<PatchName> = vec![target_patchname.clone()];
+                while let Some(Instruction { action, .. }) = instructions.get(index) {
+                    match action {
+                        Action::Squash(pn) if !is_fixup => squash_patchnames.push(pn.clone()),
+                        Action::Fixup(pn) if is_fixup => squash_patchnames.push(pn.clone()),
+                        _ => break,
+                    }
+                    instructions.remove(index);
+                }
 
                 let dummy_squash_command = clap::Command::new("dummy-squash");
                 let dummy_squash_command = patchedit::add_args(dummy_squash_command, true, false);
-                let squash_matches = match instruction.action {
-                    Action::Squash => {
-                        dummy_squash_command.try_get_matches_from(["dummy-squash", "--edit"])
-                    }
-                    Action::Fixup => {
-                        let commit = stack.get_patch_commit(target_patchname);
-                        let message = commit.message_raw()?.to_str().map_err(|_| {
-                            anyhow!("fixup target patch `{target_patchname}` has non-UTF-8 message")
-                        })?;
-                        dummy_squash_command.try_get_matches_from([
-                            "dummy-squash",
-                            "--message",
-                            message,
-                        ])
-                    }
-                    _ => panic!("only squash and fixup expected"),
+                let squash_matches = if is_fixup {
+                    let commit = stack.get_patch_commit(&target_patchname);
+                    let message = commit.message_raw()?.to_str().map_err(|_| {
+                        anyhow!("fixup target patch `{target_patchname}` has non-UTF-8 message")
+                    })?;
+                    dummy_squash_command.try_get_matches_from([
+                        "dummy-squash",
+                        "--message",
+                        message,
+                    ])
+                } else {
+                    dummy_squash_command.try_get_matches_from(["dummy-squash", "--edit"])
                 }
                 .expect("dummy command has valid arguments");
 
@@ -540,14 +576,15 @@ fn interactive_pushback(
                             trans,
                             &squash_matches,
                             &squash_patchnames,
-                            Some(target_patchname),
+                            Some(&target_patchname),
                             false,
                         )?;
-                        instructions[index - 1] = Instruction {
-                            action: Action::Keep,
-                            patchname: new_patchname,
-                            apply: instruction.apply,
+                        let target = &mut instructions[index - 1];
+                        target.action = match target.action {
+                            Action::Spill(_) => Action::Spill(new_patchname),
+                            _ => Action::Keep(new_patchname),
                         };
+                        target.apply = instruction.apply;
                         Ok(())
                     })
                     .execute("squash")?;
@@ -555,29 +592,26 @@ fn interactive_pushback(
         }
     }
 
-    let to_push: Vec<PatchName> = instructions
-        .iter()
-        .filter_map(|inst| {
-            if inst.apply {
-                Some(inst.patchname.clone())
-            } else {
-                None
-            }
+    // The remaining instructions are performed while pushing the patches back.
+    let mut sequence = sequence;
+    sequence.todo = instructions
+        .into_iter()
+        .filter(|instruction| instruction.apply)
+        .map(|instruction| match instruction.action {
+            Action::Keep(patchname) => Step::Push(patchname),
+            Action::Spill(patchname) => Step::Spill(patchname),
+            Action::Exec(command) => Step::Exec(command),
+            Action::Break => Step::Break,
+            Action::Edit(_)
+            | Action::Reword(_)
+            | Action::Squash(_)
+            | Action::Fixup(_)
+            | Action::Hide(_)
+            | Action::Delete(_) => panic!("patch modifications are already performed"),
         })
         .collect();
-    let check_merged = matches.get_flag("merged");
-
-    stack.check_head_top_mismatch()?;
-    stack
-        .setup_transaction()
-        .use_index_and_worktree(true)
-        .allow_push_conflicts(allow_push_conflicts)
-        .committer_date_is_author_date(committer_date_is_author_date)
-        .with_output_stream(get_color_stdout(matches))
-        .transact(|trans| trans.push_patches(&to_push, check_merged))
-        .execute("rebase (reapply)")?;
 
-    Ok(())
+    super::sequence::run(stack, matches, sequence)
 }
 
 fn make_instructions_template(stack: &Stack, previously_applied: &[PatchName]) -> String {
@@ -621,6 +655,21 @@ fn parse_instructions(buf: &str) -> Result<Vec<Instruction>> {
             }
         }
 
+        // The exec command is taken verbatim since it may contain '#' characters.
+        if let Some((action_str, command)) = line.split_once(|c: char| c.is_ascii_whitespace()) {
+            if matches!(action_str, "x" | "exec") {
+                let command = command.trim();
+                if command.is_empty() {
+                    return Err(anyhow!("bad instruction line: `{line}`"));
+                }
+                instructions.push(Instruction {
+                    action: Action::Exec(command.to_string()),
+                    apply,
+                });
+                continue;
+            }
+        }
+
         let instruction_str = if let Some((instruction_str, _comment)) = line.split_once('#') {
             instruction_str
         } else {
@@ -632,26 +681,29 @@ fn parse_instructions(buf: &str) -> Result<Vec<Instruction>> {
             continue;
         }
 
-        if let Some((action_str, patchname_str)) =
+        if matches!(instruction_str, "b" | "break") {
+            instructions.push(Instruction {
+                action: Action::Break,
+                apply,
+            });
+        } else if let Some((action_str, patchname_str)) =
             instruction_str.split_once(|c: char| c.is_ascii_whitespace())
         {
+            let patchname = PatchName::from_str(patchname_str)?;
+
             let action = match action_str {
-                "k" | "keep" => Action::Keep,
-                "e" | "edit" => Action::Edit,
-                "s" | "squash" => Action::Squash,
-                "f" | "fix" | "fixup" => Action::Fixup,
-                "h" | "hide" => Action::Hide,
-                "d" | "delete" => Action::Delete,
+                "k" | "keep" => Action::Keep(patchname),
+                "e" | "edit" => Action::Edit(patchname),
+                "r" | "reword" => Action::Reword(patchname),
+                "s" | "squash" => Action::Squash(patchname),
+                "f" | "fix" | "fixup" => Action::Fixup(patchname),
+                "h" | "hide" => Action::Hide(patchname),
+                "d" | "delete" => Action::Delete(patchname),
+                "spill" => Action::Spill(patchname),
                 _ => return Err(anyhow!("unknown instruction action `{action_str}`")),
             };
 
-            let patchname = PatchName::from_str(patchname_str)?;
-
-            instructions.push(Instruction {
-                patchname,
-                action,
-                apply,
-            });
+            instructions.push(Instruction { action, apply });
         } else {
             return Err(anyhow!("bad instruction line: `{line}`"));
         }
@@ -661,15 +713,36 @@ fn parse_instructions(buf: &str) -> Result<Vec<Instruction>> {
 
 fn validate_instructions(stack: &Stack, instructions: &[Instruction]) -> Result<()> {
     let mut seen_patchnames: Vec<&PatchName> = Vec::new();
+    let mut prev_action: Option<&Action> = None;
     for instruction in instructions {
-        let patchname = &instruction.patchname;
-        if !stack.has_patch(patchname) {
-            return Err(anyhow!("unknown patch name `{patchname}`"));
-        } else if seen_patchnames.contains(&patchname) {
-            return Err(anyhow!("duplicated patch name `{patchname}`"));
-        } else {
-            seen_patchnames.push(patchname);
+        let action = &instruction.action;
+        if let Some(patchname) = action.patchname() {
+            if !stack.has_patch(patchname) {
+                return Err(anyhow!("unknown patch name `{patchname}`"));
+            } else if seen_patchnames.contains(&patchname) {
+                return Err(anyhow!("duplicated patch name `{patchname}`"));
+            } else {
+                seen_patchnames.push(patchname);
+            }
+        }
+        match action {
+            Action::Squash(patchname) | Action::Fixup(patchname)
+                if matches!(prev_action, Some(Action::Exec(_) | Action::Break)) =>
+            {
+                return Err(anyhow!(
+                    "cannot {} `{patchname}`: no preceding patch",
+                    action.name()
+                ));
+            }
+            Action::Spill(_) | Action::Exec(_) | Action::Break if !instruction.apply => {
+                return Err(anyhow!(
+                    "`{}` instructions must be above the APPLY_LINE",
+                    action.name()
+                ));
+            }
+            _ => {}
         }
+        prev_action = Some(action);
     }
     Ok(())
 }
@@ -15,9 +15,9 @@ use crate::{
     argset,
     color::get_color_stdout,
     ext::{CommitExtended, RepositoryExtended, SignatureExtended},
-    hook::run_pre_commit_hook,
+    hook::{run_post_refresh_hook, run_pre_commit_hook},
     patch::{patchedit, LocationConstraint, PatchLocator, PatchName},
-    stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess},
+    stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess, TransactionError},
     stupid::{Status, StatusOptions, Statuses, Stupid, StupidContext},
     wrap::Message,
 };
@@ -203,8 +203,9 @@ fn run(matches: &ArgMatches) -> Result<()> {
             "refresh {temp_patchname} (create temporary patch)"
         ))?;
 
-    let mut absorb_success = false;
-    stack
+    let mut refreshed_commit_id = None;
+    let mut refreshed_patchname = patchname.clone();
+    let result = stack
         .setup_transaction()
         .use_index_and_worktree(true)
         .with_output_stream(get_color_stdout(matches))
@@ -253,6 +254,7 @@ fn run(matches: &ArgMatches) -> Result<()> {
                 }
                 if let Some(new_patchname) = new_patchname {
                     trans.rename_patch(&patchname, &new_patchname)?;
+                    refreshed_patchname = new_patchname;
                 } else {
                     log_msg.push_str(patchname.as_ref());
                 }
@@ -261,8 +263,10 @@ fn run(matches: &ArgMatches) -> Result<()> {
                     log_msg.push_str(annotation);
                 }
 
+                // The patch is refreshed even if pushing the popped patches back halts
+                // the transaction with conflicts.
+                refreshed_commit_id = Some(trans.get_patch_commit(&refreshed_patchname).id);
                 trans.push_patches(&to_pop, false)?;
-                absorb_success = true;
             } else {
                 // Absorb temp patch into unapplied patch
                 let popped_extra = trans.pop_patches(|pn| pn == &temp_patchname)?;
@@ -311,6 +315,7 @@ fn run(matches: &ArgMatches) -> Result<()> {
                     if let Some(new_patchname) = new_patchname {
                         trans.rename_patch(&patchname, &new_patchname)?;
                         log_msg.push_str(new_patchname.as_ref());
+                        refreshed_patchname = new_patchname;
                     } else {
                         log_msg.push_str(patchname.as_ref());
                     }
@@ -319,14 +324,27 @@ fn run(matches: &ArgMatches) -> Result<()> {
                         log_msg.push_str(annotation);
                     }
                     trans.delete_patches(|pn| pn == &temp_patchname)?;
-                    absorb_success = true;
+                    refreshed_commit_id = Some(trans.get_patch_commit(&refreshed_patchname).id);
                 }
             }
             Ok(())
         })
-        .execute(&log_msg)?;
+        .execute(&log_msg);
+
+    // The stack state is committed when the transaction succeeds or halts with conflicts.
+    let committed = match &result {
+        Ok(_) => true,
+        Err(err) => matches!(
+            err.downcast_ref::<TransactionError>(),
+            Some(TransactionError::TransactionHalt { .. })
+        ),
+    };
+    if let Some(commit_id) = refreshed_commit_id.filter(|_| committed) {
+        run_post_refresh_hook(&repo, &refreshed_patchname, commit_id);
+    }
+    result?;
 
-    if !absorb_success {
+    if refreshed_commit_id.is_none() {
         println!(
             "The new changes did not apply cleanly to {}. \
              They were saved in {}.",
@@ -337,7 +355,7 @@ fn run(matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn determine_refresh_paths(
+pub(super) fn determine_refresh_paths(
     stupid: &StupidContext,
     statuses: &Statuses,
     patch_commit: Option<&Rc<gix::Commit>>,
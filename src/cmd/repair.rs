@@ -2,11 +2,11 @@
 
 //! `stg repair` implementation.
 
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
 use anyhow::{anyhow, Result};
 use bstr::ByteSlice;
-use indexmap::{indexset, IndexSet};
+use indexmap::{indexset, IndexMap, IndexSet};
 
 use crate::{
     color::get_color_stdout,
@@ -14,6 +14,7 @@ use crate::{
     patch::PatchName,
     print_info_message, print_warning_message,
     stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess},
+    stupid::Stupid,
 };
 
 pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
@@ -43,6 +44,13 @@ fn make() -> clap::Command {
              (i.e. by using plain `git commit`), `stg repair` will convert those \
              commits to StGit patches, preserving their content.\n\
              \n\
+             - If patch commits were rewritten, e.g. with `git commit --amend` or \
+             `git rebase --interactive`, `stg repair` will match the rewritten \
+             commits with their original patches and update those patches in place, \
+             keeping their names. A rewritten commit matches a patch if it has the \
+             same author and either the same patch id, as determined by \
+             git-patch-id(1), or the same subject.\n\
+             \n\
              - However, merge commits cannot become patches. So if a merge was \
              committed on top of the stack, `stg repair` will mark all patches below \
              the merge commit as unapplied, since they are no longer reachable. An \
@@ -75,29 +83,19 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
 
     let patchname_len_limit = PatchName::get_length_limit(&config);
 
-    // Find commits that are not patches as well as applied patches.
-
-    // Commits to definitely patchify
-    let mut patchify: Vec<Rc<gix::Commit>> = Vec::new();
-
-    // Commits to patchify if a patch is found below
-    let mut maybe_patchify: Vec<Rc<gix::Commit>> = Vec::new();
-
-    let mut applied: Vec<PatchName> = Vec::new();
-
+    // Find commits that are not patches as well as applied patches, walking down from
+    // the branch head.
+    let mut walked: Vec<(Rc<gix::Commit>, Option<PatchName>)> = Vec::new();
+    let mut reached_base = false;
     let mut commit = stack.get_branch_head().clone();
 
     while commit.parent_ids().count() == 1 {
         let parent = Rc::new(commit.get_parent_commit()?);
-        if let Some(patchname) = stack
+        let patchname = stack
             .all_patches()
             .find(|pn| stack.get_patch_commit_id(pn) == commit.id)
-        {
-            applied.push(patchname.clone());
-            patchify.append(&mut maybe_patchify);
-        } else {
-            maybe_patchify.push(commit.clone());
-        }
+            .cloned();
+        walked.push((commit, patchname));
 
         commit = parent;
 
@@ -105,13 +103,33 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
             // Reaching the original stack base can happen if, for example, the first
             // applied patch is amended. In this case, any commits descending from the
             // stack base should be patchified.
-            patchify.append(&mut maybe_patchify);
+            reached_base = true;
             break;
         }
     }
 
-    applied.reverse();
-    patchify.reverse();
+    if !reached_base {
+        // Commits below the lowest patch are not patchified.
+        let len = walked
+            .iter()
+            .rposition(|(_, patchname)| patchname.is_some())
+            .map_or(0, |pos| pos + 1);
+        walked.truncate(len);
+    }
+    walked.reverse();
+
+    // Commits that are rewrites of patches, e.g. by `git commit --amend` or
+    // `git rebase --interactive`, take the place of the original patch commits.
+    let rewritten = find_rewritten_patches(&stack, &walked)?;
+    for (pos, patchname) in &rewritten {
+        walked[*pos].1 = Some(patchname.clone());
+    }
+
+    let applied: Vec<PatchName> = walked
+        .iter()
+        .filter_map(|(_, patchname)| patchname.clone())
+        .collect();
+    let num_patchify = walked.len() - applied.len();
 
     // Find patches unreachable behind a merge.
     if commit.id() != stack.base().id() {
@@ -181,36 +199,151 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
         .filter(|&pn| !stack.unapplied().contains(pn))
         .for_each(|pn| print_info_message(matches, &format!("`{pn}` is now unapplied")));
 
+    for (pos, patchname) in &rewritten {
+        print_info_message(
+            matches,
+            &format!(
+                "`{patchname}` is now commit {}, rewritten from {}",
+                walked[*pos].0.id,
+                stack.get_patch_commit_id(patchname),
+            ),
+        );
+    }
+
     stack
         .setup_transaction()
         .use_index_and_worktree(false)
         .with_output_stream(get_color_stdout(matches))
         .transact(|trans| {
-            trans.repair_appliedness(applied, unapplied, hidden);
-
             // Make patches of any linear sequence of commits on top of a patch.
-            if !patchify.is_empty() {
+            if num_patchify > 0 {
                 print_info_message(
                     matches,
                     &format!(
-                        "Creating {} new patch{}",
-                        patchify.len(),
-                        if patchify.len() == 1 { "" } else { "es" }
+                        "Creating {num_patchify} new patch{}",
+                        if num_patchify == 1 { "" } else { "es" }
                     ),
                 );
+            }
+
+            let mut new_applied: Vec<PatchName> = Vec::new();
+            for (commit, patchname) in walked {
+                if let Some(patchname) = patchname {
+                    if rewritten.values().any(|pn| pn == &patchname) {
+                        trans.update_patch(&patchname, commit.id)?;
+                    }
+                    new_applied.push(patchname);
+                } else {
+                    // New patches are pushed on top of the patches below them in the
+                    // branch's history.
+                    let rest: Vec<PatchName> = trans
+                        .all_patches()
+                        .filter(|pn| !new_applied.contains(pn) && !hidden.contains(pn))
+                        .cloned()
+                        .collect();
+                    trans.repair_appliedness(new_applied.clone(), rest, hidden.clone());
 
-                for commit in patchify {
                     let message = commit.message_raw()?.to_str_lossy();
                     let allow = &[];
                     let disallow: Vec<_> = trans.all_patches().collect();
                     let patchname = PatchName::make(&message, true, patchname_len_limit)
                         .uniquify(allow, &disallow);
                     trans.new_applied(&patchname, commit.id)?;
+                    new_applied.push(patchname);
                 }
             }
+            trans.repair_appliedness(new_applied, unapplied, hidden);
             Ok(())
         })
         .execute("repair")?;
 
     Ok(())
 }
+
+/// Find commits that are rewrites of existing patches.
+///
+/// Commits that are not patch commits are matched with applied or unapplied patches
+/// whose commits are no longer found in the branch's history. A commit matches a patch
+/// when it has the same author and either the same patch id or the same subject. Matches
+/// by patch id take precedence over matches by subject.
+///
+/// Returns the position of each rewritten commit in `walked` mapped to its patch name.
+fn find_rewritten_patches(
+    stack: &Stack,
+    walked: &[(Rc<gix::Commit>, Option<PatchName>)],
+) -> Result<IndexMap<usize, PatchName>> {
+    let mut rewritten = IndexMap::new();
+    let candidates: Vec<usize> = walked
+        .iter()
+        .enumerate()
+        .filter_map(|(pos, (_, patchname))| patchname.is_none().then_some(pos))
+        .collect();
+    let mut orphans: Vec<&PatchName> = stack
+        .applied()
+        .iter()
+        .chain(stack.unapplied().iter())
+        .filter(|pn| {
+            !walked
+                .iter()
+                .any(|(_, patchname)| patchname.as_ref() == Some(pn))
+        })
+        .collect();
+    if candidates.is_empty() || orphans.is_empty() {
+        return Ok(rewritten);
+    }
+
+    let patch_ids: HashMap<gix::ObjectId, gix::ObjectId> = stack
+        .repo
+        .stupid()
+        .patch_ids(
+            candidates
+                .iter()
+                .map(|pos| walked[*pos].0.id)
+                .chain(orphans.iter().map(|pn| stack.get_patch_commit_id(pn))),
+        )?
+        .into_iter()
+        .collect();
+
+    let subject = |commit: &gix::Commit| -> Result<Option<String>> {
+        Ok(commit
+            .message_ex()
+            .decode()?
+            .lines()
+            .next()
+            .map(str::to_string))
+    };
+
+    for match_patch_id in [true, false] {
+        for &pos in &candidates {
+            if rewritten.contains_key(&pos) {
+                continue;
+            }
+            let commit = &walked[pos].0;
+            let author = commit.author_strict()?;
+            let mut found = None;
+            for (i, patchname) in orphans.iter().enumerate() {
+                let patch_commit = stack.get_patch_commit(patchname);
+                if patch_commit.author_strict()? != author {
+                    continue;
+                }
+                let is_match = if match_patch_id {
+                    patch_ids
+                        .get(&commit.id)
+                        .is_some_and(|id| patch_ids.get(&patch_commit.id) == Some(id))
+                } else {
+                    subject(commit)? == subject(patch_commit)?
+                };
+                if is_match {
+                    found = Some(i);
+                    break;
+                }
+            }
+            if let Some(i) = found {
+                rewritten.insert(pos, orphans.remove(i).clone());
+            }
+        }
+    }
+
+    rewritten.sort_keys();
+    Ok(rewritten)
+}
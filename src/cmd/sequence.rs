@@ -0,0 +1,581 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Support for resuming stopped multi-step operations.
+//!
+//! Commands that push several patches, such as `stg push` and `stg rebase`, may stop
+//! part way through due to merge conflicts. When that happens, the steps remaining to
+//! be performed are recorded as a [`Sequence`] so that the operation may be resumed
+//! with `--continue` or `--skip`, or rolled back with `--abort`.
+
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Result};
+use clap::{parser::ValueSource, Arg, ArgGroup, ArgMatches};
+
+use crate::{
+    color::get_color_stdout,
+    ext::{CommitExtended, RepositoryExtended},
+    patch::PatchName,
+    print_info_message,
+    stack::{
+        InitializationPolicy, Sequence, Stack, StackAccess, StackState, StackStateAccess, Step,
+        Stopped, TransactionError,
+    },
+    stupid::Stupid,
+};
+
+/// How a stopped operation is to be resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Resume {
+    Continue,
+    Skip,
+    Abort,
+}
+
+impl Resume {
+    fn option_name(self) -> &'static str {
+        match self {
+            Resume::Continue => "--continue",
+            Resume::Skip => "--skip",
+            Resume::Abort => "--abort",
+        }
+    }
+}
+
+/// Add the `--continue`, `--skip`, and `--abort` options to a command.
+pub(super) fn add_args(command: clap::Command) -> clap::Command {
+    command
+        .arg(
+            Arg::new("continue")
+                .long("continue")
+                .help("Continue a stopped operation after resolving conflicts")
+                .long_help(
+                    "Continue a stopped operation. Any resolved conflicts in the index \
+                     are first recorded in the patch that caused the operation to stop.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("skip")
+                .long("skip")
+                .help("Skip the patch that caused the operation to stop and continue")
+                .long_help(
+                    "Discard changes to the index and worktree, restore the patch that \
+                     caused the operation to stop, and continue with the remaining \
+                     steps of the operation. A patch being pushed is left unapplied and \
+                     a patch created by the operation is deleted.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("abort")
+                .long("abort")
+                .help("Abort a stopped operation and restore the original stack")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .group(ArgGroup::new("resume").args(["continue", "skip", "abort"]))
+}
+
+/// Get the requested way to resume a stopped operation, if any.
+pub(super) fn get_resume(matches: &ArgMatches) -> Option<Resume> {
+    if matches.get_flag("continue") {
+        Some(Resume::Continue)
+    } else if matches.get_flag("skip") {
+        Some(Resume::Skip)
+    } else if matches.get_flag("abort") {
+        Some(Resume::Abort)
+    } else {
+        None
+    }
+}
+
+/// Begin a new operation.
+///
+/// Any record of a previously stopped operation is discarded.
+pub(super) fn begin(stack: &Stack, operation: &str) -> Result<Sequence> {
+    stack.clear_sequence()?;
+    let state_commit_id = stack
+        .repo
+        .find_reference(stack.get_stack_refname())?
+        .peel_to_commit()?
+        .id;
+    Ok(Sequence::new(operation, state_commit_id))
+}
+
+/// Get options from the command line for reuse when the operation is resumed.
+///
+/// Only options with long names are captured. Positional arguments are not captured.
+pub(super) fn explicit_options(command: &clap::Command, matches: &ArgMatches) -> Vec<String> {
+    let mut options = Vec::new();
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if arg.is_positional()
+            || ["continue", "skip", "abort"].contains(&id)
+            || matches.value_source(id) != Some(ValueSource::CommandLine)
+        {
+            continue;
+        }
+        match arg.get_action() {
+            clap::ArgAction::SetTrue | clap::ArgAction::SetFalse => {
+                options.push(format!("--{long}"));
+            }
+            clap::ArgAction::Count => {
+                for _ in 0..matches.get_count(id) {
+                    options.push(format!("--{long}"));
+                }
+            }
+            _ => {
+                if let Some(values) = matches.get_raw(id) {
+                    for value in values {
+                        options.push(format!("--{long}={}", value.to_string_lossy()));
+                    }
+                }
+            }
+        }
+    }
+    options
+}
+
+/// Resume the stopped `operation` on the current stack.
+pub(super) fn resume(matches: &ArgMatches, operation: &str, resume: Resume) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::current(&repo, InitializationPolicy::RequireInitialized)?;
+    let Some(sequence) = stack.sequence()? else {
+        return Err(anyhow!("no `{operation}` operation in progress"));
+    };
+    if sequence.operation != operation {
+        return Err(anyhow!(
+            "a `{0}` operation is in progress; use `stg {0} {1}`",
+            sequence.operation,
+            resume.option_name(),
+        ));
+    }
+
+    match resume {
+        Resume::Abort => {
+            let stack = stack
+                .setup_transaction()
+                .use_index_and_worktree(true)
+                .discard_changes(true)
+                .allow_bad_head(true)
+                .with_output_stream(get_color_stdout(matches))
+                .transact(|trans| {
+                    let commit = trans.repo().find_commit(sequence.orig_state)?;
+                    let state = StackState::from_commit(trans.repo(), &commit)?;
+                    trans.reset_to_state(state)
+                })
+                .execute(&format!("{operation} (abort)"))?;
+            stack.clear_sequence()
+        }
+        Resume::Continue => {
+            repo.stupid().statuses(None)?.check_conflicts()?;
+            let stack = commit_resolution(stack, matches, &sequence)?;
+            run(stack, matches, sequence)
+        }
+        Resume::Skip => {
+            let stack = skip_stopped(stack, matches, &sequence)?;
+            run(stack, matches, sequence)
+        }
+    }
+}
+
+/// Record the index content in the patch the sequence stopped on.
+fn commit_resolution<'repo>(
+    stack: Stack<'repo>,
+    matches: &ArgMatches,
+    sequence: &Sequence,
+) -> Result<Stack<'repo>> {
+    let Some(stopped) = sequence.stopped.as_ref() else {
+        return Ok(stack);
+    };
+    if !sequence.is_current(&stack) {
+        return Ok(stack);
+    }
+
+    let tree_id = stack.repo.stupid().write_tree()?;
+    let patch_commit = stack.get_patch_commit(&stopped.patchname);
+    if patch_commit.tree_id()?.detach() == tree_id {
+        return Ok(stack);
+    }
+    let author = patch_commit.author_strict()?;
+    let default_committer = stack.repo.get_committer()?;
+    let committer = if sequence.committer_date_is_author_date {
+        let mut committer = default_committer.to_owned();
+        committer.time = author.time;
+        committer
+    } else {
+        default_committer.to_owned()
+    };
+    let commit_id = stack.repo.commit_ex(
+        &author,
+        &committer,
+        &patch_commit.message_ex(),
+        tree_id,
+        [patch_commit.get_parent_commit()?.id],
+    )?;
+
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| trans.update_patch(&stopped.patchname, commit_id))
+        .execute(&format!("{} (continue)", sequence.operation))
+}
+
+/// Restore the patch the sequence stopped on to its state prior to the stopped step.
+fn skip_stopped<'repo>(
+    stack: Stack<'repo>,
+    matches: &ArgMatches,
+    sequence: &Sequence,
+) -> Result<Stack<'repo>> {
+    let Some(stopped) = sequence.stopped.as_ref() else {
+        return Ok(stack);
+    };
+    if !sequence.is_current(&stack) {
+        return Ok(stack);
+    }
+
+    let patchname = &stopped.patchname;
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .discard_changes(true)
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| {
+            if let Some(commit_id) = stopped.commit {
+                if !stopped.applied {
+                    trans.pop_patches(|pn| pn == patchname)?;
+                }
+                trans.update_patch(patchname, commit_id)
+            } else {
+                trans.delete_patches(|pn| pn == patchname)?;
+                Ok(())
+            }
+        })
+        .execute(&format!("{} (skip)", sequence.operation))
+}
+
+/// Perform the steps of an operation.
+///
+/// If a step stops due to conflicts, or due to a `break` or failed `exec` step, the
+/// remaining steps are recorded so that the operation may later be resumed.
+pub(super) fn run(stack: Stack, matches: &ArgMatches, sequence: Sequence) -> Result<()> {
+    let repo = stack.repo;
+    let mut sequence = sequence;
+    sequence.stopped = None;
+    let mut todo: VecDeque<Step> = std::mem::take(&mut sequence.todo).into();
+    let mut stack = stack;
+    let mut operation_matches: Option<ArgMatches> = None;
+
+    stack.clear_sequence()?;
+
+    while let Some(step) = todo.pop_front() {
+        stack = match step {
+            Step::Push(patchname) => {
+                let mut to_push = vec![patchname];
+                while let Some(Step::Push(patchname)) = todo.front() {
+                    to_push.push(patchname.clone());
+                    todo.pop_front();
+                }
+                push(stack, matches, &sequence, &to_push, &todo)?
+            }
+
+            Step::Spill(patchname) => {
+                let stack = if stack.has_patch(&patchname) && !stack.is_applied(&patchname) {
+                    let mut remaining = todo.clone();
+                    remaining.push_front(Step::Spill(patchname.clone()));
+                    push(
+                        stack,
+                        matches,
+                        &sequence,
+                        std::slice::from_ref(&patchname),
+                        &remaining,
+                    )?
+                } else {
+                    stack
+                };
+                if stack.is_applied(&patchname) {
+                    stack
+                        .setup_transaction()
+                        .with_output_stream(get_color_stdout(matches))
+                        .transact(|trans| {
+                            let popped_extra = trans.delete_patches(|pn| pn == &patchname)?;
+                            assert!(popped_extra.is_empty());
+                            Ok(())
+                        })
+                        .execute("spill")?
+                } else {
+                    stack
+                }
+            }
+
+            Step::Exec(command) => {
+                print_info_message(matches, &format!("Executing `{command}`"));
+                let result = repo.stupid().user_exec(&command);
+                // The command may have modified the stack.
+                let stack = Stack::current(repo, InitializationPolicy::RequireInitialized)?;
+                if let Err(e) = result {
+                    stop(stack, matches, sequence, "exec", todo)?;
+                    return Err(e);
+                }
+                stack
+            }
+
+            Step::Break => {
+                return stop(stack, matches, sequence, "break", todo);
+            }
+
+            Step::Sync(patchname) => {
+                if operation_matches.is_none() {
+                    operation_matches = Some(get_operation_matches(matches, &sequence)?);
+                }
+                let orig_commit_id = stack.get_patch_commit_id(&patchname);
+                match super::sync::sync_patch(
+                    stack,
+                    operation_matches.as_ref().unwrap(),
+                    &patchname,
+                ) {
+                    Ok(stack) => stack,
+                    Err(e) => {
+                        if is_conflict(&e) {
+                            sequence.stopped = Some(Stopped {
+                                patchname,
+                                commit: Some(orig_commit_id),
+                                applied: true,
+                            });
+                            sequence.todo = todo.into();
+                            save(repo, matches, &sequence)?;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+
+            Step::Import { path, strip_level } => {
+                if operation_matches.is_none() {
+                    operation_matches = Some(get_operation_matches(matches, &sequence)?);
+                }
+                match super::import::import_series_patch(
+                    stack,
+                    operation_matches.as_ref().unwrap(),
+                    &path,
+                    strip_level,
+                ) {
+                    Ok(stack) => stack,
+                    Err(e) => {
+                        if is_conflict(&e) {
+                            let stack =
+                                Stack::current(repo, InitializationPolicy::RequireInitialized)?;
+                            sequence.stopped = stack.applied().last().map(|patchname| Stopped {
+                                patchname: patchname.clone(),
+                                commit: None,
+                                applied: false,
+                            });
+                            sequence.todo = todo.into();
+                            save(repo, matches, &sequence)?;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        };
+    }
+
+    Ok(())
+}
+
+/// Push patches, recording the remaining steps if the push stops due to conflicts.
+fn push<'repo>(
+    stack: Stack<'repo>,
+    matches: &ArgMatches,
+    sequence: &Sequence,
+    to_push: &[PatchName],
+    remaining: &VecDeque<Step>,
+) -> Result<Stack<'repo>> {
+    let repo = stack.repo;
+
+    // Patches may have been pushed or deleted while the operation was stopped.
+    let to_push: Vec<(PatchName, Option<gix::ObjectId>)> = to_push
+        .iter()
+        .filter(|pn| stack.has_patch(pn) && !stack.is_applied(pn))
+        .map(|pn| (pn.clone(), Some(stack.get_patch_commit_id(pn))))
+        .collect();
+    if to_push.is_empty() {
+        return Ok(stack);
+    }
+
+    stack.check_head_top_mismatch()?;
+    let reflog_msg = if sequence.operation == "rebase" {
+        "rebase (reapply)"
+    } else {
+        sequence.operation.as_str()
+    };
+    let patchnames: Vec<&PatchName> = to_push.iter().map(|(pn, _)| pn).collect();
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .allow_push_conflicts(sequence.allow_push_conflicts)
+        .committer_date_is_author_date(sequence.committer_date_is_author_date)
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| trans.push_patches(&patchnames, sequence.check_merged))
+        .execute(reflog_msg)
+        .or_else(|e| {
+            record_conflict(
+                repo,
+                matches,
+                sequence.clone(),
+                &to_push,
+                remaining.iter().cloned(),
+                &e,
+            )?;
+            Err(e)
+        })
+}
+
+/// Record the remaining steps if pushing patches stopped due to conflicts.
+///
+/// Each patch being pushed is paired with its commit prior to being pushed, or `None`
+/// if the patch was created by the operation. Nothing is recorded if `err` is not due
+/// to conflicts.
+pub(super) fn record_conflict(
+    repo: &gix::Repository,
+    matches: &ArgMatches,
+    sequence: Sequence,
+    pushing: &[(PatchName, Option<gix::ObjectId>)],
+    remaining: impl IntoIterator<Item = Step>,
+    err: &anyhow::Error,
+) -> Result<()> {
+    if !is_conflict(err) {
+        return Ok(());
+    }
+    let stack = Stack::current(repo, InitializationPolicy::RequireInitialized)?;
+    let mut sequence = sequence;
+    let mut todo: Vec<Step> = Vec::new();
+    if let Some(pos) = stack
+        .applied()
+        .last()
+        .and_then(|top| pushing.iter().position(|(pn, _)| pn == top))
+    {
+        let (patchname, commit_id) = &pushing[pos];
+        sequence.stopped = Some(Stopped {
+            patchname: patchname.clone(),
+            commit: *commit_id,
+            applied: false,
+        });
+        todo.extend(
+            pushing[pos + 1..]
+                .iter()
+                .map(|(pn, _)| Step::Push(pn.clone())),
+        );
+    }
+    todo.extend(remaining);
+    sequence.todo = todo;
+    save(repo, matches, &sequence)
+}
+
+/// Stop the operation at a `break` or failed `exec` step.
+///
+/// The patches that remain to be pushed are moved to the front of the unapplied
+/// patches so that they may also be pushed individually with `stg push`.
+fn stop(
+    stack: Stack,
+    matches: &ArgMatches,
+    sequence: Sequence,
+    step_name: &str,
+    todo: VecDeque<Step>,
+) -> Result<()> {
+    let repo = stack.repo;
+    let mut sequence = sequence;
+    let remaining: Vec<PatchName> = todo
+        .iter()
+        .filter_map(|step| match step {
+            Step::Push(pn) | Step::Spill(pn) => Some(pn.clone()),
+            _ => None,
+        })
+        .filter(|pn| stack.has_patch(pn) && !stack.is_applied(pn))
+        .collect();
+
+    if !remaining.is_empty() {
+        let unapplied: Vec<PatchName> = remaining
+            .iter()
+            .chain(
+                stack
+                    .unapplied()
+                    .iter()
+                    .filter(|pn| !remaining.contains(pn)),
+            )
+            .cloned()
+            .collect();
+        stack
+            .setup_transaction()
+            .with_output_stream(get_color_stdout(matches))
+            .transact(|trans| trans.reorder_patches(None, Some(&unapplied), None))
+            .execute(&format!("{} (stop)", sequence.operation))?;
+        print_info_message(
+            matches,
+            &format!(
+                "Stopped at `{step_name}`; remaining patches to push: {}",
+                remaining
+                    .iter()
+                    .map(PatchName::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+        );
+    }
+
+    if !todo.is_empty() {
+        sequence.todo = todo.into();
+        save(repo, matches, &sequence)?;
+    }
+    Ok(())
+}
+
+/// Save the sequence for the current stack and tell the user how to resume it.
+fn save(repo: &gix::Repository, matches: &ArgMatches, sequence: &Sequence) -> Result<()> {
+    let stack = Stack::current(repo, InitializationPolicy::RequireInitialized)?;
+    stack.save_sequence(sequence)?;
+    let operation = &sequence.operation;
+    print_info_message(
+        matches,
+        &format!(
+            "use `stg {operation} --continue` to resume, `stg {operation} --skip` to skip \
+             the current patch, or `stg {operation} --abort` to restore the original stack"
+        ),
+    );
+    Ok(())
+}
+
+/// Determine whether an error was due to a step stopping with conflicts.
+fn is_conflict(err: &anyhow::Error) -> bool {
+    if let Some(TransactionError::TransactionHalt { conflicts, .. }) =
+        err.downcast_ref::<TransactionError>()
+    {
+        *conflicts
+    } else {
+        matches!(
+            err.downcast_ref::<super::Error>(),
+            Some(super::Error::CausedConflicts(_))
+        )
+    }
+}
+
+/// Reconstruct the operation's command line matches from the recorded options.
+fn get_operation_matches(matches: &ArgMatches, sequence: &Sequence) -> Result<ArgMatches> {
+    let command = super::STGIT_COMMANDS
+        .iter()
+        .find(|command| command.name == sequence.operation)
+        .ok_or_else(|| anyhow!("unknown operation `{}`", sequence.operation))?;
+    let color = matches
+        .get_one::<String>("color")
+        .map_or("auto", String::as_str);
+    let args = [sequence.operation.clone(), format!("--color={color}")]
+        .into_iter()
+        .chain(sequence.args.iter().cloned());
+    Ok((command.make)()
+        .arg(crate::color::get_color_arg())
+        .try_get_matches_from(args)?)
+}
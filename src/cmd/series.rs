@@ -2,11 +2,11 @@
 
 //! `stg series` implementation.
 
-use std::{io::Write, str::FromStr};
+use std::{borrow::Cow, collections::HashMap, io::Write, str::FromStr};
 
 use anyhow::{anyhow, Result};
-use bstr::ByteSlice;
-use clap::{Arg, ArgGroup, ArgMatches, ValueHint};
+use bstr::{BStr, ByteSlice};
+use clap::{parser::ValueSource, Arg, ArgGroup, ArgMatches, ValueHint};
 use termcolor::WriteColor;
 
 use crate::{
@@ -15,6 +15,8 @@ use crate::{
     ext::{CommitExtended, RepositoryExtended},
     patch::{patchrange, PatchName, PatchRange, RangeConstraint},
     stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess},
+    stupid::Stupid,
+    templates::specialize_template,
 };
 
 const UNPRINTABLE: &str = "???";
@@ -289,6 +291,117 @@ fn make() -> clap::Command {
                 .action(clap::ArgAction::SetTrue)
                 .overrides_with("show-branch"),
         )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("Display patches using <template>")
+                .long_help(
+                    "Display each patch by specializing <template>.\n\
+                     \n\
+                     Placeholders use the \"%(name)s\" syntax of StGit templates; the \
+                     trailing 's' may be omitted, e.g. \"%(index) %(name)\". The \
+                     available placeholders are:\n\
+                     \n\
+                     %(name)s - patch name\n\
+                     %(branch)s - branch name\n\
+                     %(status)s - \"applied\", \"unapplied\", or \"hidden\"\n\
+                     %(prefix)s - patch status prefix, i.e. '>', '+', '-', or '!'\n\
+                     %(index)s - absolute patch index\n\
+                     %(offset)s - relative offset from topmost patch\n\
+                     %(commit)s - full commit id\n\
+                     %(shortcommit)s - abbreviated commit id\n\
+                     %(subject)s - first line of the patch description\n\
+                     %(author.name)s, %(author.email)s, %(author.date)s - author fields\n\
+                     %(committer.name)s, %(committer.email)s, %(committer.date)s - \
+                     committer fields\n\
+                     %(files)s - number of files changed\n\
+                     %(insertions)s - number of lines inserted\n\
+                     %(deletions)s - number of lines deleted\n\
+                     \n\
+                     The default template may be set with the \"stgit.series.format\" \
+                     configuration variable. The configured template is not used when \
+                     any other display option is given or with '--no-format'.",
+                )
+                .value_name("template")
+                .num_args(1)
+                .value_hint(ValueHint::Other)
+                .conflicts_with_all([
+                    "count",
+                    "author",
+                    "commit-id",
+                    "description",
+                    "empty",
+                    "no-prefix",
+                    "indices",
+                    "offsets",
+                    "show-branch",
+                ]),
+        )
+        .arg(
+            Arg::new("no-format")
+                .long("no-format")
+                .help("Do not use the stgit.series.format template")
+                .action(clap::ArgAction::SetTrue)
+                .overrides_with("format"),
+        )
+        .arg(
+            argset::json_arg()
+                .conflicts_with_all(["count", "format"])
+                .long_help(
+                    "Output the selected patches as a JSON array of patch objects. \
+                     Only the '--reverse' display option has an effect on JSON output. \
+                     Patch objects are described in the JSON OUTPUT section of stg(1).",
+                ),
+        )
+}
+
+/// Get the template for `--format` or, absent other display options, from config.
+fn get_format_template(repo: &gix::Repository, matches: &ArgMatches) -> Option<String> {
+    if let Some(template) = matches.get_one::<String>("format") {
+        Some(template.clone())
+    } else if matches.get_flag("no-format")
+        || [
+            "author",
+            "commit-id",
+            "description",
+            "empty",
+            "no-prefix",
+            "indices",
+            "offsets",
+            "show-branch",
+        ]
+        .iter()
+        .any(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+    {
+        None
+    } else {
+        repo.config_snapshot()
+            .string("stgit.series.format")
+            .and_then(|template| template.to_str().ok().map(str::to_string))
+    }
+}
+
+/// Expand `%(name)` placeholders to the `%(name)s` form used by [`specialize_template`].
+///
+/// Only placeholders with a replacement are expanded, so unknown placeholders are
+/// output as written.
+fn expand_placeholders(template: &str, replacements: &HashMap<&str, Cow<'_, BStr>>) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("%(") {
+        let Some(len) = rest[start + 2..].find(')') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let end = start + 2 + len + 1;
+        expanded.push_str(&rest[..end]);
+        rest = &rest[end..];
+        if !rest.starts_with('s') && replacements.contains_key(name) {
+            expanded.push('s');
+        }
+    }
+    expanded.push_str(rest);
+    expanded
 }
 
 #[derive(Clone)]
@@ -478,6 +591,90 @@ fn run(matches: &ArgMatches) -> Result<()> {
         return Ok(());
     }
 
+    if matches.get_flag("json") {
+        if matches.get_flag("reverse") {
+            patches.reverse();
+        }
+        let patches = patches
+            .iter()
+            .map(|Entry { patchname, .. }| super::json::Patch::new(&stack, patchname))
+            .collect::<Result<Vec<_>>>()?;
+        return super::json::print(&patches);
+    }
+
+    if let Some(template) = get_format_template(&repo, matches) {
+        if matches.get_flag("reverse") {
+            patches.reverse();
+        }
+        let stupid = repo.stupid();
+        let need_diffstat = ["files", "insertions", "deletions"]
+            .iter()
+            .any(|name| template.contains(&format!("%({name})")));
+        let branch_name = stack.get_branch_name().to_string();
+        let mut stdout = std::io::stdout().lock();
+        for Entry {
+            patchname,
+            commit_id,
+            sigil,
+            index,
+            offset_from_top,
+        } in patches
+        {
+            let commit = repo.find_commit(commit_id)?;
+            let commit_ref = commit.decode()?;
+            let author = commit_ref.author();
+            let committer = commit_ref.committer();
+            let status = match sigil {
+                '>' | '+' => "applied",
+                '-' => "unapplied",
+                _ => "hidden",
+            };
+            let mut replacements: HashMap<&str, Cow<'_, BStr>> = HashMap::new();
+            replacements.insert("name", Cow::Owned(patchname.to_string().into()));
+            replacements.insert("branch", Cow::Borrowed(branch_name.as_str().into()));
+            replacements.insert("status", Cow::Borrowed(status.into()));
+            replacements.insert("prefix", Cow::Owned(sigil.to_string().into()));
+            replacements.insert("index", Cow::Owned(index.to_string().into()));
+            replacements.insert("offset", Cow::Owned(format!("{offset_from_top:+}").into()));
+            replacements.insert("commit", Cow::Owned(commit_id.to_string().into()));
+            replacements.insert(
+                "shortcommit",
+                Cow::Owned(commit.id().shorten_or_id().to_string().into()),
+            );
+            replacements.insert("subject", commit_ref.message_summary());
+            replacements.insert("author.name", Cow::Borrowed(author.name));
+            replacements.insert("author.email", Cow::Borrowed(author.email));
+            replacements.insert(
+                "author.date",
+                Cow::Owned(author.time.format(gix::date::time::format::ISO8601).into()),
+            );
+            replacements.insert("committer.name", Cow::Borrowed(committer.name));
+            replacements.insert("committer.email", Cow::Borrowed(committer.email));
+            replacements.insert(
+                "committer.date",
+                Cow::Owned(
+                    committer
+                        .time
+                        .format(gix::date::time::format::ISO8601)
+                        .into(),
+                ),
+            );
+            if need_diffstat {
+                let (files, insertions, deletions) = stupid.diff_tree_numstat(
+                    commit.get_parent_commit()?.tree_id()?.detach(),
+                    commit.tree_id()?.detach(),
+                )?;
+                replacements.insert("files", Cow::Owned(files.to_string().into()));
+                replacements.insert("insertions", Cow::Owned(insertions.to_string().into()));
+                replacements.insert("deletions", Cow::Owned(deletions.to_string().into()));
+            }
+            let template = expand_placeholders(&template, &replacements);
+            stdout.write_all(&specialize_template(&template, &replacements))?;
+            writeln!(stdout)?;
+        }
+        return Ok(());
+    }
+
     let opt_commit_id = matches.get_one::<CommitIdLength>("commit-id");
     let description_flag = matches.get_flag("description");
     let author_flag = matches.get_flag("author");
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg series-version diff` implementation.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Arg;
+
+use crate::{
+    argset,
+    branchloc::BranchLocator,
+    color::use_color,
+    ext::RepositoryExtended,
+    patch::PatchName,
+    stack::{InitializationPolicy, Stack, StackAccess, StackState},
+};
+
+pub(super) fn command() -> clap::Command {
+    clap::Command::new("diff")
+        .about("Compare saved series versions")
+        .long_about(
+            "Compare two saved series versions, or a saved series version with the \
+             current series. Patches are matched by name and compared using \
+             git-range-diff(1). Patches only present in one of the versions are \
+             reported as added or removed.",
+        )
+        .override_usage(super::super::make_usage(
+            "stg series-version diff",
+            &["[OPTIONS] <old-version> [new-version] [-p <patch>...]"],
+        ))
+        .arg(argset::branch_arg())
+        .arg(
+            Arg::new("old-version")
+                .help("Older series version")
+                .value_name("old-version")
+                .required(true)
+                .value_parser(clap::builder::NonEmptyStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("new-version")
+                .help("Newer series version")
+                .long_help("Newer series version. Defaults to the current series.")
+                .value_name("new-version")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new()),
+        )
+        .arg(
+            Arg::new("patches")
+                .long("patch")
+                .short('p')
+                .help("Only compare <patch>")
+                .long_help("Only compare <patch>. May be specified multiple times.")
+                .value_name("patch")
+                .action(clap::ArgAction::Append)
+                .value_parser(clap::value_parser!(PatchName)),
+        )
+        .arg(argset::diff_opts_arg())
+}
+
+pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::from_branch_locator(
+        &repo,
+        matches.get_one::<BranchLocator>("branch"),
+        InitializationPolicy::RequireInitialized,
+    )?;
+    let config = repo.config_snapshot();
+    let diff_opts = argset::get_diff_opts(matches, &config, false, false);
+
+    let old_version = stack.series_version(argset::get_one_str(matches, "old-version").unwrap())?;
+    let old_state = StackState::from_commit(&repo, &old_version.state_commit)?;
+    let new_state = if let Some(name) = argset::get_one_str(matches, "new-version") {
+        StackState::from_commit(&repo, &stack.series_version(name)?.state_commit)?
+    } else {
+        let state_commit = repo
+            .find_reference(stack.get_stack_refname())?
+            .peel_to_commit()?;
+        StackState::from_commit(&repo, &state_commit)?
+    };
+    let patchnames: Option<Vec<PatchName>> = matches
+        .get_many::<PatchName>("patches")
+        .map(|pns| pns.cloned().collect());
+
+    let mut output = Vec::new();
+    super::super::range_diff::compare_states(
+        &repo,
+        &old_state,
+        &new_state,
+        patchnames,
+        use_color(matches),
+        &diff_opts,
+        &mut output,
+    )?;
+    std::io::stdout().write_all(&output)?;
+    Ok(())
+}
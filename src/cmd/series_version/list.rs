@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg series-version list` implementation.
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::{
+    argset,
+    branchloc::BranchLocator,
+    ext::RepositoryExtended,
+    stack::{InitializationPolicy, Stack, StackState, StackStateAccess},
+};
+
+pub(super) fn command() -> clap::Command {
+    clap::Command::new("list")
+        .about("List saved series versions")
+        .long_about(
+            "List saved series versions. Each line shows the version name, the \
+             stack state commit id, and the number of applied patches in the saved \
+             series.",
+        )
+        .arg(argset::branch_arg())
+}
+
+pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::from_branch_locator(
+        &repo,
+        matches.get_one::<BranchLocator>("branch"),
+        InitializationPolicy::RequireInitialized,
+    )?;
+    let mut stdout = std::io::stdout().lock();
+    for version in stack.series_versions()? {
+        let state = StackState::from_commit(&repo, &version.state_commit)?;
+        let short_id = version.state_commit.id().shorten_or_id();
+        let num_applied = state.applied().len();
+        let plural = if num_applied == 1 { "" } else { "es" };
+        writeln!(
+            stdout,
+            "{}  {short_id}  {num_applied} patch{plural}",
+            version.name
+        )?;
+    }
+    Ok(())
+}
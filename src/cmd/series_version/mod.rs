@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg series-version` implementation.
+
+mod diff;
+mod list;
+mod save;
+mod show;
+
+use anyhow::Result;
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "series-version",
+    category: super::CommandCategory::StackManipulation,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Manage saved versions of the patch series")
+        .long_about(
+            "Manage saved versions of the patch series.\n\
+             \n\
+             A series version is a named, immutable snapshot of the stack's state. \
+             Saving a series version each time the series is sent for review, e.g. \
+             with `stg email`, makes it possible to later see exactly what was sent \
+             as v1, v2, etc. and how the series has changed since.\n\
+             \n\
+             Series versions are recorded as references to stack state commits \
+             under `refs/stacks/<branch>.versions/`. Versions named 'v<n>' are \
+             numbered; `stg email format` uses the saved numbered versions to \
+             determine the reroll count and to generate a range-diff against the \
+             previous version.",
+        )
+        .subcommand_required(true)
+        .subcommand(save::command())
+        .subcommand(list::command())
+        .subcommand(show::command())
+        .subcommand(diff::command())
+}
+
+fn run(matches: &clap::ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("save", sub_matches)) => save::dispatch(sub_matches),
+        Some(("list", sub_matches)) => list::dispatch(sub_matches),
+        Some(("show", sub_matches)) => show::dispatch(sub_matches),
+        Some(("diff", sub_matches)) => diff::dispatch(sub_matches),
+        _ => panic!("valid subcommand is expected"),
+    }
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg series-version save` implementation.
+
+use anyhow::Result;
+use clap::Arg;
+
+use crate::{
+    argset,
+    branchloc::BranchLocator,
+    ext::RepositoryExtended,
+    stack::{InitializationPolicy, Stack},
+};
+
+pub(super) fn command() -> clap::Command {
+    clap::Command::new("save")
+        .about("Save the current series as a new version")
+        .long_about(
+            "Save the current stack state as a new series version. By default, the \
+             version is named 'v<n>' where <n> is one more than the highest saved \
+             version number. Saved versions are immutable; saving a version with the \
+             name of an existing version is an error.",
+        )
+        .arg(argset::branch_arg())
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .short('n')
+                .help("Use <name> for the saved version")
+                .value_name("name")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new()),
+        )
+}
+
+pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::from_branch_locator(
+        &repo,
+        matches.get_one::<BranchLocator>("branch"),
+        InitializationPolicy::RequireInitialized,
+    )?;
+    let name = if let Some(name) = argset::get_one_str(matches, "name") {
+        name.to_string()
+    } else {
+        stack.next_series_version_name()?
+    };
+    stack.save_series_version(&name)?;
+    println!("Saved series version {name}");
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg series-version show` implementation.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Arg;
+
+use crate::{
+    argset,
+    branchloc::BranchLocator,
+    ext::{CommitExtended, RepositoryExtended},
+    patch::PatchName,
+    stack::{InitializationPolicy, Stack, StackState, StackStateAccess},
+};
+
+pub(super) fn command() -> clap::Command {
+    clap::Command::new("show")
+        .about("Show the patches of a saved series version")
+        .long_about(
+            "Show the patches of a saved series version. The patches are shown in \
+             the same form as `stg series --description`, with the commit id of \
+             each patch.",
+        )
+        .arg(argset::branch_arg())
+        .arg(
+            Arg::new("version")
+                .help("Series version to show")
+                .value_name("version")
+                .required(true)
+                .value_parser(clap::builder::NonEmptyStringValueParser::new()),
+        )
+}
+
+pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::from_branch_locator(
+        &repo,
+        matches.get_one::<BranchLocator>("branch"),
+        InitializationPolicy::RequireInitialized,
+    )?;
+    let version = stack.series_version(argset::get_one_str(matches, "version").unwrap())?;
+    let state = StackState::from_commit(&repo, &version.state_commit)?;
+
+    let mut stdout = std::io::stdout().lock();
+    let applied = state.applied();
+    for (i, patchname) in applied.iter().enumerate() {
+        let prefix = if i + 1 == applied.len() { '>' } else { '+' };
+        show_patch(&mut stdout, &state, prefix, patchname)?;
+    }
+    for patchname in state.unapplied() {
+        show_patch(&mut stdout, &state, '-', patchname)?;
+    }
+    for patchname in state.hidden() {
+        show_patch(&mut stdout, &state, '!', patchname)?;
+    }
+    Ok(())
+}
+
+fn show_patch(
+    stdout: &mut impl Write,
+    state: &StackState,
+    prefix: char,
+    patchname: &PatchName,
+) -> Result<()> {
+    let commit = state.get_patch_commit(patchname);
+    let short_id = commit.id().shorten_or_id();
+    let message = commit.message_ex();
+    let message = message.decode()?;
+    let subject = message.lines().next().unwrap_or_default();
+    writeln!(stdout, "{prefix} {short_id} {patchname} # {subject}")?;
+    Ok(())
+}
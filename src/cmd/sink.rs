@@ -10,7 +10,7 @@ use crate::{
     color::get_color_stdout,
     ext::RepositoryExtended,
     patch::{patchrange, LocationConstraint, PatchLocator, PatchName, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stack::{DependencyGraph, InitializationPolicy, Stack, StackStateAccess},
     stupid::Stupid,
 };
 
@@ -94,6 +94,11 @@ fn make() -> clap::Command {
                 .value_parser(clap::value_parser!(PatchLocator))
                 .conflicts_with("target-below"),
         )
+        .arg(argset::with_deps_arg().long_help(
+            "Also sink the patches that the sunk patches depend on, unless they are \
+             already below the target position. See `stg deps`.",
+        ))
+        .arg(argset::ignore_deps_arg())
         .arg(argset::keep_arg())
         .arg(argset::committer_date_is_author_date_arg())
 }
@@ -151,6 +156,52 @@ fn run(matches: &ArgMatches) -> Result<()> {
         }
     }
 
+    // Applied patches below this position are not moved by the sink.
+    let bottom_pos = if let Some(target_patch) = &opt_target {
+        stack
+            .applied()
+            .iter()
+            .position(|pn| pn == target_patch)
+            .expect("already validated that target is applied")
+            + if is_above { 1 } else { 0 }
+    } else {
+        0
+    };
+
+    let graph = if matches.get_flag("ignore-deps") {
+        None
+    } else {
+        let start = patches
+            .iter()
+            .map(|pn| stack.index_of(pn))
+            .chain([bottom_pos])
+            .min()
+            .expect("chain is not empty");
+        let window = stack.applied_and_unapplied().skip(start).cloned().collect();
+        Some(DependencyGraph::new(&stack, window)?)
+    };
+
+    let patches = match &graph {
+        Some(graph) if matches.get_flag("with-deps") => {
+            let below_target = &stack.applied()[..bottom_pos];
+            graph.expand(&patches, |graph, pn| {
+                let mut related = graph.dependencies(pn);
+                related.retain(|pn| !below_target.contains(pn));
+                related
+            })
+        }
+        _ => patches,
+    };
+
+    if let Some(target_patch) = &opt_target {
+        if patches.contains(target_patch) {
+            return Err(anyhow!(
+                "cannot sink below target patch `{target_patch}` \
+                 since the patches to sink depend on it",
+            ));
+        }
+    }
+
     let mut remaining_unapplied: Vec<PatchName> = stack
         .unapplied()
         .iter()
@@ -195,6 +246,10 @@ fn run(matches: &ArgMatches) -> Result<()> {
         (applied, remaining_unapplied)
     };
 
+    if let Some(graph) = &graph {
+        graph.check(&applied)?;
+    }
+
     stack
         .setup_transaction()
         .use_index_and_worktree(true)
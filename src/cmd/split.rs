@@ -0,0 +1,436 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg split` implementation.
+
+use std::{
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Result};
+use bstr::BString;
+use clap::{Arg, ArgGroup, ArgMatches, ValueHint};
+
+use crate::{
+    color::get_color_stdout,
+    ext::{CommitExtended, RepositoryExtended},
+    patch::{patchedit, PatchLocator, PatchName},
+    stack::{InitializationPolicy, Stack, StackStateAccess},
+    stupid::{parse_diff, FileDiff, Stupid, StupidContext},
+};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "split",
+    category: super::CommandCategory::PatchManipulation,
+    make,
+    run,
+};
+
+fn make() -> clap::Command {
+    let app = clap::Command::new(STGIT_COMMAND.name)
+        .about("Split a patch into several patches")
+        .long_about(
+            "Split a patch into two or more patches, each containing a subset of the \
+             original patch's changes. The new patches take the place of the original \
+             patch in the stack; the first new patch keeps the original patch's name \
+             and the combined changes of the new patches are the same as the original \
+             patch's changes. The topmost patch is split if no patch is specified.\n\
+             \n\
+             The changes may be split by file using the '--path' option. Each use of \
+             '--path' creates a new patch with the changes to the files matching the \
+             given comma-separated paths. The changes to any remaining files go into \
+             a final new patch.\n\
+             \n\
+             Alternatively, with '--interactive', the changes for each new patch are \
+             chosen hunk-by-hunk, similar to 'git add --patch'. Selecting all of the \
+             remaining hunks finishes the split; selecting none of them puts the \
+             remaining hunks into a final new patch.\n\
+             \n\
+             The editor is invoked for the message of each new patch, which is \
+             initially the message of the original patch. The author of each new \
+             patch is that of the original patch.\n\
+             \n\
+             The split is recorded as a single entry in the stack log and may thus be \
+             undone with one 'stg undo'.",
+        )
+        .override_usage(
+            "stg split [OPTIONS] --path <path>[,<path>...]... [patch]\n       \
+             stg split [OPTIONS] --interactive [patch]",
+        )
+        .arg(
+            Arg::new("patch")
+                .help("Patch to split")
+                .allow_hyphen_values(true)
+                .value_parser(clap::value_parser!(PatchLocator))
+                .value_hint(ValueHint::Other),
+        )
+        .next_help_heading("Split Options")
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .help("Split off changes to files matching <path>[,<path>...] into a new patch")
+                .long_help(
+                    "Split off the changes to files matching the given comma-separated \
+                     paths into a new patch. This option may be repeated to create \
+                     several new patches.",
+                )
+                .value_name("path")
+                .num_args(1)
+                .value_delimiter(',')
+                .action(clap::ArgAction::Append)
+                .value_parser(clap::value_parser!(PathBuf))
+                .value_hint(ValueHint::AnyPath),
+        )
+        .arg(
+            Arg::new("interactive")
+                .long("interactive")
+                .short('i')
+                .help("Interactively select the hunks for each new patch")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .group(
+            ArgGroup::new("split-mode")
+                .args(["path", "interactive"])
+                .required(true),
+        );
+    patchedit::add_args(app, false, false)
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stack = Stack::current(&repo, InitializationPolicy::AllowUninitialized)?;
+    let stupid = repo.stupid();
+
+    repo.check_repository_state()?;
+    let statuses = stupid.statuses(None)?;
+    statuses.check_conflicts()?;
+    stack.check_head_top_mismatch()?;
+
+    let patchname = if let Some(patch_loc) = matches.get_one::<PatchLocator>("patch") {
+        patch_loc.resolve_name(&stack)?
+    } else if let Some(top_patchname) = stack.applied().last() {
+        top_patchname.clone()
+    } else {
+        return Err(super::Error::NoAppliedPatches.into());
+    };
+
+    if !stack.is_applied(&patchname) && !stack.unapplied().contains(&patchname) {
+        return Err(anyhow!("cannot split hidden patch `{patchname}`"));
+    }
+
+    let patch_commit = stack.get_patch_commit(&patchname).clone();
+    let parent_commit = patch_commit.get_parent_commit()?;
+    let parent_tree_id = parent_commit.tree_id()?.detach();
+    let tree_id = patch_commit.tree_id()?.detach();
+
+    if parent_tree_id == tree_id {
+        return Err(anyhow!("cannot split empty patch `{patchname}`"));
+    }
+
+    let tree_ids = if let Some(path_groups) = matches.get_occurrences::<PathBuf>("path") {
+        split_by_paths(&stupid, parent_tree_id, tree_id, path_groups)?
+    } else {
+        split_interactive(&stupid, parent_tree_id, tree_id)?
+    };
+
+    if tree_ids.len() < 2 {
+        return Err(anyhow!(
+            "patch `{patchname}` not split; \
+             all changes would remain in a single patch"
+        ));
+    }
+
+    let author = patch_commit.author_strict()?;
+    let message = patch_commit.message_ex().decode()?.to_string();
+
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .committer_date_is_author_date(matches.get_flag("committer-date-is-author-date"))
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| {
+            let is_applied = trans.is_applied(&patchname);
+            let to_push = if is_applied {
+                trans.pop_patches(|pn| pn == &patchname)?
+            } else {
+                Vec::new()
+            };
+            let position = trans
+                .unapplied()
+                .iter()
+                .position(|pn| pn == &patchname)
+                .expect("patch to split is unapplied");
+
+            let mut new_patchnames: Vec<PatchName> = Vec::with_capacity(tree_ids.len());
+            let mut parent_id = parent_commit.id;
+
+            for (i, tree_id) in tree_ids.iter().enumerate() {
+                let builder = patchedit::EditBuilder::default()
+                    .override_tree_id(*tree_id)
+                    .override_parent_id(parent_id)
+                    .allow_implicit_edit(true)
+                    .allow_diff_edit(false)
+                    .allow_template_save(false);
+                let builder = if i == 0 {
+                    builder
+                        .original_patchname(Some(&patchname))
+                        .existing_patch_commit(&patch_commit)
+                } else {
+                    builder
+                        .template_patchname(None)
+                        .default_author(author.clone())
+                        .default_message(message.clone())
+                };
+
+                let patchedit::EditOutcome::Edited {
+                    new_patchname,
+                    new_commit_id,
+                } = builder.edit(trans, &repo, matches)?
+                else {
+                    panic!("template save not allowed for split")
+                };
+
+                if i == 0 {
+                    let commit_id = new_commit_id.expect("first patch's tree is changed");
+                    trans.update_patch(&patchname, commit_id)?;
+                    if let Some(new_patchname) = new_patchname.as_ref() {
+                        trans.rename_patch(&patchname, new_patchname)?;
+                    }
+                    new_patchnames.push(new_patchname.unwrap_or_else(|| patchname.clone()));
+                    parent_id = commit_id;
+                } else {
+                    let new_patchname =
+                        new_patchname.expect("new patch must have a name after edit");
+                    let commit_id = new_commit_id.expect("new patch must have a commit");
+                    trans.new_unapplied(&new_patchname, commit_id, position + i)?;
+                    new_patchnames.push(new_patchname);
+                    parent_id = commit_id;
+                }
+            }
+
+            if is_applied {
+                trans.push_patches(&new_patchnames, false)?;
+                trans.push_patches(&to_push, false)?;
+            }
+            Ok(())
+        })
+        .execute(&format!("split {patchname}"))?;
+
+    Ok(())
+}
+
+/// Split changes between two trees into a tree for each group of paths.
+///
+/// The changes to files not matching any of the path groups are captured in the final
+/// tree, which is always `tree_id`.
+fn split_by_paths<'a>(
+    stupid: &StupidContext,
+    parent_tree_id: gix::ObjectId,
+    tree_id: gix::ObjectId,
+    path_groups: impl Iterator<Item = impl Iterator<Item = &'a PathBuf>>,
+) -> Result<Vec<gix::ObjectId>> {
+    let mut tree_ids = Vec::new();
+    let mut current_tree_id = parent_tree_id;
+
+    for paths in path_groups {
+        let paths: Vec<&PathBuf> = paths.collect();
+        let new_tree_id = stupid.with_temp_index(|stupid_temp| {
+            stupid_temp.read_tree(current_tree_id)?;
+            if stupid_temp.apply_pathlimited_treediff_to_index(
+                current_tree_id,
+                tree_id,
+                false,
+                &paths,
+            )? {
+                stupid_temp.write_tree()
+            } else {
+                Err(anyhow!("failed to split off changes to paths"))
+            }
+        })?;
+
+        if new_tree_id == current_tree_id {
+            let paths = paths
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(",");
+            return Err(anyhow!("no remaining changes match `--path {paths}`"));
+        }
+
+        tree_ids.push(new_tree_id);
+        current_tree_id = new_tree_id;
+    }
+
+    if current_tree_id != tree_id {
+        tree_ids.push(tree_id);
+    }
+
+    Ok(tree_ids)
+}
+
+/// Interactively split changes between two trees into several trees.
+///
+/// For each new tree, the user is prompted to select from the remaining hunks. The
+/// final tree is always `tree_id`.
+fn split_interactive(
+    stupid: &StupidContext,
+    parent_tree_id: gix::ObjectId,
+    tree_id: gix::ObjectId,
+) -> Result<Vec<gix::ObjectId>> {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut tree_ids = Vec::new();
+    let mut current_tree_id = parent_tree_id;
+
+    while current_tree_id != tree_id {
+        let diff = stupid.diff_tree_patch(
+            current_tree_id,
+            tree_id,
+            None::<Vec<&str>>,
+            false,
+            ["--binary", "--no-ext-diff", "--no-renames"],
+        )?;
+        let file_diffs = parse_diff(diff.as_ref())?;
+
+        println!("Select changes for new patch #{}", tree_ids.len() + 1);
+        let selected_diff = select_hunks(&file_diffs, &mut input)?;
+
+        if selected_diff.is_empty() {
+            break;
+        }
+
+        let new_tree_id = stupid.with_temp_index(|stupid_temp| {
+            stupid_temp.read_tree(current_tree_id)?;
+            stupid_temp.apply_to_index(selected_diff.as_ref())?;
+            stupid_temp.write_tree()
+        })?;
+        tree_ids.push(new_tree_id);
+        current_tree_id = new_tree_id;
+    }
+
+    if current_tree_id != tree_id {
+        tree_ids.push(tree_id);
+    }
+
+    Ok(tree_ids)
+}
+
+const SELECT_HELP: &str = "\
+y - include this hunk
+n - do not include this hunk
+q - quit; do not include this hunk or any of the remaining ones
+a - include this hunk and all later hunks in the file
+d - do not include this hunk or any of the later hunks in the file
+? - print help";
+
+/// Prompt the user to select hunks, returning a diff of the selected hunks.
+///
+/// Changes without textual hunks, e.g. binary changes, are selected as a whole.
+fn select_hunks(file_diffs: &[FileDiff], input: &mut impl BufRead) -> Result<BString> {
+    let mut selected_diff = BString::default();
+    let mut stdout = std::io::stdout();
+    let unit_count: usize = file_diffs
+        .iter()
+        .map(|file_diff| file_diff.hunks.len().max(1))
+        .sum();
+    let mut unit_number = 0;
+    let mut quit = false;
+
+    for file_diff in file_diffs {
+        // Each hunk's text, or None for the single unit of a file without hunks.
+        let units: Vec<Option<BString>> = if file_diff.hunks.is_empty() {
+            vec![None]
+        } else {
+            file_diff
+                .hunks
+                .iter()
+                .map(|hunk| {
+                    let mut text = BString::from(hunk.header);
+                    text.push(b'\n');
+                    text.extend_from_slice(hunk.body);
+                    Some(text)
+                })
+                .collect()
+        };
+
+        let mut file_choice: Option<bool> = None;
+        let mut included: Vec<&Option<BString>> = Vec::new();
+
+        for (i, unit) in units.iter().enumerate() {
+            unit_number += 1;
+            let include = if quit {
+                false
+            } else if let Some(choice) = file_choice {
+                choice
+            } else {
+                if i == 0 {
+                    stdout.write_all(file_diff.header)?;
+                }
+                if let Some(text) = unit {
+                    stdout.write_all(text)?;
+                }
+                prompt(
+                    &mut stdout,
+                    input,
+                    &format!(
+                        "({unit_number}/{unit_count}) Include this {}",
+                        if unit.is_some() { "hunk" } else { "change" }
+                    ),
+                    &mut quit,
+                    &mut file_choice,
+                )?
+            };
+            if include {
+                included.push(unit);
+            }
+        }
+
+        if !included.is_empty() {
+            selected_diff.extend_from_slice(file_diff.header);
+            for text in included.into_iter().flatten() {
+                selected_diff.extend_from_slice(text);
+            }
+        }
+    }
+
+    Ok(selected_diff)
+}
+
+/// Prompt whether to include a hunk, returning the user's choice.
+///
+/// The `quit` and `file_choice` flags are updated for answers that also apply to
+/// subsequent hunks.
+fn prompt(
+    stdout: &mut impl Write,
+    input: &mut impl BufRead,
+    question: &str,
+    quit: &mut bool,
+    file_choice: &mut Option<bool>,
+) -> Result<bool> {
+    loop {
+        write!(stdout, "{question} [y,n,q,a,d,?]? ")?;
+        stdout.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(stdout)?;
+            answer.push('q');
+        }
+        match answer.trim() {
+            "y" => return Ok(true),
+            "n" => return Ok(false),
+            "q" => {
+                *quit = true;
+                return Ok(false);
+            }
+            "a" => {
+                *file_choice = Some(true);
+                return Ok(true);
+            }
+            "d" => {
+                *file_choice = Some(false);
+                return Ok(false);
+            }
+            _ => writeln!(stdout, "{SELECT_HELP}")?,
+        }
+    }
+}
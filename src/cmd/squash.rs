@@ -12,7 +12,7 @@ use crate::{
     ext::{CommitExtended, RepositoryExtended, SignatureExtended},
     patch::{patchedit, patchrange, PatchName, PatchRange, RangeConstraint},
     print_info_message,
-    stack::{InitializationPolicy, Stack, StackStateAccess, StackTransaction},
+    stack::{InitializationPolicy, PatchMeta, Stack, StackStateAccess, StackTransaction},
     stupid::Stupid,
 };
 
@@ -178,6 +178,7 @@ pub(super) fn squash(
     patchname: Option<&PatchName>,
     should_push_squashed: bool,
 ) -> Result<PatchName> {
+    let meta = PatchMeta::merge(patchnames.iter().map(|pn| trans.get_patch_meta(pn)));
     let (new_patchname, commit_id, to_push) = if let Some((new_patchname, commit_id)) =
         try_squash(trans, matches, patchnames, patchname)?
     {
@@ -202,6 +203,7 @@ pub(super) fn squash(
     };
 
     trans.new_unapplied(&new_patchname, commit_id, 0)?;
+    trans.update_patch_meta(&new_patchname, meta);
 
     let mut to_push = to_push;
 
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg stack fetch` implementation.
+
+use anyhow::Result;
+use bstr::ByteSlice;
+use clap::Arg;
+
+use super::REMOTE_STACKS_PREFIX;
+use crate::{ext::RepositoryExtended, print_info_message, stupid::Stupid};
+
+pub(super) fn command() -> clap::Command {
+    clap::Command::new("fetch")
+        .about("Fetch stacks from a remote")
+        .long_about(
+            "Fetch the stacks of the given branches, or of all branches, from a \
+             remote. Each fetched stack is recorded as \
+             `refs/remote-stacks/<remote>/<branch>`, replacing any previously fetched \
+             state of that stack.",
+        )
+        .arg(
+            Arg::new("remote")
+                .help("Remote to fetch stacks from")
+                .required(true),
+        )
+        .arg(
+            Arg::new("branches")
+                .help("Branches whose stacks to fetch")
+                .value_name("branch")
+                .num_args(1..),
+        )
+}
+
+pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let stupid = repo.stupid();
+    let remote = matches
+        .get_one::<String>("remote")
+        .expect("remote is required");
+    let mut branches: Vec<String> = if let Some(branches) = matches.get_many::<String>("branches") {
+        branches.cloned().collect()
+    } else {
+        stupid
+            .ls_remote_refs(remote, "refs/stacks/*")?
+            .iter()
+            .filter_map(|refname| refname.strip_prefix(b"refs/stacks/"))
+            .map(|branch| branch.to_str_lossy().to_string())
+            .filter(|branch| is_stack_state_branch(branch))
+            .collect()
+    };
+    branches.sort();
+    branches.dedup();
+    if branches.is_empty() {
+        return Ok(());
+    }
+
+    let refspecs: Vec<String> = branches
+        .iter()
+        .map(|branch| format!("+refs/stacks/{branch}:{REMOTE_STACKS_PREFIX}/{remote}/{branch}"))
+        .collect();
+    stupid.fetch(remote, &refspecs)?;
+
+    for branch in branches {
+        print_info_message(matches, &format!("Fetched stack `{remote}/{branch}`"));
+    }
+    Ok(())
+}
+
+/// Determine whether a name under `refs/stacks/` is that of a stack state reference
+/// rather than one of the `<branch>.versions/` or `<branch>.published/` namespaces kept
+/// alongside it.
+fn is_stack_state_branch(name: &str) -> bool {
+    name.split('/')
+        .all(|component| !component.ends_with(".versions") && !component.ends_with(".published"))
+}
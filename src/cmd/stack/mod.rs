@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg stack` implementation.
+
+mod fetch;
+mod push;
+
+use anyhow::{anyhow, Result};
+
+pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
+    name: "stack",
+    category: super::CommandCategory::StackManipulation,
+    make,
+    run,
+};
+
+/// Prefix of the references under which `stg stack fetch` records remote stacks.
+const REMOTE_STACKS_PREFIX: &str = "refs/remote-stacks";
+
+fn make() -> clap::Command {
+    clap::Command::new(STGIT_COMMAND.name)
+        .about("Share stacks over git remotes")
+        .long_about(
+            "Share stacks over git remotes.\n\
+             \n\
+             The state of a StGit stack, including its applied, unapplied, and \
+             hidden patches, is recorded by the `refs/stacks/<branch>` reference. \
+             Pushing this reference to a remote repository allows others to fetch \
+             the stack and synchronize their own patches with it using `stg sync \
+             --from-stack`.\n\
+             \n\
+             Fetched stacks are recorded as `refs/remote-stacks/<remote>/<branch>` \
+             and may be referred to as `<remote>/<branch>`.",
+        )
+        .subcommand_required(true)
+        .subcommand(fetch::command())
+        .subcommand(push::command())
+}
+
+fn run(matches: &clap::ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("fetch", sub_matches)) => fetch::dispatch(sub_matches),
+        Some(("push", sub_matches)) => push::dispatch(sub_matches),
+        _ => panic!("valid subcommand is expected"),
+    }
+}
+
+/// Resolve a stack specification to a stack state reference.
+///
+/// The specification may be `<remote>/<branch>` for a stack fetched with `stg stack
+/// fetch`, the name of a local branch with a stack, or a full reference name.
+pub(super) fn resolve_stack_ref<'repo>(
+    repo: &'repo gix::Repository,
+    spec: &str,
+) -> Result<gix::Reference<'repo>> {
+    let candidates = if spec.starts_with("refs/") {
+        vec![spec.to_string()]
+    } else {
+        vec![
+            format!("{REMOTE_STACKS_PREFIX}/{spec}"),
+            crate::stack::state_refname_from_branch_name(spec),
+        ]
+    };
+    candidates
+        .iter()
+        .find_map(|refname| repo.find_reference(refname.as_str()).ok())
+        .ok_or_else(|| {
+            anyhow!("stack `{spec}` not found; remote stacks may be fetched with `stg stack fetch`")
+        })
+}
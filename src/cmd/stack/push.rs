@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! `stg stack push` implementation.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use clap::Arg;
+
+use crate::{
+    ext::RepositoryExtended,
+    stack::{InitializationPolicy, Stack, StackAccess},
+    stupid::Stupid,
+    wrap::PartialRefName,
+};
+
+pub(super) fn command() -> clap::Command {
+    clap::Command::new("push")
+        .about("Push stacks to a remote")
+        .long_about(
+            "Push the stacks of the given branches, or of the current branch, to a \
+             remote. The `refs/stacks/<branch>` reference of each stack is pushed \
+             along with the commits of all of the stack's patches. The remote stack \
+             is only updated if it is an ancestor of the local stack state, unless \
+             '--force' is given.",
+        )
+        .arg(
+            Arg::new("remote")
+                .help("Remote to push stacks to")
+                .required(true),
+        )
+        .arg(
+            Arg::new("branches")
+                .help("Branches whose stacks to push")
+                .value_name("branch")
+                .num_args(1..)
+                .value_parser(clap::value_parser!(PartialRefName)),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .short('f')
+                .help("Overwrite remote stacks that are not ancestors of the local stacks")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+pub(super) fn dispatch(matches: &clap::ArgMatches) -> Result<()> {
+    let repo = gix::Repository::open()?;
+    let remote = matches
+        .get_one::<String>("remote")
+        .expect("remote is required");
+
+    let stacks: Vec<Stack> =
+        if let Some(branchnames) = matches.get_many::<PartialRefName>("branches") {
+            branchnames
+                .map(|name| {
+                    Stack::from_branch_name(&repo, name, InitializationPolicy::RequireInitialized)
+                })
+                .collect::<Result<_>>()?
+        } else {
+            let branch = repo.get_current_branch()?;
+            let branchname = PartialRefName::from_str(branch.get_branch_name()?)?;
+            vec![Stack::from_branch_name(
+                &repo,
+                &branchname,
+                InitializationPolicy::RequireInitialized,
+            )?]
+        };
+
+    let refspecs: Vec<String> = stacks
+        .iter()
+        .map(|stack| {
+            let refname = stack.get_stack_refname();
+            format!("{refname}:{refname}")
+        })
+        .collect();
+    repo.stupid()
+        .push(remote, &refspecs, matches.get_flag("force"))
+}
@@ -18,7 +18,11 @@ use crate::{
     color::get_color_stdout,
     ext::{CommitExtended, RepositoryExtended},
     patch::{patchrange, PatchName, PatchRange, RangeConstraint},
-    stack::{InitializationPolicy, Stack, StackAccess, StackStateAccess, StackTransaction},
+    print_info_message,
+    stack::{
+        InitializationPolicy, Stack, StackAccess, StackState, StackStateAccess, StackTransaction,
+        Step,
+    },
     stupid::Stupid,
 };
 
@@ -30,18 +34,33 @@ pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
 };
 
 fn make() -> clap::Command {
-    clap::Command::new(STGIT_COMMAND.name)
-        .about("Synchronize patches with a branch or a series")
+    let app = clap::Command::new(STGIT_COMMAND.name)
+        .about("Synchronize patches with a branch, stack, or series")
         .long_about(
             "For each of the specified patches, perform a three-way merge with the \
-             same patch in the specified branch or series. The command can be used for \
-             keeping patches on several branches in sync. Note that the operation may \
-             fail for some patches because of conflicts. The patches in the series \
-             must apply cleanly.",
+             same patch in the specified branch, stack, or series. The command can be \
+             used for keeping patches on several branches in sync. Note that the \
+             operation may fail for some patches because of conflicts. The patches in \
+             the series must apply cleanly.\n\
+             \n\
+             With '--from-stack', patches are synchronized with the patches recorded \
+             in a stack state reference, such as a teammate's stack fetched with 'stg \
+             stack fetch'. The patches added, removed, and renamed in that stack \
+             relative to the current stack are reported. A patch renamed in the other \
+             stack is synchronized with its renamed counterpart, which is identified \
+             by having the same author, author date, and subject.\n\
+             \n\
+             When synchronizing stops due to conflicts, resolve the conflicts and \
+             use '--continue' to synchronize the remaining patches. Use '--skip' to \
+             leave the conflicting patch unsynchronized or '--abort' to restore the \
+             stack to its state before the command.",
         )
         .override_usage(super::make_usage(
             "stg sync",
-            &["<--ref-branch=BRANCH|--series=SERIES> [<patch>...|--all]"],
+            &[
+                "<--ref-branch=BRANCH|--from-stack=STACK|--series=SERIES> [<patch>...|--all]",
+                "--continue | --skip | --abort",
+            ],
         ))
         .arg(
             Arg::new("patchranges")
@@ -69,7 +88,20 @@ fn make() -> clap::Command {
                 .short('B')
                 .help("Synchronize patches with <branch>")
                 .value_name("branch")
-                .value_parser(clap::value_parser!(BranchLocator)),
+                .value_parser(clap::value_parser!(BranchLocator))
+                .required_unless_present_any(["from-stack", "series", "resume"]),
+        )
+        .arg(
+            Arg::new("from-stack")
+                .long("from-stack")
+                .help("Synchronize patches with the stack state of <stack>")
+                .long_help(
+                    "Synchronize patches with the patches of <stack>. The stack may be \
+                     given as <remote>/<branch> for a stack fetched with 'stg stack \
+                     fetch', as the name of a local branch, or as the full name of a \
+                     stack state reference.",
+                )
+                .value_name("stack"),
         )
         .arg(
             Arg::new("series")
@@ -82,13 +114,18 @@ fn make() -> clap::Command {
         )
         .group(
             ArgGroup::new("target")
-                .args(["ref-branch", "series"])
-                .required(true),
+                .args(["ref-branch", "from-stack", "series"])
+                .required(false),
         )
-        .arg(argset::committer_date_is_author_date_arg())
+        .arg(argset::committer_date_is_author_date_arg());
+    super::sequence::add_args(app)
 }
 
 fn run(matches: &clap::ArgMatches) -> Result<()> {
+    if let Some(resume) = super::sequence::get_resume(matches) {
+        return super::sequence::resume(matches, STGIT_COMMAND.name, resume);
+    }
+
     let repo = gix::Repository::open()?;
     let stack = Stack::current(&repo, InitializationPolicy::AllowUninitialized)?;
     let stupid = repo.stupid();
@@ -110,22 +147,36 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
         return Err(super::Error::NoAppliedPatches.into());
     };
 
-    let ref_stack = matches
-        .get_one::<BranchLocator>("ref-branch")
-        .map(|loc| {
-            Stack::from_branch_locator(&repo, Some(loc), InitializationPolicy::AllowUninitialized)
-        })
-        .transpose()?;
-
-    let series_dir = matches
-        .get_one::<PathBuf>("series")
-        .map(|series_path| series_path.parent().unwrap_or_else(|| Path::new(".")));
+    let ref_stack = get_ref_stack(&repo, matches)?;
+    let ref_state = get_ref_state(&stack, matches)?;
 
     let ref_patches: Vec<PatchName> = if let Some(ref_stack) = ref_stack.as_ref() {
         if ref_stack.get_branch_name() == stack.get_branch_name() {
             return Err(anyhow!("cannot synchronize with the current branch"));
         }
         ref_stack.applied().to_vec()
+    } else if let Some(ref_state) = ref_state.as_ref() {
+        let spec = matches
+            .get_one::<String>("from-stack")
+            .expect("ref state is from --from-stack");
+        let changes = compare_stacks(&stack, ref_state)?;
+        for patchname in &changes.added {
+            print_info_message(matches, &format!("Patch `{patchname}` added in `{spec}`"));
+        }
+        for patchname in &changes.removed {
+            print_info_message(matches, &format!("Patch `{patchname}` removed in `{spec}`"));
+        }
+        for (patchname, ref_patchname) in &changes.renamed {
+            print_info_message(
+                matches,
+                &format!("Patch `{patchname}` renamed to `{ref_patchname}` in `{spec}`"),
+            );
+        }
+        ref_state
+            .all_patches()
+            .cloned()
+            .chain(changes.renamed.into_iter().map(|(patchname, _)| patchname))
+            .collect()
     } else if let Some(series_path) = matches.get_one::<PathBuf>("series") {
         let series = std::fs::read(series_path)
             .with_context(|| format!("opening series `{}`", series_path.to_string_lossy()))?;
@@ -149,7 +200,7 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
         }
         ref_patches
     } else {
-        panic!("either --ref-branch or --series is required")
+        panic!("one of --ref-branch, --from-stack, or --series is required")
     };
 
     let sync_patches: Vec<PatchName> = patches
@@ -169,6 +220,26 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
         return Err(anyhow!("no common patches to synchronize"));
     };
 
+    let mut sequence = super::sequence::begin(&stack, STGIT_COMMAND.name)?;
+    if let Some(ref_stack) = ref_stack.as_ref() {
+        sequence
+            .args
+            .push(format!("--ref-branch={}", ref_stack.get_branch_name()));
+    } else if let Some(spec) = matches.get_one::<String>("from-stack") {
+        sequence.args.push(format!("--from-stack={spec}"));
+    } else if let Some(series_path) = matches.get_one::<PathBuf>("series") {
+        let series_path = gix::path::realpath(series_path)?;
+        sequence
+            .args
+            .push(format!("--series={}", series_path.to_string_lossy()));
+    }
+    if matches.get_flag("committer-date-is-author-date") {
+        sequence.committer_date_is_author_date = true;
+        sequence
+            .args
+            .push("--committer-date-is-author-date".to_string());
+    }
+
     let mut stack = stack;
     let mut pushed: Vec<PatchName> = Vec::new();
     let mut popped: Vec<PatchName> = Vec::new();
@@ -195,67 +266,179 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
 
     popped.extend(patches.iter().filter(|&pn| unapplied.contains(pn)).cloned());
 
+    for pn in pushed.iter().chain(popped.iter()) {
+        if popped.contains(pn) {
+            sequence.todo.push(Step::Push(pn.clone()));
+        }
+        if sync_patches.contains(pn) {
+            sequence.todo.push(Step::Sync(pn.clone()));
+        }
+    }
+
+    super::sequence::run(stack, matches, sequence)
+}
+
+/// Get the reference stack from the `--ref-branch` option, if any.
+fn get_ref_stack<'repo>(
+    repo: &'repo gix::Repository,
+    matches: &clap::ArgMatches,
+) -> Result<Option<Stack<'repo>>> {
+    matches
+        .get_one::<BranchLocator>("ref-branch")
+        .map(|loc| {
+            Stack::from_branch_locator(repo, Some(loc), InitializationPolicy::AllowUninitialized)
+        })
+        .transpose()
+}
+
+/// Get the reference stack state from the `--from-stack` option, if any.
+fn get_ref_state<'repo>(
+    stack: &Stack<'repo>,
+    matches: &clap::ArgMatches,
+) -> Result<Option<StackState<'repo>>> {
+    matches
+        .get_one::<String>("from-stack")
+        .map(|spec| {
+            let reference = super::stack::resolve_stack_ref(stack.repo, spec)?;
+            if reference.name().as_bstr() == stack.get_stack_refname().as_bytes() {
+                return Err(anyhow!("cannot synchronize with the current branch"));
+            }
+            let commit = reference.id().object()?.try_into_commit()?;
+            StackState::from_commit(stack.repo, &commit)
+        })
+        .transpose()
+}
+
+/// Patches added, removed, and renamed in a reference stack relative to a stack.
+struct StackChanges {
+    added: Vec<PatchName>,
+    removed: Vec<PatchName>,
+    renamed: Vec<(PatchName, PatchName)>,
+}
+
+/// Compare the patches of a stack with the patches of a reference stack.
+///
+/// A patch that is missing from the reference stack is considered renamed when a
+/// patch that is only in the reference stack has the same author, author date, and
+/// subject.
+fn compare_stacks<'repo>(
+    stack: &impl StackStateAccess<'repo>,
+    ref_state: &impl StackStateAccess<'repo>,
+) -> Result<StackChanges> {
+    let mut added: Vec<PatchName> = ref_state
+        .all_patches()
+        .filter(|pn| !stack.has_patch(pn))
+        .cloned()
+        .collect();
+    let mut removed = Vec::new();
+    let mut renamed = Vec::new();
+    for patchname in stack.all_patches().filter(|pn| !ref_state.has_patch(pn)) {
+        let commit = stack.get_patch_commit(patchname);
+        let author = commit.author_strict()?;
+        let subject = commit
+            .message_ex()
+            .decode()?
+            .lines()
+            .next()
+            .map(str::to_string);
+        let mut renamed_pos = None;
+        for (pos, ref_patchname) in added.iter().enumerate() {
+            let ref_commit = ref_state.get_patch_commit(ref_patchname);
+            if ref_commit.author_strict()? == author
+                && ref_commit.message_ex().decode()?.lines().next() == subject.as_deref()
+            {
+                renamed_pos = Some(pos);
+                break;
+            }
+        }
+        if let Some(pos) = renamed_pos {
+            renamed.push((patchname.clone(), added.remove(pos)));
+        } else {
+            removed.push(patchname.clone());
+        }
+    }
+    Ok(StackChanges {
+        added,
+        removed,
+        renamed,
+    })
+}
+
+/// Synchronize the topmost applied patch with the reference branch, stack, or series.
+pub(super) fn sync_patch<'repo>(
+    stack: Stack<'repo>,
+    matches: &clap::ArgMatches,
+    patchname: &PatchName,
+) -> Result<Stack<'repo>> {
+    let ref_stack = get_ref_stack(stack.repo, matches)?;
+    let ref_state = get_ref_state(&stack, matches)?;
+    let ref_commit = if let Some(ref_stack) = ref_stack.as_ref() {
+        Some(ref_stack.get_patch_commit(patchname).clone())
+    } else if let Some(ref_state) = ref_state.as_ref() {
+        let ref_patchname = if ref_state.has_patch(patchname) {
+            patchname.clone()
+        } else {
+            compare_stacks(&stack, ref_state)?
+                .renamed
+                .into_iter()
+                .find_map(|(pn, ref_pn)| (&pn == patchname).then_some(ref_pn))
+                .ok_or_else(|| anyhow!("patch `{patchname}` not found in reference stack"))?
+        };
+        Some(ref_state.get_patch_commit(&ref_patchname).clone())
+    } else {
+        None
+    };
+    let series_dir = matches
+        .get_one::<PathBuf>("series")
+        .map(|series_path| series_path.parent().unwrap_or_else(|| Path::new(".")));
+
     stack
         .setup_transaction()
         .use_index_and_worktree(true)
         .with_output_stream(get_color_stdout(matches))
         .transact(|trans| {
-            for pn in pushed.iter().chain(popped.iter()) {
-                let parent_id = trans.top().id;
-
-                if popped.contains(pn) {
-                    trans.push_patches(&[pn], false)?;
-                }
-
-                if !sync_patches.contains(pn) {
-                    continue;
-                }
-
-                let commit = trans.get_patch_commit(pn);
+            let commit = trans.get_patch_commit(patchname);
+            let parent_id = commit.get_parent_commit()?.id;
 
-                let maybe_tree_id = if let Some(ref_stack) = ref_stack.as_ref() {
-                    branch_merge_patch(ref_stack, trans, pn, commit)?
-                } else if let Some(series_dir) = series_dir {
-                    series_merge_patch(series_dir, trans, pn, commit)?
+            let maybe_tree_id = if let Some(ref_commit) = ref_commit.as_ref() {
+                branch_merge_patch(ref_commit, trans, patchname, commit)?
+            } else if let Some(series_dir) = series_dir {
+                series_merge_patch(series_dir, trans, patchname, commit)?
+            } else {
+                panic!("must have either ref_commit or series_dir");
+            };
+
+            if let Some(tree_id) = maybe_tree_id {
+                let author = commit.author_strict()?;
+                let default_committer = trans.repo().get_committer()?;
+                let committer = if matches.get_flag("committer-date-is-author-date") {
+                    let mut committer = default_committer.to_owned();
+                    committer.time = author.time;
+                    committer
                 } else {
-                    panic!("must have either ref_branch or series_dir");
+                    default_committer.to_owned()
                 };
-
-                if let Some(tree_id) = maybe_tree_id {
-                    let author = commit.author_strict()?;
-                    let default_committer = trans.repo().get_committer()?;
-                    let committer = if matches.get_flag("committer-date-is-author-date") {
-                        let mut committer = default_committer.to_owned();
-                        committer.time = author.time;
-                        committer
-                    } else {
-                        default_committer.to_owned()
-                    };
-                    let commit_id = trans.repo().commit_ex(
-                        &author,
-                        &committer,
-                        &commit.message_ex(),
-                        tree_id,
-                        [parent_id],
-                    )?;
-                    trans.update_patch(pn, commit_id)?;
-                }
+                let commit_id = trans.repo().commit_ex(
+                    &author,
+                    &committer,
+                    &commit.message_ex(),
+                    tree_id,
+                    [parent_id],
+                )?;
+                trans.update_patch(patchname, commit_id)?;
             }
             Ok(())
         })
-        .execute("sync")?;
-
-    Ok(())
+        .execute("sync")
 }
 
 fn branch_merge_patch(
-    ref_stack: &Stack,
+    ref_commit: &gix::Commit,
     trans: &StackTransaction,
     patchname: &PatchName,
     commit: &gix::Commit,
 ) -> Result<Option<gix::ObjectId>> {
     let commit_ref = commit.decode()?;
-    let ref_commit = ref_stack.get_patch_commit(patchname);
     let ref_commit_ref = ref_commit.decode()?;
     let ref_parent = ref_commit.get_parent_commit()?;
     let ref_parent_ref = ref_parent.decode()?;
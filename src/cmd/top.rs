@@ -31,6 +31,7 @@ fn make() -> clap::Command {
              message will be printed if no patches are applied.",
         )
         .arg(argset::branch_arg())
+        .arg(argset::json_arg())
 }
 
 fn run(matches: &clap::ArgMatches) -> Result<()> {
@@ -42,6 +43,9 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
     )?;
 
     if let Some(patchname) = stack.applied().last() {
+        if matches.get_flag("json") {
+            return super::json::print(&super::json::Patch::new(&stack, patchname)?);
+        }
         let mut stdout = crate::color::get_color_stdout(matches);
         let mut color_spec = termcolor::ColorSpec::new();
         color_spec.set_bold(true);
@@ -143,7 +143,7 @@ fn run(matches: &ArgMatches) -> Result<()> {
             }
         }
 
-        let patchnames = make_patchnames(&stack, &commits, patchname_len_limit);
+        let patchnames = make_patchnames(&stack, &commits, patchname_len_limit)?;
         (commits, patchnames)
     } else {
         let mut commits = Vec::new();
@@ -170,7 +170,7 @@ fn run(matches: &ArgMatches) -> Result<()> {
                 check_patchnames(&stack, &patchnames)?;
                 patchnames
             } else {
-                make_patchnames(&stack, &commits, patchname_len_limit)
+                make_patchnames(&stack, &commits, patchname_len_limit)?
             }
         } else if let Some(user_patchnames) = matches.get_many::<PatchName>("patchname") {
             let patchnames = user_patchnames.cloned().collect::<Vec<_>>();
@@ -184,7 +184,7 @@ fn run(matches: &ArgMatches) -> Result<()> {
         } else {
             check_commit(&next_commit)?;
             commits.push(next_commit);
-            make_patchnames(&stack, &commits, patchname_len_limit)
+            make_patchnames(&stack, &commits, patchname_len_limit)?
         };
         (commits, patchnames)
     };
@@ -225,21 +225,23 @@ fn make_patchnames(
     stack: &Stack,
     commits: &[Rc<gix::Commit<'_>>],
     patchname_len_limit: Option<usize>,
-) -> Vec<PatchName> {
+) -> Result<Vec<PatchName>> {
     let mut patchnames = Vec::with_capacity(commits.len());
     let mut taken_names: Vec<_> = stack.all_patches().cloned().collect();
     for commit in commits.iter().rev() {
-        let patchname = PatchName::make(
+        let patchname = PatchName::generate(
+            stack.repo,
             &commit.message_ex().decode().unwrap_or_default(),
             true,
             patchname_len_limit,
-        )
+            &taken_names,
+        )?
         .uniquify(&[], &taken_names);
         taken_names.push(patchname.clone());
         patchnames.push(patchname);
     }
     patchnames.reverse();
-    patchnames
+    Ok(patchnames)
 }
 
 fn check_patchnames(stack: &Stack, patchnames: &[PatchName]) -> Result<()> {
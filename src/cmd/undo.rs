@@ -2,7 +2,7 @@
 
 //! `stg undo` implementation.
 
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
 use anyhow::{anyhow, Result};
 use bstr::{BStr, ByteSlice};
@@ -10,8 +10,10 @@ use clap::Arg;
 
 use crate::{
     color::get_color_stdout,
-    ext::RepositoryExtended,
-    stack::{InitializationPolicy, Stack, StackAccess, StackState},
+    ext::{CommitExtended, RepositoryExtended},
+    patch::{PatchLocator, PatchName},
+    stack::{InitializationPolicy, Stack, StackAccess, StackState, StackStateAccess, StateHistory},
+    stupid::Stupid,
 };
 
 pub(super) const STGIT_COMMAND: super::StGitCommand = super::StGitCommand {
@@ -26,13 +28,27 @@ fn make() -> clap::Command {
         .about("Undo the last command")
         .long_about(
             "Reset the patch stack to the state before the last operation. \
-             Consecutive undos will go back to yet older stack states.",
+             Consecutive undos will go back to yet older stack states.\n\
+             \n\
+             With '--patch', only the given patch is restored to its previous version \
+             from the stack's history, i.e. the version it had before it was last \
+             changed. The patch's content, message, and author are restored while all \
+             other patches and the order of patches remain unchanged. If the patch is \
+             applied, it is pushed again on top of the patches below it and any \
+             conflicts are reported as with 'stg push'. Changes to a patch that only \
+             moved it to a new parent, e.g. due to a rebase, are not considered to be \
+             new versions of the patch. With '--number', the patch is restored to the \
+             version <n> changes ago.",
         )
         .arg(
             Arg::new("number")
                 .long("number")
                 .short('n')
                 .help("Undo the last <n> commands")
+                .long_help(
+                    "Undo the last <n> commands. With '--patch', restore the version \
+                     of the patch from <n> changes ago.",
+                )
                 .value_name("n")
                 .value_parser(|s: &str| {
                     s.parse::<isize>()
@@ -52,6 +68,14 @@ fn make() -> clap::Command {
                 .help("Discard changes in the index and worktree")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("patch")
+                .long("patch")
+                .short('p')
+                .help("Restore only <patch> to its previous version")
+                .value_name("patch")
+                .value_parser(clap::value_parser!(PatchLocator)),
+        )
 }
 
 fn run(matches: &clap::ArgMatches) -> Result<()> {
@@ -59,6 +83,11 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
     let stack = Stack::current(&repo, InitializationPolicy::RequireInitialized)?;
     let undo_steps = matches.get_one::<isize>("number").copied().unwrap_or(1);
 
+    if let Some(patch_loc) = matches.get_one::<PatchLocator>("patch") {
+        let patchname = patch_loc.resolve_name(&stack)?;
+        return undo_patch(matches, stack, &patchname, undo_steps);
+    }
+
     stack
         .setup_transaction()
         .use_index_and_worktree(true)
@@ -74,6 +103,94 @@ fn run(matches: &clap::ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn undo_patch(
+    matches: &clap::ArgMatches,
+    stack: Stack,
+    patchname: &PatchName,
+    undo_steps: isize,
+) -> Result<()> {
+    let commit_id = find_patch_undo_commit(&stack, patchname, undo_steps)?;
+
+    stack
+        .setup_transaction()
+        .use_index_and_worktree(true)
+        .discard_changes(matches.get_flag("hard"))
+        .with_output_stream(get_color_stdout(matches))
+        .transact(|trans| {
+            let to_push = if let Some(pos) = trans.applied().iter().position(|pn| pn == patchname) {
+                let to_pop = trans.applied()[pos..].to_vec();
+                let popped_extra = trans.pop_patches(|pn| to_pop.contains(pn))?;
+                assert!(popped_extra.is_empty());
+                to_pop
+            } else {
+                vec![]
+            };
+            trans.update_patch(patchname, commit_id)?;
+            trans.push_patches(&to_push, false)
+        })
+        .execute(&format!("undo {undo_steps}: {patchname}"))?;
+
+    Ok(())
+}
+
+/// Find the commit of a previous version of a patch in the stack's history.
+///
+/// Versions of the patch are distinguished by their diff, message, and author, such that
+/// commits of the patch that only differ by their parent are considered to be the same
+/// version. The history is searched from the current state back to the state where the
+/// patch first appears with its current name.
+fn find_patch_undo_commit(
+    stack: &Stack,
+    patchname: &PatchName,
+    undo_steps: isize,
+) -> Result<gix::ObjectId> {
+    let mut commits: Vec<Rc<gix::Commit>> = Vec::new();
+    for entry in StateHistory::from_stack(stack)? {
+        let (_, state) = entry?;
+        if state.has_patch(patchname) {
+            let commit = state.get_patch_commit(patchname);
+            if commits.last().map_or(true, |last| last.id != commit.id) {
+                commits.push(commit.clone());
+            }
+        } else {
+            break;
+        }
+    }
+
+    let patch_ids: HashMap<gix::ObjectId, gix::ObjectId> = stack
+        .repo
+        .stupid()
+        .patch_ids(commits.iter().map(|commit| commit.id))?
+        .into_iter()
+        .collect();
+    let version = |commit: &gix::Commit| -> Result<_> {
+        Ok((
+            patch_ids.get(&commit.id).copied(),
+            commit.message_raw()?.to_owned(),
+            commit.author_strict()?,
+        ))
+    };
+
+    let mut commits = commits.iter();
+    let current = commits.next().expect("patch exists in current state");
+    let mut last_version = version(current)?;
+    let mut steps = 0;
+    for commit in commits {
+        let commit_version = version(commit)?;
+        if commit_version != last_version {
+            steps += 1;
+            if steps == undo_steps {
+                return Ok(commit.id);
+            }
+            last_version = commit_version;
+        }
+    }
+
+    Err(anyhow!(
+        "not enough undo information available for patch `{patchname}`"
+    ))
+}
+
 pub(super) fn find_undo_state<'repo>(
     stack: &Stack<'repo>,
     undo_steps: isize,
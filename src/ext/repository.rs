@@ -1,9 +1,9 @@
 // SPDX-License-Identifier: GPL-2.0-only
 
-use std::borrow::Cow;
+use std::{borrow::Cow, path::PathBuf};
 
 use anyhow::{anyhow, Result};
-use bstr::BStr;
+use bstr::{BStr, ByteSlice};
 
 use crate::{
     stupid::Stupid,
@@ -40,6 +40,15 @@ pub(crate) trait RepositoryExtended {
     /// Returns an error if the head is detached or unborn.
     fn get_current_branch(&self) -> Result<Branch<'_>>;
 
+    /// Get the path of another worktree in which the branch is checked-out.
+    ///
+    /// Returns `None` if the branch is not checked-out in any worktree other than the
+    /// current worktree.
+    fn get_other_worktree_for_branch(
+        &self,
+        branch_refname: &gix::refs::FullNameRef,
+    ) -> Result<Option<PathBuf>>;
+
     /// Get repository-local config file which can be used to change local
     /// configuration.
     fn local_config_file(&self) -> Result<gix::config::File<'static>>;
@@ -164,6 +173,25 @@ impl RepositoryExtended for gix::Repository {
         }
     }
 
+    fn get_other_worktree_for_branch(
+        &self,
+        branch_refname: &gix::refs::FullNameRef,
+    ) -> Result<Option<PathBuf>> {
+        // Avoid running git when there are no linked worktrees.
+        if self.worktrees()?.is_empty() {
+            return Ok(None);
+        }
+        let current_dir = self.workdir().and_then(|dir| dir.canonicalize().ok());
+        for (path, branch) in self.stupid().worktree_list()? {
+            if branch.as_ref().map(|branch| branch.as_bstr()) == Some(branch_refname.as_bstr())
+                && path.canonicalize().ok() != current_dir
+            {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
     fn local_config_file(&self) -> Result<gix::config::File<'static>> {
         let source = gix::config::Source::Local;
 
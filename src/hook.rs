@@ -4,14 +4,16 @@
 
 use std::{
     borrow::Cow,
+    ffi::OsStr,
     io::Write,
     path::{Path, PathBuf},
+    process::ExitStatus,
 };
 
 use anyhow::{anyhow, Context, Result};
 use bstr::BString;
 
-use crate::wrap::Message;
+use crate::{patch::PatchName, wrap::Message};
 
 /// Find path to hook script given a hook name.
 ///
@@ -152,6 +154,134 @@ pub(crate) fn run_commit_msg_hook<'repo>(
     }
 }
 
+/// Run a StGit-specific hook script with the given arguments.
+///
+/// The hook is run from the root of the work tree or, for bare repositories, from
+/// the git directory.
+///
+/// Returns `Ok(None)` if the hook script does not exist, is not a file, or is not
+/// executable. Otherwise the hook's exit status is returned.
+fn run_stgit_hook<I, S>(
+    repo: &gix::Repository,
+    hook_name: &str,
+    args: I,
+) -> Result<Option<ExitStatus>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let hook_path = if let Some(hook_path) = get_hook_path(repo, hook_name)? {
+        hook_path
+    } else {
+        return Ok(None);
+    };
+
+    let mut hook_command = std::process::Command::from(
+        gix::command::prepare(hook_path).stdout(std::process::Stdio::inherit()),
+    );
+    hook_command.current_dir(repo.workdir().unwrap_or_else(|| repo.path()));
+    hook_command.args(args);
+
+    let status = hook_command
+        .status()
+        .with_context(|| format!("`{hook_name}` hook"))?;
+
+    Ok(Some(status))
+}
+
+/// Run the StGit `stg-pre-push` hook script before pushing a patch.
+///
+/// The hook is given the patch name, the patch's commit id, and the id of the commit
+/// the patch is to be pushed onto. The hook may veto the push by exiting with a
+/// non-zero status, in which case an error is returned.
+pub(crate) fn run_pre_push_hook(
+    repo: &gix::Repository,
+    patchname: &PatchName,
+    commit_id: gix::ObjectId,
+    parent_id: gix::ObjectId,
+) -> Result<()> {
+    let hook_name = "stg-pre-push";
+    let args = [
+        patchname.to_string(),
+        commit_id.to_string(),
+        parent_id.to_string(),
+    ];
+    match run_stgit_hook(repo, hook_name, args)? {
+        Some(status) if !status.success() => Err(anyhow!(
+            "`{hook_name}` hook returned {}; patch `{patchname}` not pushed",
+            status.code().unwrap_or(-1)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Run the StGit `stg-post-refresh` hook script after refreshing a patch.
+///
+/// The hook is given the patch name and the patch's new commit id. Since the refresh is
+/// already complete, the hook's exit status is ignored and a failure to run the hook
+/// only results in a warning.
+pub(crate) fn run_post_refresh_hook(
+    repo: &gix::Repository,
+    patchname: &PatchName,
+    commit_id: gix::ObjectId,
+) {
+    let args = [patchname.to_string(), commit_id.to_string()];
+    run_post_hook(repo, "stg-post-refresh", args);
+}
+
+/// Run the StGit `stg-post-transaction` hook script after the stack state changes.
+///
+/// The hook is given the previous and new stack state commit ids along with the stack
+/// log message. Since the transaction is already committed, the hook's exit status is
+/// ignored and a failure to run the hook only results in a warning.
+pub(crate) fn run_post_transaction_hook(
+    repo: &gix::Repository,
+    old_state_id: gix::ObjectId,
+    new_state_id: gix::ObjectId,
+    reflog_msg: &str,
+) {
+    let args = [
+        old_state_id.to_string(),
+        new_state_id.to_string(),
+        reflog_msg.to_string(),
+    ];
+    run_post_hook(repo, "stg-post-transaction", args);
+}
+
+/// Run a hook after an operation has completed, ignoring its exit status.
+///
+/// Like git's post-* hooks, the hook cannot affect the outcome of the operation, so
+/// a failure to run the hook is reported as a warning rather than an error.
+fn run_post_hook<I, S>(repo: &gix::Repository, hook_name: &str, args: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    if let Err(err) = run_stgit_hook(repo, hook_name, args) {
+        eprintln!("warning: {err:#}");
+    }
+}
+
+/// Run the StGit `stg-pre-email` hook script before sending email.
+///
+/// The hook is given the revision range or files to be sent. The hook may prevent the
+/// email from being sent by exiting with a non-zero status, in which case an error is
+/// returned.
+pub(crate) fn run_pre_email_hook<I, S>(repo: &gix::Repository, sources: I) -> Result<()>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let hook_name = "stg-pre-email";
+    match run_stgit_hook(repo, hook_name, sources)? {
+        Some(status) if !status.success() => Err(anyhow!(
+            "`{hook_name}` hook returned {}",
+            status.code().unwrap_or(-1)
+        )),
+        _ => Ok(()),
+    }
+}
+
 /// Temporary commit message file for commit-msg hook.
 ///
 /// The temporary file is created relative to the work dir using the StGit process id to
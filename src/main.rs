@@ -21,7 +21,7 @@ mod stupid;
 mod templates;
 mod wrap;
 
-use std::{ffi::OsString, fmt::Write as _, io::Write as _, path::PathBuf};
+use std::{ffi::OsString, fmt::Write as _, io::Write as _, path::PathBuf, time::Duration};
 
 use anyhow::{anyhow, Context, Result};
 use bstr::ByteSlice;
@@ -90,6 +90,25 @@ fn get_base_command(color_choice: Option<termcolor::ColorChoice>) -> clap::Comma
                 .value_name("path")
                 .value_hint(clap::ValueHint::AnyPath),
         )
+        .arg(
+            clap::Arg::new("wait")
+                .long("wait")
+                .help("Wait for a busy stack to become available")
+                .long_help(
+                    "Wait for a stack that is being modified by another StGit process to \
+                     become available instead of failing. Without a value, StGit waits \
+                     as long as it takes. Otherwise, StGit fails if the stack does not \
+                     become available within '<seconds>'.\n\
+                     \n\
+                     Besides waiting to modify a stack, StGit also waits to read a stack \
+                     while it is being modified, such that a consistent view of the \
+                     stack, index, and working tree is observed.",
+                )
+                .num_args(0..=1)
+                .require_equals(true)
+                .value_name("seconds")
+                .value_parser(clap::value_parser!(u64)),
+        )
         .arg(color::get_color_arg().global(true).display_order(998));
 
     // Ensure "stg" and not "stg.exe" shows up in usage on Windows.
@@ -162,6 +181,8 @@ fn main() -> ! {
     // First, using a minimal top-level Command instance, let clap find anything that looks
     // like a subcommand name (i.e. by using AppSettings::AllowExternalSubcommands).
     if let Ok(matches) = get_bootstrap_command(color_choice).try_get_matches_from(&argv) {
+        set_lock_wait(&matches);
+
         // N.B. changing directories here, early, affects which aliases will ultimately
         // be found.
         if matches.get_flag("version") {
@@ -292,6 +313,18 @@ fn change_directories(matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+/// Set how long to wait for busy stacks based on the --wait option from the top-level
+/// Command matches.
+fn set_lock_wait(matches: &ArgMatches) {
+    if matches.contains_id("wait") {
+        stack::set_lock_wait(
+            matches
+                .get_one::<u64>("wait")
+                .map_or(Duration::MAX, |&seconds| Duration::from_secs(seconds)),
+        );
+    }
+}
+
 /// Display the help for the fully-instantiated top-level [`clap::Command`].
 ///
 /// Process `argv` using full top-level [`clap::Command`] instance with the expectation
@@ -23,7 +23,9 @@ use anyhow::{anyhow, Result};
 use bstr::{BString, ByteSlice};
 use clap::ArgMatches;
 
-pub(crate) use self::{args::add_args, interactive::call_editor, parse::parse_name_email};
+pub(crate) use self::{
+    args::add_args, interactive::call_editor, parse::parse_name_email, trailers::add_trailers,
+};
 use self::{
     description::{DiffBuffer, EditablePatchDescription, EditedPatchDescription},
     interactive::edit_interactive,
@@ -331,8 +333,14 @@ impl<'a, 'repo> EditBuilder<'a, 'repo> {
             Some(original_patchname.clone())
         } else if !message.is_empty() && !need_interactive_edit {
             Some(
-                PatchName::make(&message.decode()?, true, patchname_len_limit)
-                    .uniquify(&allowed_patchnames, &disallow_patchnames),
+                PatchName::generate(
+                    repo,
+                    &message.decode()?,
+                    true,
+                    patchname_len_limit,
+                    &disallow_patchnames,
+                )?
+                .uniquify(&allowed_patchnames, &disallow_patchnames),
             )
         } else {
             None
@@ -349,7 +357,8 @@ impl<'a, 'repo> EditBuilder<'a, 'repo> {
             // N.B. add_trailers needs to operate on utf-8 data. The user providing
             // trailer-altering options (e.g. --review) will force the message to be
             // decoded. In such cases the returned message will wrap a utf-8 String.
-            trailers::add_trailers(repo, message, matches, default_committer, autosign)?
+            let trailers = trailers::trailers_from_args(matches, default_committer, autosign)?;
+            trailers::add_trailers(repo, message, trailers)?
         };
 
         let tree_id = overlay_tree_id.unwrap_or_else(|| {
@@ -538,8 +547,14 @@ impl<'a, 'repo> EditBuilder<'a, 'repo> {
         } else if let Some(Some(template_patchname)) = template_patchname {
             template_patchname.uniquify(&allowed_patchnames, &disallow_patchnames)
         } else {
-            PatchName::make(&message.decode()?, true, patchname_len_limit)
-                .uniquify(&allowed_patchnames, &disallow_patchnames)
+            PatchName::generate(
+                repo,
+                &message.decode()?,
+                true,
+                patchname_len_limit,
+                &disallow_patchnames,
+            )?
+            .uniquify(&allowed_patchnames, &disallow_patchnames)
         };
 
         let committer = if matches.get_flag("committer-date-is-author-date") {
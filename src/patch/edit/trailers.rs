@@ -8,17 +8,17 @@ use clap::ArgMatches;
 
 use crate::{stupid::Stupid, wrap::Message};
 
-/// Add trailers to commit message based on user-provided command line options.
+/// Get trailers to add to a commit message based on user-provided command line options.
 ///
 /// The `matches` provided to this function must be from a [`clap::Command`] that was
-/// setup with [`super::add_args`].
-pub(crate) fn add_trailers<'a, 'b>(
-    repo: &gix::Repository,
-    message: Message<'a>,
+/// setup with [`super::add_args`]. The trailers are returned in command line order,
+/// preceded by the `autosign` trailer, if any. Trailer options without a value take
+/// their value from `signature`.
+pub(crate) fn trailers_from_args<'b>(
     matches: &ArgMatches,
     signature: impl Into<gix::actor::SignatureRef<'b>>,
     autosign: Option<&str>,
-) -> Result<Message<'a>> {
+) -> Result<Vec<(String, String)>> {
     let signature = signature.into();
     let mut trailers: Vec<(usize, &str, &str)> = vec![];
 
@@ -43,7 +43,7 @@ pub(crate) fn add_trailers<'a, 'b>(
     }
 
     if trailers.is_empty() && autosign.is_none() {
-        Ok(message)
+        Ok(Vec::new())
     } else {
         let default_value =
             if let (Ok(name), Ok(email)) = (signature.name.to_str(), signature.email.to_str()) {
@@ -58,21 +58,48 @@ pub(crate) fn add_trailers<'a, 'b>(
 
         trailers.sort_by_key(|(index, _, _)| *index);
 
-        let message_str = message.decode()?;
-        let message_bytes = repo.stupid().interpret_trailers(
-            message_str.as_bytes(),
-            trailers.iter().map(|(_index, trailer, value)| {
-                if value.is_empty() {
-                    (*trailer, default_value.as_str())
+        Ok(trailers
+            .iter()
+            .map(|(_index, trailer, value)| {
+                let value = if value.is_empty() {
+                    default_value.as_str()
                 } else {
-                    (*trailer, *value)
-                }
-            }),
-        )?;
-        let message = String::from_utf8(message_bytes)
-            .map_err(|_| anyhow!("could not decode message after adding trailers"))?;
-        Ok(Message::from(message))
+                    value
+                };
+                (trailer.to_string(), value.to_string())
+            })
+            .collect())
+    }
+}
+
+/// Add `(trailer, value)` pairs to a commit message.
+///
+/// The trailers may come from command line options, see [`trailers_from_args()`], or
+/// from other sources, such as review replies to patch emails. The message is returned
+/// unchanged when there are no trailers to add.
+pub(crate) fn add_trailers<'a, T, V>(
+    repo: &gix::Repository,
+    message: Message<'a>,
+    trailers: impl IntoIterator<Item = (T, V)>,
+) -> Result<Message<'a>>
+where
+    T: AsRef<str>,
+    V: AsRef<str>,
+{
+    let trailers: Vec<(T, V)> = trailers.into_iter().collect();
+    if trailers.is_empty() {
+        return Ok(message);
     }
+    let message_str = message.decode()?;
+    let message_bytes = repo.stupid().interpret_trailers(
+        message_str.as_bytes(),
+        trailers
+            .iter()
+            .map(|(trailer, value)| (trailer.as_ref(), value.as_ref())),
+    )?;
+    let message = String::from_utf8(message_bytes)
+        .map_err(|_| anyhow!("could not decode message after adding trailers"))?;
+    Ok(Message::from(message))
 }
 
 #[cfg(test)]
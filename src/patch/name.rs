@@ -4,8 +4,10 @@
 
 use std::str::FromStr;
 
+use bstr::ByteSlice;
+
 use super::{LocationConstraint, LocationGroup, PatchName};
-use crate::stack::StackStateAccess;
+use crate::{stack::StackStateAccess, stupid::Stupid};
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum Error {
@@ -157,6 +159,100 @@ impl PatchName {
             .or(Some(30))
     }
 
+    /// Generate a patch name from a patch message using the configured strategy.
+    ///
+    /// The strategy is selected with the `stgit.namestrategy` configuration variable:
+    ///
+    /// - `subject` (default): derive the name from the message subject, as with
+    ///   [`PatchName::make()`].
+    /// - `ticket`: prefix the subject-derived name with a ticket identifier, e.g.
+    ///   `JIRA-123`, found in the message's trailers or in the current branch name.
+    /// - `date`: use the current date with a sequence number following that of any
+    ///   `existing` patch names for the same date, e.g. `20240131-1`.
+    /// - `command`: use the output of the `stgit.namecmd` shell command, which
+    ///   receives the message on stdin.
+    ///
+    /// The generated name is not guaranteed to be unique; see
+    /// [`PatchName::uniquify()`].
+    pub(crate) fn generate<P>(
+        repo: &gix::Repository,
+        message: &str,
+        lower: bool,
+        len_limit: Option<usize>,
+        existing: &[P],
+    ) -> anyhow::Result<Self>
+    where
+        P: AsRef<PatchName>,
+    {
+        let config = repo.config_snapshot();
+        let strategy = config
+            .string("stgit.namestrategy")
+            .map(|value| value.to_str_lossy().into_owned());
+        match strategy.as_deref() {
+            None | Some("subject") => Ok(Self::make(message, lower, len_limit)),
+            Some("ticket") => {
+                let branch_name = repo
+                    .head_name()?
+                    .map(|refname| refname.shorten().to_str_lossy().into_owned());
+                let ticket = find_ticket_in_trailers(message)
+                    .or_else(|| branch_name.as_deref().and_then(find_ticket));
+                let name = Self::make(message, lower, len_limit);
+                if let Some(ticket) = ticket
+                    .filter(|ticket| !name.0.to_lowercase().starts_with(&ticket.to_lowercase()))
+                {
+                    let name = Self::make(
+                        message,
+                        lower,
+                        len_limit.map(|limit| limit.saturating_sub(ticket.len() + 1).max(1)),
+                    );
+                    Ok(Self::from_str(&format!("{ticket}-{name}"))?)
+                } else {
+                    Ok(name)
+                }
+            }
+            Some("date") => {
+                let date = jiff::Zoned::now().strftime("%Y%m%d").to_string();
+                Ok(Self::next_dated(&date, existing)?)
+            }
+            Some("command") => {
+                let command = config.string("stgit.namecmd").ok_or_else(|| {
+                    anyhow::anyhow!("`stgit.namecmd` must be set for the `command` name strategy")
+                })?;
+                let output = repo
+                    .stupid()
+                    .user_filter(&command.to_str_lossy(), message.as_bytes())?;
+                let output = output.to_str_lossy();
+                if output.trim().is_empty() {
+                    Err(anyhow::anyhow!(
+                        "`stgit.namecmd` command `{command}` did not output a patch name"
+                    ))
+                } else {
+                    Ok(Self::make(&output, false, len_limit))
+                }
+            }
+            Some(other) => Err(anyhow::anyhow!(
+                "invalid `stgit.namestrategy` value `{other}`; \
+                 expected `subject`, `ticket`, `date`, or `command`"
+            )),
+        }
+    }
+
+    /// Make a dated patch name with the sequence number following that of any
+    /// `existing` patch names for the same date.
+    fn next_dated<P>(date: &str, existing: &[P]) -> Result<Self, Error>
+    where
+        P: AsRef<PatchName>,
+    {
+        let prefix = format!("{date}-");
+        let number = existing
+            .iter()
+            .filter_map(|pn| pn.as_ref().0.strip_prefix(&prefix)?.parse::<usize>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        Self::from_str(&format!("{prefix}{number}"))
+    }
+
     /// Make patch name unique relative to provided list of disallowed names.
     ///
     /// If the patch name conflicts with a name in the `disallow` slice, it will be
@@ -267,6 +363,51 @@ impl PatchName {
     }
 }
 
+/// Find a ticket identifier in the trailers of a patch message.
+///
+/// Trailers are the `Key: value` lines of the message's last paragraph.
+fn find_ticket_in_trailers(message: &str) -> Option<&str> {
+    let body = message.trim_end();
+    let last_paragraph = body.rsplit_once("\n\n").map_or(body, |(_, last)| last);
+    last_paragraph.lines().find_map(|line| {
+        line.split_once(": ")
+            .filter(|(key, _)| {
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+            .and_then(|(_, value)| find_ticket(value))
+    })
+}
+
+/// Find the first ticket identifier, e.g. `JIRA-123`, in a string.
+///
+/// A ticket identifier is an uppercase project key, which may contain digits after its
+/// first letter, followed by '-' and an issue number.
+fn find_ticket(s: &str) -> Option<&str> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_uppercase() && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric()) {
+            let mut j = i + 1;
+            while j < bytes.len() && (bytes[j].is_ascii_uppercase() || bytes[j].is_ascii_digit()) {
+                j += 1;
+            }
+            if bytes.get(j) == Some(&b'-') {
+                let mut k = j + 1;
+                while k < bytes.len() && bytes[k].is_ascii_digit() {
+                    k += 1;
+                }
+                if k > j + 1 && (k == bytes.len() || !bytes[k].is_ascii_alphanumeric()) {
+                    return Some(&s[i..k]);
+                }
+            }
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
 impl AsRef<str> for PatchName {
     #[inline]
     fn as_ref(&self) -> &str {
@@ -409,6 +550,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn make_dated_patch_names() {
+        let none: [PatchName; 0] = [];
+        assert_eq!(
+            PatchName::next_dated("20240131", &none).unwrap().0,
+            "20240131-1"
+        );
+        let existing = [
+            PatchName("20240130-5".into()),
+            PatchName("20240131-1".into()),
+            PatchName("20240131-3".into()),
+            PatchName("20240131-x".into()),
+            PatchName("other".into()),
+        ];
+        assert_eq!(
+            PatchName::next_dated("20240131", &existing).unwrap().0,
+            "20240131-4"
+        );
+    }
+
     #[test]
     fn make_unique_patch_names() {
         let allow = [PatchName("allow".into())];
@@ -425,4 +586,42 @@ mod tests {
             assert_eq!(&unique.0, expected);
         }
     }
+
+    #[test]
+    fn find_tickets() {
+        let cases = [
+            ("JIRA-123", Some("JIRA-123")),
+            ("feature/JIRA-123-fix-bug", Some("JIRA-123")),
+            ("see AB2-7, XY-8", Some("AB2-7")),
+            ("https://example.com/browse/PROJ-42", Some("PROJ-42")),
+            ("Jira-123", None),
+            ("xJIRA-123", None),
+            ("JIRA-123a", None),
+            ("JIRA-", None),
+            ("2FA-1", None),
+            ("master", None),
+        ];
+
+        for (s, expected) in cases.iter() {
+            assert_eq!(find_ticket(s), *expected, "{s}");
+        }
+    }
+
+    #[test]
+    fn find_tickets_in_trailers() {
+        let cases = [
+            ("Subject\n\nBody\n\nTicket: ABC-1\n", Some("ABC-1")),
+            (
+                "Subject\n\nSigned-off-by: A U Thor <author@example.com>\nRefs: XYZ-22",
+                Some("XYZ-22"),
+            ),
+            ("Subject ABC-1\n\nBody mentions DEF-2\n", None),
+            ("Subject\n\nNot a trailer: GHI-3\n", None),
+            ("Ticket: ABC-1", Some("ABC-1")),
+        ];
+
+        for (message, expected) in cases.iter() {
+            assert_eq!(find_ticket_in_trailers(message), *expected, "{message}");
+        }
+    }
 }
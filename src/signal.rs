@@ -12,6 +12,8 @@ const SIGINT_CODE: i32 = 130;
 pub(super) fn setup() -> Result<()> {
     ctrlc::set_handler(|| {
         if SIGNALED.load(Ordering::SeqCst) || !CRITICAL.load(Ordering::SeqCst) {
+            // Lock files, e.g. stack locks, would otherwise be left behind.
+            gix::tempfile::registry::cleanup_tempfiles();
             std::process::exit(SIGINT_CODE);
         } else {
             SIGNALED.store(true, Ordering::SeqCst);
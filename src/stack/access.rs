@@ -7,6 +7,7 @@ use std::rc::Rc;
 
 use super::{
     iter::{AllPatches, BothPatches},
+    meta::PatchMeta,
     state::PatchState,
 };
 use crate::patch::{LocationConstraint, LocationGroup, PatchName};
@@ -89,6 +90,14 @@ pub(crate) trait StackStateAccess<'repo> {
         self.get_patch_commit(patchname).id
     }
 
+    /// Get the metadata for the given patch name.
+    fn get_patch_meta<'a>(&'a self, patchname: &PatchName) -> &'a PatchMeta
+    where
+        'repo: 'a,
+    {
+        &self.get_patch(patchname).meta
+    }
+
     /// Test whether given patch name is applied.
     fn is_applied(&self, patchname: &PatchName) -> bool {
         self.applied().contains(patchname)
@@ -0,0 +1,438 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Patch dependency tracking.
+//!
+//! A patch depends on another, earlier patch in the stack when the two patches'
+//! diffs touch overlapping or adjacent lines of the same file. Such patches cannot be
+//! reordered relative to each other without (likely) causing merge conflicts.
+//!
+//! Dependencies are determined by walking the patches in stack order while tracking,
+//! for each file, which patch last introduced each range of lines. The line ranges
+//! are taken from zero-context diffs of each patch's commit against its parent.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
+use bstr::{BStr, BString, ByteSlice};
+use indexmap::IndexSet;
+
+use super::{Stack, StackStateAccess};
+use crate::{
+    ext::CommitExtended,
+    patch::PatchName,
+    stupid::{parse_diff, FileDiff, Hunk, Stupid},
+};
+
+/// Dependency relationships between a sequence of patches.
+pub(crate) struct DependencyGraph {
+    /// Patch names in stack order.
+    patchnames: Vec<PatchName>,
+
+    /// Direct dependencies of each patch, by index into `patchnames`.
+    depends_on: Vec<BTreeSet<usize>>,
+}
+
+impl DependencyGraph {
+    /// Determine dependencies for the stack's applied and unapplied patches.
+    ///
+    /// Hidden patches do not participate in dependency tracking.
+    pub(crate) fn from_stack(stack: &Stack) -> Result<Self> {
+        let patchnames: Vec<PatchName> = stack.applied_and_unapplied().cloned().collect();
+        Self::new(stack, patchnames)
+    }
+
+    /// Determine dependencies between the given patches.
+    ///
+    /// The patch names must be provided in stack order.
+    pub(crate) fn new(stack: &Stack, patchnames: Vec<PatchName>) -> Result<Self> {
+        let stupid = stack.repo.stupid();
+        let mut diffs: Vec<BString> = Vec::with_capacity(patchnames.len());
+        for patchname in &patchnames {
+            let commit = stack.get_patch_commit(patchname);
+            let parent = commit.get_parent_commit()?;
+            diffs.push(stupid.diff_tree_patch(
+                parent.tree_id()?.detach(),
+                commit.tree_id()?.detach(),
+                None::<Vec<&str>>,
+                false,
+                ["-U0", "--no-renames", "--no-ext-diff"],
+            )?);
+        }
+        let depends_on = find_dependencies(diffs.iter().map(|diff| diff.as_bstr()))?;
+        Ok(Self {
+            patchnames,
+            depends_on,
+        })
+    }
+
+    fn index_of(&self, patchname: &PatchName) -> Option<usize> {
+        self.patchnames.iter().position(|pn| pn == patchname)
+    }
+
+    /// Patches the given patch directly depends on, in stack order.
+    pub(crate) fn dependencies(&self, patchname: &PatchName) -> Vec<&PatchName> {
+        self.index_of(patchname)
+            .map(|i| {
+                self.depends_on[i]
+                    .iter()
+                    .map(|&j| &self.patchnames[j])
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Patches directly depending on the given patch, in stack order.
+    pub(crate) fn dependents(&self, patchname: &PatchName) -> Vec<&PatchName> {
+        self.index_of(patchname)
+            .map(|i| {
+                self.depends_on
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, deps)| deps.contains(&i))
+                    .map(|(j, _)| &self.patchnames[j])
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Direct and indirect dependencies of the given patch, in stack order.
+    pub(crate) fn all_dependencies(&self, patchname: &PatchName) -> Vec<&PatchName> {
+        self.transitive(patchname, Self::dependencies)
+    }
+
+    /// Direct and indirect dependents of the given patch, in stack order.
+    pub(crate) fn all_dependents(&self, patchname: &PatchName) -> Vec<&PatchName> {
+        self.transitive(patchname, Self::dependents)
+    }
+
+    fn transitive<'a, F>(&'a self, patchname: &PatchName, step: F) -> Vec<&'a PatchName>
+    where
+        F: Fn(&'a Self, &PatchName) -> Vec<&'a PatchName>,
+    {
+        let mut found: BTreeSet<usize> = BTreeSet::new();
+        let mut pending: Vec<&PatchName> = step(self, patchname);
+        while let Some(pn) = pending.pop() {
+            if let Some(i) = self.index_of(pn) {
+                if found.insert(i) {
+                    pending.extend(step(self, pn));
+                }
+            }
+        }
+        found.iter().map(|&i| &self.patchnames[i]).collect()
+    }
+
+    /// Grow a set of patches until it is closed under the given relation.
+    ///
+    /// Starting from `seeds`, the patches returned by `step` for any member of the set
+    /// are added to the set. The result is ordered such that each patch follows the
+    /// patches it depends on; see [`DependencyGraph::order()`].
+    pub(crate) fn expand<F>(&self, seeds: &[PatchName], step: F) -> Vec<PatchName>
+    where
+        F: for<'a> Fn(&'a Self, &PatchName) -> Vec<&'a PatchName>,
+    {
+        let mut set: IndexSet<PatchName> = seeds.iter().cloned().collect();
+        let mut i = 0;
+        while i < set.len() {
+            let pn = set[i].clone();
+            for related in step(self, &pn) {
+                set.insert(related.clone());
+            }
+            i += 1;
+        }
+        self.order(&set.into_iter().collect::<Vec<_>>())
+    }
+
+    /// Order patches such that each patch follows the patches it depends on.
+    ///
+    /// The relative order of `patchnames` is preserved except where a patch must be
+    /// moved after one of its dependencies.
+    pub(crate) fn order(&self, patchnames: &[PatchName]) -> Vec<PatchName> {
+        fn visit(
+            graph: &DependencyGraph,
+            pn: &PatchName,
+            members: &IndexSet<&PatchName>,
+            ordered: &mut IndexSet<PatchName>,
+        ) {
+            if ordered.contains(pn) {
+                return;
+            }
+            for dep in graph.dependencies(pn) {
+                if members.contains(dep) {
+                    visit(graph, dep, members, ordered);
+                }
+            }
+            ordered.insert(pn.clone());
+        }
+
+        let members: IndexSet<&PatchName> = patchnames.iter().collect();
+        let mut ordered: IndexSet<PatchName> = IndexSet::with_capacity(patchnames.len());
+        for pn in patchnames {
+            visit(self, pn, &members, &mut ordered);
+        }
+        ordered.into_iter().collect()
+    }
+
+    /// Check that a new arrangement of applied patches respects the dependencies.
+    ///
+    /// Each applied patch must be preceded by the applied patches it depends on.
+    /// Unapplied patches are not checked since they are not pushed by the operation
+    /// and their dependencies are only relevant once they are pushed.
+    pub(crate) fn check(&self, applied: &[PatchName]) -> Result<()> {
+        for (pos, patchname) in applied.iter().enumerate() {
+            for dependency in self.dependencies(patchname) {
+                if !applied[..pos].contains(dependency) {
+                    return Err(anyhow!(
+                        "patch `{patchname}` depends on `{dependency}`; \
+                         use `--with-deps` to also move related patches \
+                         or `--ignore-deps` to override"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Line range of a file introduced by a patch.
+///
+/// Ranges are half-open, `[start, end)`, using 1-based line numbers. An empty range
+/// marks the position of lines removed by the owning patch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Span {
+    start: usize,
+    end: usize,
+    owner: usize,
+}
+
+/// Per-file record of which patches introduced which lines.
+#[derive(Default)]
+struct FileOwnership {
+    spans: Vec<Span>,
+
+    /// Patch that last changed the file without line information, e.g. a binary file.
+    whole: Option<usize>,
+}
+
+/// Position of the first line in the pre-image affected by a zero-context hunk.
+///
+/// For pure insertions, git reports the line *after which* lines are inserted, so the
+/// position is the following line.
+fn first_line(hunk: &Hunk) -> usize {
+    if hunk.old_count == 0 {
+        hunk.old_start + 1
+    } else {
+        hunk.old_start
+    }
+}
+
+/// Get a key uniquely identifying the file changed by a diff without renames.
+///
+/// Without renames, both paths of the `diff --git` line are the same, so the first half
+/// of the paths uniquely identifies the file, even when the paths are quoted.
+fn file_key<'a>(file_diff: &FileDiff<'a>) -> &'a BStr {
+    let paths = file_diff.git_paths();
+    paths[..paths.len() / 2].trim_end().as_bstr()
+}
+
+/// Compute direct dependencies given each patch's zero-context diff, in stack order.
+fn find_dependencies<'a>(diffs: impl Iterator<Item = &'a BStr>) -> Result<Vec<BTreeSet<usize>>> {
+    let mut files: BTreeMap<&BStr, FileOwnership> = BTreeMap::new();
+    let mut depends_on = Vec::new();
+
+    for (owner, diff) in diffs.enumerate() {
+        let mut deps = BTreeSet::new();
+
+        for file_diff in parse_diff(diff)? {
+            let file = files.entry(file_key(&file_diff)).or_default();
+            let hunks = file_diff.hunks;
+
+            if let Some(whole_owner) = file.whole {
+                deps.insert(whole_owner);
+            }
+
+            if hunks.is_empty() {
+                deps.extend(file.spans.iter().map(|span| span.owner));
+                file.spans.clear();
+                file.whole = Some(owner);
+                continue;
+            }
+
+            // Hunks are applied bottom-up so that line numbers of not-yet-applied
+            // hunks remain valid pre-image line numbers.
+            for hunk in hunks.iter().rev() {
+                let start = first_line(hunk);
+                let end = start + hunk.old_count;
+                deps.extend(
+                    file.spans
+                        .iter()
+                        .filter(|span| span.start <= end && start <= span.end)
+                        .map(|span| span.owner),
+                );
+                replace_lines(&mut file.spans, start, end, hunk.new_count, owner);
+            }
+            file.spans.sort_by_key(|span| span.start);
+        }
+
+        deps.remove(&owner);
+        depends_on.push(deps);
+    }
+
+    Ok(depends_on)
+}
+
+/// Replace lines `[start, end)` with `new_len` lines owned by `owner`.
+fn replace_lines(spans: &mut Vec<Span>, start: usize, end: usize, new_len: usize, owner: usize) {
+    let shift = |line: usize| -> usize {
+        (line + new_len)
+            .checked_sub(end - start)
+            .expect("line numbers remain positive")
+    };
+
+    let mut updated = Vec::with_capacity(spans.len() + 2);
+    for span in spans.drain(..) {
+        let is_before = if span.start == span.end {
+            span.start <= start
+        } else {
+            span.end <= start
+        };
+        if is_before {
+            updated.push(span);
+        } else if span.start >= end {
+            updated.push(Span {
+                start: shift(span.start),
+                end: shift(span.end),
+                owner: span.owner,
+            });
+        } else {
+            if span.start < start {
+                updated.push(Span {
+                    start: span.start,
+                    end: start,
+                    owner: span.owner,
+                });
+            }
+            if span.end > end {
+                updated.push(Span {
+                    start: shift(end),
+                    end: shift(span.end),
+                    owner: span.owner,
+                });
+            }
+        }
+    }
+    updated.push(Span {
+        start,
+        end: start + new_len,
+        owner,
+    });
+    *spans = updated;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deps(diffs: &[&str]) -> Vec<Vec<usize>> {
+        find_dependencies(diffs.iter().map(|diff| diff.as_bytes().as_bstr()))
+            .unwrap()
+            .into_iter()
+            .map(|deps| deps.into_iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn file_paths() {
+        let diff = "diff --git a/foo b/foo\n\
+                    index 1234567..89abcde 100644\n\
+                    --- a/foo\n\
+                    +++ b/foo\n\
+                    @@ -1 +1 @@\n\
+                    -old\n\
+                    +new\n\
+                    diff --git \"a/sp ace\" \"b/sp ace\"\n\
+                    Binary files differ\n";
+        let files = parse_diff(diff.as_bytes().as_bstr()).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(file_key(&files[0]), "a/foo");
+        assert_eq!(files[0].hunks.len(), 1);
+        assert_eq!(file_key(&files[1]), "\"a/sp ace\"");
+        assert!(files[1].hunks.is_empty());
+    }
+
+    #[test]
+    fn independent_files() {
+        let diffs = [
+            "diff --git a/a b/a\n@@ -0,0 +1,3 @@\n",
+            "diff --git a/b b/b\n@@ -0,0 +1,3 @@\n",
+        ];
+        assert_eq!(deps(&diffs), vec![Vec::<usize>::new(), vec![]]);
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_lines() {
+        let diffs = [
+            // Create a file with ten lines.
+            "diff --git a/f b/f\n@@ -0,0 +1,10 @@\n",
+            // Change line 20 of some other file.
+            "diff --git a/g b/g\n@@ -20 +20 @@\n",
+            // Append a line after line 10, adjacent to lines from patch 0.
+            "diff --git a/f b/f\n@@ -10,0 +11 @@\n",
+            // Change line 11, which was introduced by patch 2 and is adjacent to the
+            // last line from patch 0.
+            "diff --git a/f b/f\n@@ -11 +11 @@\n",
+            // Change line 5, from patch 0.
+            "diff --git a/f b/f\n@@ -5 +5 @@\n",
+        ];
+        assert_eq!(
+            deps(&diffs),
+            vec![vec![], vec![], vec![0], vec![0, 2], vec![0]]
+        );
+    }
+
+    #[test]
+    fn lines_shift_with_insertions() {
+        let diffs = [
+            // Change line 50 of a pre-existing file.
+            "diff --git a/f b/f\n@@ -50 +50 @@\n",
+            // Insert five lines at the top of the file.
+            "diff --git a/f b/f\n@@ -0,0 +1,5 @@\n",
+            // Change line 55, formerly line 50.
+            "diff --git a/f b/f\n@@ -55 +55 @@\n",
+            // Change line 50, not touched by any patch.
+            "diff --git a/f b/f\n@@ -50 +50 @@\n",
+        ];
+        assert_eq!(deps(&diffs), vec![vec![], vec![], vec![0], vec![]]);
+    }
+
+    #[test]
+    fn multiple_hunks_per_file() {
+        let diffs = [
+            "diff --git a/f b/f\n@@ -10 +10,3 @@\n@@ -30 +32 @@\n",
+            // Line 32 was line 30 before the insertions from patch 0.
+            "diff --git a/f b/f\n@@ -1,0 +2,2 @@\n@@ -32 +34 @@\n",
+        ];
+        assert_eq!(deps(&diffs), vec![vec![], vec![0]]);
+    }
+
+    #[test]
+    fn removed_lines() {
+        let diffs = [
+            "diff --git a/f b/f\n@@ -20,5 +19,0 @@\n",
+            // Line 20 is next to where lines were removed.
+            "diff --git a/f b/f\n@@ -20 +20 @@\n",
+            // Line 40 is far away.
+            "diff --git a/f b/f\n@@ -40 +40 @@\n",
+        ];
+        assert_eq!(deps(&diffs), vec![vec![], vec![0], vec![]]);
+    }
+
+    #[test]
+    fn binary_files() {
+        let diffs = [
+            "diff --git a/bin b/bin\nBinary files differ\n",
+            "diff --git a/bin b/bin\nBinary files differ\n",
+            "diff --git a/f b/f\n@@ -1 +1 @@\n",
+        ];
+        assert_eq!(deps(&diffs), vec![vec![], vec![0], vec![]]);
+    }
+}
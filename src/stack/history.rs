@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Access to the history of stack states.
+//!
+//! Each stack state commit refers to its predecessor via [`StackState::prev`]. Walking
+//! this chain of states allows the evolution of individual patches to be recovered.
+
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use super::{state::StackState, Stack, StackAccess};
+use crate::patch::PatchName;
+
+/// Iterator over stack states, from newest to oldest.
+///
+/// Each item is a stack state commit along with its decoded [`StackState`].
+pub(crate) struct StateHistory<'repo> {
+    repo: &'repo gix::Repository,
+    next: Option<Rc<gix::Commit<'repo>>>,
+}
+
+impl<'repo> StateHistory<'repo> {
+    /// Walk stack states starting with the given stack state commit.
+    pub(crate) fn new(repo: &'repo gix::Repository, state_commit: Rc<gix::Commit<'repo>>) -> Self {
+        Self {
+            repo,
+            next: Some(state_commit),
+        }
+    }
+
+    /// Walk stack states starting with the stack's current state.
+    pub(crate) fn from_stack(stack: &Stack<'repo>) -> Result<Self> {
+        let state_commit = stack
+            .repo
+            .find_reference(stack.get_stack_refname())?
+            .peel_to_commit()?;
+        Ok(Self::new(stack.repo, Rc::new(state_commit)))
+    }
+}
+
+impl<'repo> Iterator for StateHistory<'repo> {
+    type Item = Result<(Rc<gix::Commit<'repo>>, StackState<'repo>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let state_commit = self.next.take()?;
+        match StackState::from_commit(self.repo, &state_commit) {
+            Ok(state) => {
+                self.next.clone_from(&state.prev);
+                Some(Ok((state_commit, state)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Get the distinct commits a patch has had, oldest first.
+///
+/// The `states` are expected in the order produced by [`StateHistory`], newest first.
+/// Versions are keyed on patch name; a patch that was renamed is only tracked under its
+/// current name from the point of the rename onward. A commit that recurs later in the
+/// history, e.g. due to an undo, is only reported once at its first appearance.
+pub(crate) fn patch_versions<'repo>(
+    states: &[(Rc<gix::Commit<'repo>>, StackState<'repo>)],
+    patchname: &PatchName,
+) -> Vec<Rc<gix::Commit<'repo>>> {
+    let mut versions: Vec<Rc<gix::Commit<'repo>>> = Vec::new();
+    for (_, state) in states {
+        if let Some(patch) = state.patches.get(patchname) {
+            // The oldest occurrence of a commit determines the version's position.
+            if let Some(pos) = versions
+                .iter()
+                .position(|commit| commit.id == patch.commit.id)
+            {
+                versions.remove(pos);
+            }
+            versions.push(patch.commit.clone());
+        }
+    }
+    versions.reverse();
+    versions
+}
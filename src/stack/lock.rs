@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Advisory locking of stacks against concurrent modification.
+//!
+//! A stack transaction updates the working tree, index, branch, and stack state
+//! reference over a span of time during which another StGit process, e.g. one run by an
+//! editor integration, could otherwise modify the same stack. Stack modifications are
+//! serialized by holding a lock file in the repository's common git directory:
+//! `stgit/locks/<branch>.lock`.
+//!
+//! By default, an attempt to lock a busy stack fails immediately. The `stg --wait`
+//! option instead makes StGit wait for the stack to become available, both before
+//! modifying a stack and before reading a stack that is being modified.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+/// How long to wait for a busy stack. Not waiting is the default.
+static WAIT: OnceLock<Duration> = OnceLock::new();
+
+/// Lock resource paths of stacks locked by this process.
+static HELD: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Set how long to wait for busy stacks to become available.
+pub(crate) fn set_wait(timeout: Duration) {
+    WAIT.set(timeout).ok();
+}
+
+/// Lock on a stack, held until dropped.
+pub(crate) struct StackLock {
+    _marker: gix::lock::Marker,
+    resource_path: PathBuf,
+}
+
+impl Drop for StackLock {
+    fn drop(&mut self) {
+        if let Ok(mut held) = HELD.lock() {
+            held.retain(|path| path != &self.resource_path);
+        }
+    }
+}
+
+/// Lock the stack of the given branch.
+///
+/// An error is returned if the stack is locked by another process and does not become
+/// available within the wait time set with [`set_wait()`].
+pub(crate) fn lock(repo: &gix::Repository, branch_name: &str) -> Result<StackLock> {
+    let resource_path = resource_path(repo, branch_name);
+    let mode = WAIT
+        .get()
+        .map_or(gix::lock::acquire::Fail::Immediately, |&timeout| {
+            timeout.into()
+        });
+    match gix::lock::Marker::acquire_to_hold_resource(
+        &resource_path,
+        mode,
+        Some(repo.common_dir().to_owned()),
+    ) {
+        Ok(marker) => {
+            HELD.lock()
+                .expect("stack lock registry is not poisoned")
+                .push(resource_path.clone());
+            Ok(StackLock {
+                _marker: marker,
+                resource_path,
+            })
+        }
+        Err(gix::lock::acquire::Error::PermanentlyLocked { .. }) => {
+            Err(busy_error(branch_name, &resource_path))
+        }
+        Err(e) => Err(anyhow!("cannot lock stack `{branch_name}`: {e}")),
+    }
+}
+
+/// Wait for the stack of the given branch to be unlocked.
+///
+/// Returns immediately unless a wait time was set with [`set_wait()`] or if the stack
+/// is locked by this process.
+pub(crate) fn wait_unlocked(repo: &gix::Repository, branch_name: &str) -> Result<()> {
+    let Some(&timeout) = WAIT.get().filter(|timeout| !timeout.is_zero()) else {
+        return Ok(());
+    };
+    let resource_path = resource_path(repo, branch_name);
+    if HELD
+        .lock()
+        .expect("stack lock registry is not poisoned")
+        .contains(&resource_path)
+    {
+        return Ok(());
+    }
+
+    let lock_path = lock_path(&resource_path);
+    for wait in gix::lock::backoff::Quadratic::default_with_random().until_no_remaining(timeout) {
+        if !lock_path.exists() {
+            return Ok(());
+        }
+        std::thread::sleep(wait);
+    }
+    if lock_path.exists() {
+        Err(busy_error(branch_name, &resource_path))
+    } else {
+        Ok(())
+    }
+}
+
+fn resource_path(repo: &gix::Repository, branch_name: &str) -> PathBuf {
+    repo.common_dir()
+        .join("stgit")
+        .join("locks")
+        .join(branch_name)
+}
+
+fn lock_path(resource_path: &Path) -> PathBuf {
+    let mut lock_path = resource_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+fn busy_error(branch_name: &str, resource_path: &Path) -> anyhow::Error {
+    anyhow!(
+        "stack `{branch_name}` is busy: another StGit process is modifying it. \
+         Use `stg --wait` to wait for the stack to become available. If no other \
+         StGit process is running, remove `{}`",
+        lock_path(resource_path).display(),
+    )
+}
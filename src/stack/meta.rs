@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Structured per-patch metadata.
+//!
+//! Patch metadata is recorded in the stack state alongside each patch's commit id. It
+//! is thus versioned along with the rest of the stack state and follows the patch
+//! through operations such as rename, pick, squash, and undo.
+
+use std::{collections::BTreeSet, fmt::Write as _, str::FromStr};
+
+use anyhow::{anyhow, Result};
+
+/// Review status of a patch.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PatchStatus {
+    /// The patch is a work in progress.
+    Draft,
+
+    /// The patch has been sent for review.
+    Posted,
+
+    /// The patch has been approved by reviewers.
+    Approved,
+}
+
+impl PatchStatus {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            PatchStatus::Draft => "draft",
+            PatchStatus::Posted => "posted",
+            PatchStatus::Approved => "approved",
+        }
+    }
+}
+
+impl std::fmt::Display for PatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PatchStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "draft" => Ok(PatchStatus::Draft),
+            "posted" => Ok(PatchStatus::Posted),
+            "approved" => Ok(PatchStatus::Approved),
+            _ => Err(anyhow!("invalid patch status `{s}`")),
+        }
+    }
+}
+
+/// Metadata associated with a patch.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PatchMeta {
+    /// Free-form labels.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) labels: BTreeSet<String>,
+
+    /// Review status.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) status: Option<PatchStatus>,
+
+    /// Message-Id of the email the patch was most recently sent or received as.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) message_id: Option<String>,
+
+    /// Changelog entries, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) changelog: Vec<String>,
+}
+
+impl PatchMeta {
+    /// Determine whether no metadata is recorded.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+            && self.status.is_none()
+            && self.message_id.is_none()
+            && self.changelog.is_empty()
+    }
+
+    /// Combine metadata from several patches, e.g. when squashing.
+    ///
+    /// Labels and changelog entries are accumulated. The status and Message-Id are
+    /// taken from the first patch having them.
+    pub(crate) fn merge<'a>(metas: impl IntoIterator<Item = &'a PatchMeta>) -> PatchMeta {
+        let mut merged = PatchMeta::default();
+        for meta in metas {
+            merged.labels.extend(meta.labels.iter().cloned());
+            if merged.status.is_none() {
+                merged.status = meta.status;
+            }
+            if merged.message_id.is_none() {
+                merged.message_id.clone_from(&meta.message_id);
+            }
+            merged.changelog.extend(meta.changelog.iter().cloned());
+        }
+        merged
+    }
+
+    /// Format metadata as "Key: value" lines.
+    ///
+    /// Nothing is emitted for unset fields. Each changelog entry is on its own line,
+    /// indented below a `Changelog:` header.
+    pub(crate) fn format(&self) -> String {
+        let mut s = String::new();
+        if let Some(status) = self.status {
+            writeln!(s, "Status: {status}").unwrap();
+        }
+        if !self.labels.is_empty() {
+            let labels: Vec<&str> = self.labels.iter().map(String::as_str).collect();
+            writeln!(s, "Labels: {}", labels.join(", ")).unwrap();
+        }
+        if let Some(message_id) = self.message_id.as_ref() {
+            writeln!(s, "Message-Id: {message_id}").unwrap();
+        }
+        if !self.changelog.is_empty() {
+            s.push_str("Changelog:\n");
+            for entry in &self.changelog {
+                writeln!(s, "  {entry}").unwrap();
+            }
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_meta_serializes_to_empty_object() {
+        let meta = PatchMeta::default();
+        assert!(meta.is_empty());
+        assert_eq!(serde_json::to_string(&meta).unwrap(), "{}");
+        let meta: PatchMeta = serde_json::from_str("{}").unwrap();
+        assert!(meta.is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let meta = PatchMeta {
+            labels: ["net".to_string(), "fix".to_string()].into(),
+            status: Some(PatchStatus::Posted),
+            message_id: Some("<abc@example.com>".to_string()),
+            changelog: vec!["v2: rebase".to_string()],
+        };
+        let json = serde_json::to_string(&meta).unwrap();
+        assert_eq!(
+            json,
+            r#"{"labels":["fix","net"],"status":"posted","message-id":"<abc@example.com>","changelog":["v2: rebase"]}"#
+        );
+        assert_eq!(serde_json::from_str::<PatchMeta>(&json).unwrap(), meta);
+    }
+
+    #[test]
+    fn merge() {
+        let a = PatchMeta {
+            labels: ["a".to_string()].into(),
+            changelog: vec!["one".to_string()],
+            ..Default::default()
+        };
+        let b = PatchMeta {
+            labels: ["b".to_string()].into(),
+            status: Some(PatchStatus::Approved),
+            message_id: Some("<b@x>".to_string()),
+            changelog: vec!["two".to_string()],
+        };
+        let merged = PatchMeta::merge([&a, &b]);
+        assert_eq!(merged.labels.len(), 2);
+        assert_eq!(merged.status, Some(PatchStatus::Approved));
+        assert_eq!(merged.message_id.as_deref(), Some("<b@x>"));
+        assert_eq!(merged.changelog, vec!["one", "two"]);
+    }
+}
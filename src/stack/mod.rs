@@ -2,8 +2,14 @@
 
 //! The StGit stack data structure.
 mod access;
+mod deps;
+mod history;
 mod iter;
+mod lock;
+mod meta;
+mod sequencer;
 mod serde;
+mod series_version;
 #[allow(clippy::module_inception)]
 mod stack;
 mod state;
@@ -11,6 +17,15 @@ mod transaction;
 mod upgrade;
 
 pub(crate) use access::{StackAccess, StackStateAccess};
-pub(crate) use stack::{state_refname_from_branch_name, InitializationPolicy, Stack};
+pub(crate) use deps::DependencyGraph;
+pub(crate) use history::{patch_versions, StateHistory};
+pub(crate) use lock::set_wait as set_lock_wait;
+pub(crate) use meta::{PatchMeta, PatchStatus};
+pub(crate) use sequencer::{Sequence, Step, Stopped};
+pub(crate) use series_version::{create_series_version_ref, SeriesVersion};
+pub(crate) use stack::{
+    delete_stale_patch_refs, published_refname_prefix, state_refname_from_branch_name,
+    InitializationPolicy, Stack,
+};
 pub(crate) use state::{PatchState, StackState};
 pub(crate) use transaction::{Error as TransactionError, StackTransaction};
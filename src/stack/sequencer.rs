@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Persistent record of a multi-step operation in progress.
+//!
+//! Commands such as `stg push`, `stg rebase`, and `stg pick` may stop part way through
+//! their work, e.g. due to merge conflicts. The work remaining to be done is recorded
+//! in a [`Sequence`] so that the operation may later be continued, or rolled back, by
+//! the command's `--continue`, `--skip`, and `--abort` options.
+//!
+//! Sequences are stored as JSON in `<git-dir>/stgit/sequencer/<branch>`.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use super::{Stack, StackAccess, StackStateAccess};
+use crate::patch::PatchName;
+
+/// A multi-step operation that has been stopped before completion.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Sequence {
+    /// Name of the command that started the operation, e.g. "push".
+    pub(crate) operation: String,
+
+    /// Stack state commit from before the operation started.
+    #[serde(with = "hex_oid")]
+    pub(crate) orig_state: gix::ObjectId,
+
+    /// The patch whose step caused the operation to stop, if any.
+    pub(crate) stopped: Option<Stopped>,
+
+    /// Steps remaining to be performed.
+    pub(crate) todo: Vec<Step>,
+
+    /// Command-specific options to be reused when the operation is resumed.
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+
+    /// Check for patches merged upstream when pushing.
+    #[serde(default)]
+    pub(crate) check_merged: bool,
+
+    /// Allow patches to be pushed with conflicts.
+    #[serde(default = "default_true")]
+    pub(crate) allow_push_conflicts: bool,
+
+    /// Use author date as the committer date of new patch commits.
+    #[serde(default)]
+    pub(crate) committer_date_is_author_date: bool,
+}
+
+/// The patch being operated on when a sequence stopped.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Stopped {
+    /// Name of the patch.
+    pub(crate) patchname: PatchName,
+
+    /// The patch's commit prior to the step; `None` if the step created the patch.
+    #[serde(default, with = "opt_hex_oid")]
+    pub(crate) commit: Option<gix::ObjectId>,
+
+    /// Whether the patch was applied prior to the step.
+    #[serde(default)]
+    pub(crate) applied: bool,
+}
+
+/// A unit of work in a [`Sequence`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Step {
+    /// Push the patch.
+    Push(PatchName),
+
+    /// Push the patch and then spill its changes into the index and worktree.
+    Spill(PatchName),
+
+    /// Run a shell command.
+    Exec(String),
+
+    /// Stop the operation.
+    Break,
+
+    /// Merge the applied, topmost patch with its counterpart from `stg sync`'s
+    /// reference branch or series.
+    Sync(PatchName),
+
+    /// Import a patch file from a series.
+    Import {
+        path: PathBuf,
+        strip_level: Option<usize>,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Sequence {
+    /// Create a new sequence for the given operation and original stack state.
+    pub(crate) fn new(operation: &str, orig_state: gix::ObjectId) -> Self {
+        Self {
+            operation: operation.to_string(),
+            orig_state,
+            stopped: None,
+            todo: Vec::new(),
+            args: Vec::new(),
+            check_merged: false,
+            allow_push_conflicts: true,
+            committer_date_is_author_date: false,
+        }
+    }
+
+    /// Determine whether the sequence still applies to the stack.
+    ///
+    /// A sequence that stopped on a patch is stale once that patch is no longer the
+    /// topmost applied patch, e.g. due to the operation being undone.
+    pub(crate) fn is_current(&self, stack: &Stack) -> bool {
+        if let Some(stopped) = self.stopped.as_ref() {
+            stack.applied().last() == Some(&stopped.patchname)
+        } else {
+            true
+        }
+    }
+}
+
+impl Stack<'_> {
+    /// Get the stack's operation in progress, if any.
+    pub(crate) fn sequence(&self) -> Result<Option<Sequence>> {
+        let path = self.sequence_path();
+        match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("reading `{}`", path.display()))
+                .map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("reading `{}`", path.display())),
+        }
+    }
+
+    /// Record the stack's operation in progress.
+    pub(crate) fn save_sequence(&self, sequence: &Sequence) -> Result<()> {
+        let path = self.sequence_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_vec_pretty(sequence)?;
+        std::fs::write(&path, data).with_context(|| format!("writing `{}`", path.display()))
+    }
+
+    /// Remove the stack's operation in progress record, if any.
+    pub(crate) fn clear_sequence(&self) -> Result<()> {
+        let path = self.sequence_path();
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("removing `{}`", path.display())),
+        }
+    }
+
+    fn sequence_path(&self) -> PathBuf {
+        sequence_path(self.repo, self.get_branch_name())
+    }
+}
+
+/// Get path to a branch's operation in progress record.
+pub(super) fn sequence_path(repo: &gix::Repository, branch_name: &str) -> PathBuf {
+    repo.git_dir()
+        .join("stgit")
+        .join("sequencer")
+        .join(branch_name)
+}
+
+mod hex_oid {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(id: &gix::ObjectId, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&id.to_string())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<gix::ObjectId, D::Error> {
+        let hex = String::deserialize(d)?;
+        gix::ObjectId::from_hex(hex.as_bytes()).map_err(serde::de::Error::custom)
+    }
+}
+
+mod opt_hex_oid {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        id: &Option<gix::ObjectId>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        match id {
+            Some(id) => s.serialize_some(&id.to_string()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Option<gix::ObjectId>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|hex| gix::ObjectId::from_hex(hex.as_bytes()).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut sequence = Sequence::new(
+            "rebase",
+            gix::ObjectId::from_hex(b"0123456789abcdef0123456789abcdef01234567").unwrap(),
+        );
+        sequence.stopped = Some(Stopped {
+            patchname: PatchName::from_str("p1").unwrap(),
+            commit: None,
+            applied: false,
+        });
+        sequence.todo = vec![
+            Step::Push(PatchName::from_str("p2").unwrap()),
+            Step::Exec("make test # comment".to_string()),
+            Step::Break,
+        ];
+        let data = serde_json::to_vec(&sequence).unwrap();
+        let parsed: Sequence = serde_json::from_slice(&data).unwrap();
+        assert_eq!(parsed.operation, "rebase");
+        assert_eq!(parsed.orig_state, sequence.orig_state);
+        assert_eq!(parsed.todo, sequence.todo);
+        assert!(parsed.allow_push_conflicts);
+        let stopped = parsed.stopped.unwrap();
+        assert_eq!(stopped.patchname.to_string(), "p1");
+        assert!(stopped.commit.is_none());
+    }
+}
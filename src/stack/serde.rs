@@ -6,6 +6,7 @@ use std::collections::BTreeMap;
 
 use anyhow::{Context, Result};
 
+use super::meta::PatchMeta;
 use crate::patch::PatchName;
 
 /// Raw state deserialization representation.
@@ -24,6 +25,9 @@ pub(crate) struct RawStackState {
 pub(crate) struct RawPatchState {
     /// The commit id of the patch.
     pub oid: gix::ObjectId,
+
+    /// The patch's metadata.
+    pub meta: PatchMeta,
 }
 
 impl RawStackState {
@@ -55,6 +59,8 @@ impl<'de> serde::Deserialize<'de> for RawStackState {
         #[derive(serde::Deserialize)]
         struct DeserPatchState {
             pub oid: String,
+            #[serde(default)]
+            pub meta: PatchMeta,
         }
 
         let ds = DeserState::deserialize(deserializer)?;
@@ -90,7 +96,13 @@ impl<'de> serde::Deserialize<'de> for RawStackState {
                     patchname, &raw_patch.oid
                 ))
             })?;
-            patches.insert(patchname, RawPatchState { oid });
+            patches.insert(
+                patchname,
+                RawPatchState {
+                    oid,
+                    meta: raw_patch.meta,
+                },
+            );
         }
 
         Ok(RawStackState {
@@ -117,22 +129,25 @@ impl serde::Serialize for super::state::StackState<'_> {
             pub applied: &'a Vec<PatchName>,
             pub unapplied: &'a Vec<PatchName>,
             pub hidden: &'a Vec<PatchName>,
-            pub patches: BTreeMap<&'a PatchName, SerializablePatchState>,
+            pub patches: BTreeMap<&'a PatchName, SerializablePatchState<'a>>,
         }
 
         #[derive(serde::Serialize)]
-        struct SerializablePatchState {
+        struct SerializablePatchState<'a> {
             pub oid: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub meta: Option<&'a PatchMeta>,
         }
 
         let prev: Option<String> = self.prev.as_ref().map(|commit| commit.id().to_string());
         let head: String = self.head.id().to_string();
-        let mut patches: BTreeMap<&PatchName, SerializablePatchState> = BTreeMap::new();
+        let mut patches: BTreeMap<&PatchName, SerializablePatchState<'_>> = BTreeMap::new();
         for (patchname, patch_state) in &self.patches {
             patches.insert(
                 patchname,
                 SerializablePatchState {
                     oid: patch_state.commit.id().to_string(),
+                    meta: (!patch_state.meta.is_empty()).then_some(&patch_state.meta),
                 },
             );
         }
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: GPL-2.0-only
+
+//! Named, immutable snapshots of a stack's state.
+//!
+//! A series version records the stack state commit as of, e.g., sending a patch series
+//! for review. Series versions are stored as references to stack state commits under
+//! `refs/stacks/<branch>.versions/<name>`.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, Result};
+use bstr::ByteSlice;
+
+use super::{Stack, StackAccess};
+
+/// A saved series version.
+pub(crate) struct SeriesVersion<'repo> {
+    /// Name of the version, e.g. "v2".
+    pub(crate) name: String,
+
+    /// Stack state commit recorded for the version.
+    pub(crate) state_commit: gix::Commit<'repo>,
+}
+
+impl SeriesVersion<'_> {
+    /// Get the version number for names of the form "v<n>".
+    pub(crate) fn number(&self) -> Option<usize> {
+        version_number(&self.name)
+    }
+}
+
+/// Get the version number for names of the form "v<n>".
+fn version_number(name: &str) -> Option<usize> {
+    name.strip_prefix('v').and_then(|n| n.parse::<usize>().ok())
+}
+
+/// Get reference name prefix for a branch's series versions.
+pub(super) fn series_version_ref_prefix(branch_name: &str) -> String {
+    format!("refs/stacks/{branch_name}.versions/")
+}
+
+/// Order numbered versions by number, followed by other versions ordered by name.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (version_number(a), version_number(b)) {
+        (Some(a_num), Some(b_num)) => a_num.cmp(&b_num).then_with(|| a.cmp(b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+impl<'repo> Stack<'repo> {
+    /// Get the stack's saved series versions in version order.
+    pub(crate) fn series_versions(&self) -> Result<Vec<SeriesVersion<'repo>>> {
+        let prefix = series_version_ref_prefix(self.get_branch_name());
+        let names: Vec<String> = self
+            .repo
+            .references()?
+            .all()?
+            .filter_map(Result::ok)
+            .filter_map(|reference| {
+                reference
+                    .name()
+                    .as_bstr()
+                    .strip_prefix(prefix.as_bytes())
+                    .map(|name| name.to_str_lossy().to_string())
+            })
+            .collect();
+        let mut versions = names
+            .iter()
+            .map(|name| self.series_version(name))
+            .collect::<Result<Vec<_>>>()?;
+        versions.sort_by(|a, b| compare_versions(&a.name, &b.name));
+        Ok(versions)
+    }
+
+    /// Get saved series version by name.
+    pub(crate) fn series_version(&self, name: &str) -> Result<SeriesVersion<'repo>> {
+        let refname = format!(
+            "{}{name}",
+            series_version_ref_prefix(self.get_branch_name())
+        );
+        let mut reference = self
+            .repo
+            .try_find_reference(refname.as_str())?
+            .ok_or_else(|| anyhow!("series version `{name}` not found"))?;
+        let state_commit = reference.peel_to_commit()?;
+        Ok(SeriesVersion {
+            name: name.to_string(),
+            state_commit,
+        })
+    }
+
+    /// Get the name for the next numbered series version.
+    pub(crate) fn next_series_version_name(&self) -> Result<String> {
+        let next_number = self
+            .series_versions()?
+            .iter()
+            .filter_map(SeriesVersion::number)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        Ok(format!("v{next_number}"))
+    }
+
+    /// Save the current stack state as a named series version.
+    ///
+    /// Series versions are immutable; an error is returned if the named version
+    /// already exists.
+    pub(crate) fn save_series_version(&self, name: &str) -> Result<()> {
+        let state_commit_id = self
+            .repo
+            .find_reference(self.get_stack_refname())?
+            .peel_to_commit()?
+            .id;
+        create_series_version_ref(
+            self.repo,
+            self.get_branch_name(),
+            name,
+            state_commit_id,
+            format!("series-version save {name}"),
+        )
+    }
+}
+
+/// Create reference for a series version of the given branch.
+///
+/// Fails if the series version already exists.
+pub(crate) fn create_series_version_ref(
+    repo: &gix::Repository,
+    branch_name: &str,
+    name: &str,
+    state_commit_id: gix::ObjectId,
+    message: String,
+) -> Result<()> {
+    let refname = format!("{}{name}", series_version_ref_prefix(branch_name));
+    let refname = gix::refs::FullName::try_from(refname)
+        .map_err(|_| anyhow!("invalid series version name `{name}`"))?;
+    if repo.try_find_reference(refname.as_ref())?.is_some() {
+        return Err(anyhow!("series version `{name}` already exists"));
+    }
+    repo.edit_reference(gix::refs::transaction::RefEdit {
+        change: gix::refs::transaction::Change::Update {
+            log: gix::refs::transaction::LogChange {
+                mode: gix::refs::transaction::RefLog::AndReference,
+                force_create_reflog: false,
+                message: message.into(),
+            },
+            expected: gix::refs::transaction::PreviousValue::MustNotExist,
+            new: gix::refs::Target::Object(state_commit_id),
+        },
+        name: refname,
+        deref: false,
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_numbers() {
+        assert_eq!(version_number("v1"), Some(1));
+        assert_eq!(version_number("v12"), Some(12));
+        assert_eq!(version_number("v"), None);
+        assert_eq!(version_number("1"), None);
+        assert_eq!(version_number("v2-rc"), None);
+    }
+
+    #[test]
+    fn version_ordering() {
+        let mut names = vec!["final", "v10", "v2", "rfc", "v1"];
+        names.sort_by(|a, b| compare_versions(a, b));
+        assert_eq!(names, vec!["v1", "v2", "v10", "final", "rfc"]);
+    }
+}
@@ -8,12 +8,17 @@ use anyhow::{anyhow, Context, Result};
 use bstr::ByteSlice;
 
 use super::{
-    state::StackState, transaction::TransactionBuilder, upgrade::stack_upgrade, PatchState,
-    StackAccess, StackStateAccess,
+    lock::{self, StackLock},
+    sequencer::sequence_path,
+    series_version::series_version_ref_prefix,
+    state::StackState,
+    transaction::TransactionBuilder,
+    upgrade::stack_upgrade,
+    PatchState, StackAccess, StackStateAccess, StateHistory,
 };
 use crate::{
     branchloc::BranchLocator,
-    ext::RepositoryExtended,
+    ext::{CommitExtended, RepositoryExtended},
     patch::PatchName,
     wrap::{Branch, PartialRefName},
 };
@@ -30,6 +35,7 @@ pub(crate) struct Stack<'repo> {
     stack_refname: String,
     base: Rc<gix::Commit<'repo>>,
     state: StackState<'repo>,
+    state_commit_id: Option<gix::ObjectId>,
     is_initialized: bool,
 }
 
@@ -58,9 +64,11 @@ pub(crate) enum InitializationPolicy {
 impl<'repo> Stack<'repo> {
     /// Remove StGit stack state from the repository.
     ///
-    /// This removes the reference to the stack state, i.e. `refs/stacks/<name>`, and
-    /// references to the stacks patches found in `refs/patches/<name>/`. StGit specific
-    /// configuration associated with the stack is also removed from the config.
+    /// This removes the reference to the stack state, i.e. `refs/stacks/<name>`,
+    /// references to the stacks patches found in `refs/patches/<name>/`, and the series
+    /// version and published state references found in `refs/stacks/<name>.versions/`
+    /// and `refs/stacks/<name>.published/`. StGit specific configuration associated
+    /// with the stack is also removed from the config.
     ///
     /// N.B. stack and patch commits that become unreferenced are subject to git's
     /// normal periodic garbage collection.
@@ -73,21 +81,28 @@ impl<'repo> Stack<'repo> {
         } = self;
         let state_ref = repo.find_reference(&stack_refname)?;
         let patch_ref_prefix = get_patch_refname(&branch_name, "");
+        let version_ref_prefix = series_version_ref_prefix(&branch_name);
+        let published_ref_prefix = published_refname_prefix(&branch_name);
         for patch_reference in
             repo.references()?
                 .all()?
                 .filter_map(Result::ok)
                 .filter(|reference| {
-                    reference
-                        .name()
-                        .as_bstr()
-                        .starts_with(patch_ref_prefix.as_bytes())
+                    let name = reference.name().as_bstr();
+                    name.starts_with(patch_ref_prefix.as_bytes())
+                        || name.starts_with(version_ref_prefix.as_bytes())
+                        || name.starts_with(published_ref_prefix.as_bytes())
                 })
         {
             patch_reference.delete()?;
         }
         state_ref.delete()?;
 
+        let sequence_path = sequence_path(repo, &branch_name);
+        if sequence_path.is_file() {
+            std::fs::remove_file(sequence_path)?;
+        }
+
         // It is ok if the StGit-specific config section does not exist.
         let mut local_config_file = repo.local_config_file()?;
         let section = format!("{branch_name}.stgit");
@@ -147,13 +162,22 @@ impl<'repo> Stack<'repo> {
         let stack_refname = state_refname_from_branch_name(&branch_name);
         let is_initialized;
 
+        lock::wait_unlocked(repo, &branch_name)?;
         stack_upgrade(repo, &branch_name)?;
 
         let maybe_state_ref = repo.find_reference(&stack_refname).ok();
 
+        type StateBaseAndId<'repo> = (
+            StackState<'repo>,
+            Rc<gix::Commit<'repo>>,
+            Option<gix::ObjectId>,
+        );
+
         let state_and_base_from_ref =
-            |state_ref: gix::Reference<'repo>| -> Result<(StackState<'repo>, Rc<gix::Commit<'repo>>)> {
-                let stack_tree = state_ref.id().object()?.try_into_commit()?.tree()?;
+            |state_ref: gix::Reference<'repo>| -> Result<StateBaseAndId<'repo>> {
+                let state_commit = state_ref.id().object()?.try_into_commit()?;
+                let state_commit_id = state_commit.id;
+                let stack_tree = state_commit.tree()?;
                 let state = StackState::from_tree(repo, stack_tree)?;
                 let base = if let Some(first_patchname) = state.applied.first() {
                     Rc::new(
@@ -169,18 +193,17 @@ impl<'repo> Stack<'repo> {
                 } else {
                     branch_head.clone()
                 };
-                Ok((state, base))
+                Ok((state, base, Some(state_commit_id)))
             };
 
-        let initialize_state_and_base =
-            || -> Result<(StackState<'repo>, Rc<gix::Commit<'repo>>)> {
-                let state = StackState::new(branch_head.clone());
-                let base = branch_head.clone();
-                state.commit(repo, Some(&stack_refname), "initialize")?;
-                Ok((state, base))
-            };
+        let initialize_state_and_base = || -> Result<StateBaseAndId<'repo>> {
+            let state = StackState::new(branch_head.clone());
+            let base = branch_head.clone();
+            let state_commit_id = state.commit(repo, Some(&stack_refname), "initialize")?;
+            Ok((state, base, Some(state_commit_id)))
+        };
 
-        let (state, base) = match init_policy {
+        let (state, base, state_commit_id) = match init_policy {
             InitializationPolicy::AutoInitialize => {
                 is_initialized = true;
                 if let Some(state_ref) = maybe_state_ref {
@@ -217,7 +240,7 @@ impl<'repo> Stack<'repo> {
                     is_initialized = false;
                     let state = StackState::new(branch_head.clone());
                     let base = branch_head.clone();
-                    (state, base)
+                    (state, base, None)
                 }
             }
         };
@@ -231,6 +254,7 @@ impl<'repo> Stack<'repo> {
             stack_refname,
             base,
             state,
+            state_commit_id,
             is_initialized,
         })
     }
@@ -340,7 +364,11 @@ impl<'repo> Stack<'repo> {
             deref: false,
         })?;
 
-        Ok(Self { state, ..self })
+        Ok(Self {
+            state,
+            state_commit_id: Some(state_commit_id),
+            ..self
+        })
     }
 
     /// Start a transaction to modify the stack.
@@ -352,20 +380,100 @@ impl<'repo> Stack<'repo> {
         TransactionBuilder::new(self)
     }
 
+    /// Lock the stack against modification by other StGit processes.
+    ///
+    /// An error is returned if the stack is busy or if the stack state was modified
+    /// since this stack was loaded.
+    pub(super) fn lock(&self) -> Result<StackLock> {
+        let lock = lock::lock(self.repo, &self.branch_name)?;
+        let state_commit_id = self
+            .repo
+            .find_reference(&self.stack_refname)
+            .ok()
+            .and_then(|reference| reference.try_id().map(|id| id.detach()));
+        if state_commit_id == self.state_commit_id {
+            Ok(lock)
+        } else {
+            Err(anyhow!(
+                "stack `{}` was modified by another process; try again",
+                self.branch_name
+            ))
+        }
+    }
+
     /// Clear the stack state history.
     pub(crate) fn clear_state_log(&mut self, reflog_msg: &str) -> Result<()> {
+        let _lock = self.lock()?;
         self.state.prev = None;
-        self.state
-            .commit(self.repo, Some(&self.stack_refname), reflog_msg)?;
+        let state_commit_id =
+            self.state
+                .commit(self.repo, Some(&self.stack_refname), reflog_msg)?;
+        self.state_commit_id = Some(state_commit_id);
         Ok(())
     }
 
+    /// Rewrite the stack state history to retain only the `keep` newest states.
+    ///
+    /// The retained states are recommitted with their original messages and
+    /// signatures such that undo and redo remain available within the retained
+    /// history. The current state is always retained.
+    ///
+    /// Returns the number of states dropped from the history.
+    pub(crate) fn compact_state_log(&mut self, keep: usize, reflog_msg: &str) -> Result<usize> {
+        let _lock = self.lock()?;
+        let keep = keep.max(1);
+        let mut retained = Vec::with_capacity(keep);
+        let mut num_dropped = 0;
+        for entry in StateHistory::from_stack(self)? {
+            let (state_commit, state) = entry?;
+            if retained.len() < keep {
+                retained.push((state_commit, state));
+            } else {
+                num_dropped += 1;
+            }
+        }
+
+        if num_dropped == 0 {
+            return Ok(0);
+        }
+
+        let mut prev: Option<Rc<gix::Commit<'repo>>> = None;
+        for (state_commit, mut state) in retained.into_iter().rev() {
+            state.prev = prev;
+            let message = state_commit.message_ex();
+            let state_commit_id = state.commit_with_signatures(
+                self.repo,
+                None,
+                &message.decode()?,
+                state_commit.author()?,
+                state_commit.committer()?,
+            )?;
+            prev = Some(Rc::new(self.repo.find_commit(state_commit_id)?));
+        }
+        let state_commit = prev.expect("at least one state is retained");
+
+        self.repo.reference(
+            self.stack_refname.as_str(),
+            state_commit.id,
+            gix::refs::transaction::PreviousValue::Any,
+            reflog_msg,
+        )?;
+        self.state.prev = StackState::from_commit(self.repo, &state_commit)?.prev;
+        self.state_commit_id = Some(state_commit.id);
+        Ok(num_dropped)
+    }
+
     /// Update the branch and branch head commit.
     pub(super) fn update_head(&mut self, branch: Branch<'repo>, commit: Rc<gix::Commit<'repo>>) {
         self.branch = branch;
         self.branch_head = commit;
     }
 
+    /// Record the id of the stack state commit newly committed for this stack.
+    pub(super) fn set_state_commit_id(&mut self, state_commit_id: gix::ObjectId) {
+        self.state_commit_id = Some(state_commit_id);
+    }
+
     /// Get mutable reference to the stack state.
     pub(super) fn state_mut(&mut self) -> &mut StackState<'repo> {
         &mut self.state
@@ -441,11 +549,52 @@ pub(crate) fn state_refname_from_branch_name(branch_name: &str) -> String {
     format!("refs/stacks/{branch_name}")
 }
 
+/// Get reference name prefix for the stack states last published from a branch.
+///
+/// See `stg publish`.
+pub(crate) fn published_refname_prefix(branch_name: &str) -> String {
+    format!("refs/stacks/{branch_name}.published/")
+}
+
 /// Get reference name for a patch in the given branch.
 fn get_patch_refname(branch_name: &str, patch_spec: &str) -> String {
     format!("refs/patches/{branch_name}/{patch_spec}")
 }
 
+/// Delete patch references that do not belong to any stack.
+///
+/// Patch references are only maintained for stacks that exist. When a stack's state
+/// reference is removed by means other than StGit, e.g. by deleting its branch with
+/// `git branch -D`, the stack's patch references are left behind.
+///
+/// Returns the names of the deleted references.
+pub(crate) fn delete_stale_patch_refs(repo: &gix::Repository) -> Result<Vec<String>> {
+    let mut deleted = Vec::new();
+    for patch_ref in repo
+        .references()?
+        .prefixed("refs/patches/")?
+        .filter_map(Result::ok)
+    {
+        let refname = patch_ref.name().as_bstr().to_str_lossy().to_string();
+        let path = refname
+            .strip_prefix("refs/patches/")
+            .expect("references are prefixed");
+        let mut has_stack = false;
+        for (pos, _) in path.match_indices('/') {
+            let state_refname = state_refname_from_branch_name(&path[..pos]);
+            if repo.try_find_reference(state_refname.as_str())?.is_some() {
+                has_stack = true;
+                break;
+            }
+        }
+        if !has_stack {
+            patch_ref.delete()?;
+            deleted.push(refname);
+        }
+    }
+    Ok(deleted)
+}
+
 /// Fix-up stack's patch references.
 ///
 /// Ensures that each patch in the stack has a valid patch reference and that there are
@@ -10,7 +10,7 @@ use std::{collections::BTreeMap, rc::Rc, str};
 use anyhow::{anyhow, Result};
 use bstr::{BString, ByteVec};
 
-use super::{access::StackStateAccess, iter::AllPatches, serde::RawStackState};
+use super::{access::StackStateAccess, iter::AllPatches, meta::PatchMeta, serde::RawStackState};
 use crate::{
     ext::{CommitExtended, CommitOptions, RepositoryExtended},
     patch::PatchName,
@@ -49,10 +49,11 @@ pub(crate) struct StackState<'repo> {
 
 /// State associated with a patch.
 ///
-/// Currently the only state is a commit object.
+/// Each patch has a commit object and optional structured metadata.
 #[derive(Clone, Debug)]
 pub(crate) struct PatchState<'repo> {
     pub(crate) commit: Rc<gix::Commit<'repo>>,
+    pub(crate) meta: PatchMeta,
 }
 
 impl<'repo> StackStateAccess<'repo> for StackState<'repo> {
@@ -143,6 +144,7 @@ impl<'repo> StackState<'repo> {
                 patchname,
                 PatchState {
                     commit: Rc::new(commit),
+                    meta: raw_state.meta,
                 },
             );
         }
@@ -200,6 +202,27 @@ impl<'repo> StackState<'repo> {
         repo: &'repo gix::Repository,
         update_ref: Option<&str>,
         message: &str,
+    ) -> Result<gix::ObjectId> {
+        self.commit_with_signatures(
+            repo,
+            update_ref,
+            message,
+            repo.get_author()?,
+            repo.get_committer()?,
+        )
+    }
+
+    /// Commit stack state to repository using the given author and committer.
+    ///
+    /// This allows a stack state commit to be recreated with its original signatures,
+    /// e.g. when rewriting the stack state log.
+    pub(crate) fn commit_with_signatures(
+        &self,
+        repo: &'repo gix::Repository,
+        update_ref: Option<&str>,
+        message: &str,
+        author: gix::actor::SignatureRef<'_>,
+        committer: gix::actor::SignatureRef<'_>,
     ) -> Result<gix::ObjectId> {
         let (state_tree_id, prev_state) = if let Some(prev_commit) = self.prev.as_ref() {
             let prev_state = Self::from_tree(repo, prev_commit.tree()?)?;
@@ -209,8 +232,6 @@ impl<'repo> StackState<'repo> {
             (self.make_tree(repo, None)?, None)
         };
         let config = repo.config_snapshot();
-        let committer = repo.get_committer()?;
-        let author = repo.get_author()?;
 
         let simplified_parents: Vec<gix::ObjectId> = match &self.prev {
             Some(prev_commit) => {
@@ -378,7 +399,7 @@ impl<'repo> StackState<'repo> {
     ///
     /// The patch metadata blobs are for human consumption. The per-patch log,
     /// viewed with `stg log <patchname>`, shows the evolution of the patch's
-    /// metadata, including its commit message.
+    /// metadata, including its commit message and any [`PatchMeta`] fields.
     fn make_patch_meta(
         &self,
         repo: &gix::Repository,
@@ -386,12 +407,13 @@ impl<'repo> StackState<'repo> {
         prev_state: Option<&StackState>,
         prev_patches_tree: &Option<gix::Tree>,
     ) -> Result<gix::ObjectId> {
-        let commit = &self.patches[patchname].commit;
+        let patch_state = &self.patches[patchname];
+        let commit = &patch_state.commit;
         let commit_ref = commit.decode()?;
 
         if let Some(prev_state) = prev_state {
             if let Some(prev_patch) = prev_state.patches.get(patchname) {
-                if prev_patch.commit.id == commit.id {
+                if prev_patch.commit.id == commit.id && prev_patch.meta == patch_state.meta {
                     if let Some(prev_patches_tree) = prev_patches_tree {
                         let patchname_str: &str = patchname.as_ref();
                         if let Some(prev_patch_entry) = prev_patches_tree
@@ -426,7 +448,9 @@ impl<'repo> StackState<'repo> {
         patch_meta.push_str(">\n");
         patch_meta.push_str("Date:   ");
         patch_meta.push_str(date);
-        patch_meta.push_str("\n\n");
+        patch_meta.push_str("\n");
+        patch_meta.push_str(patch_state.meta.format());
+        patch_meta.push_str("\n");
         patch_meta.push_str(commit.message_raw_sloppy());
 
         let patch_meta_id = repo.write_blob(patch_meta)?;
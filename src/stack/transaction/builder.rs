@@ -2,14 +2,17 @@
 
 use std::collections::BTreeMap;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 use super::{
     options::{ConflictMode, TransactionOptions},
     ui::TransactionUserInterface,
     ExecuteContext, StackTransaction,
 };
-use crate::stack::{Stack, StackAccess, StackStateAccess};
+use crate::{
+    ext::RepositoryExtended,
+    stack::{Stack, StackAccess, StackStateAccess},
+};
 
 /// Builder used to setup a stack transaction.
 pub(crate) struct TransactionBuilder<'repo> {
@@ -153,6 +156,7 @@ impl<'repo> TransactionBuilder<'repo> {
 
         let mut transaction = StackTransaction {
             stack,
+            lock: None,
             ui,
             options,
             applied,
@@ -165,8 +169,36 @@ impl<'repo> TransactionBuilder<'repo> {
             error: None,
         };
 
-        transaction.error = f(&mut transaction).err();
+        // The lock is held until the transaction executes.
+        transaction.error = transaction
+            .stack
+            .lock()
+            .and_then(|lock| {
+                transaction.lock = Some(lock);
+                check_other_worktrees(&transaction.stack)
+            })
+            .and_then(|()| f(&mut transaction))
+            .err();
 
         ExecuteContext(transaction)
     }
 }
+
+/// Refuse to modify a stack whose branch is checked-out in another worktree.
+///
+/// The other worktree's index and working tree would otherwise be left out of sync with
+/// its branch head.
+fn check_other_worktrees(stack: &Stack) -> Result<()> {
+    if let Some(path) = stack
+        .repo
+        .get_other_worktree_for_branch(stack.get_branch_refname())?
+    {
+        Err(anyhow!(
+            "branch `{}` is checked out in worktree `{}`",
+            stack.get_branch_name(),
+            path.display()
+        ))
+    } else {
+        Ok(())
+    }
+}
@@ -40,11 +40,11 @@ use self::{
     options::{ConflictMode, TransactionOptions},
     ui::TransactionUserInterface,
 };
-use super::{state::StackState, StackAccess};
+use super::{lock::StackLock, state::StackState, StackAccess};
 use crate::{
     ext::{CommitExtended, RepositoryExtended},
     patch::PatchName,
-    stack::{PatchState, Stack, StackStateAccess},
+    stack::{PatchMeta, PatchState, Stack, StackStateAccess},
     stupid::{Stupid, StupidContext},
     wrap::Branch,
 };
@@ -61,6 +61,7 @@ pub(crate) enum Error {
 /// Stack transaction state.
 pub(crate) struct StackTransaction<'repo> {
     stack: Stack<'repo>,
+    lock: Option<StackLock>,
     ui: TransactionUserInterface,
     options: TransactionOptions,
 
@@ -134,6 +135,7 @@ impl<'repo> ExecuteContext<'repo> {
 
         let StackTransaction {
             stack,
+            lock,
             ui,
             options,
             applied,
@@ -204,19 +206,21 @@ impl<'repo> ExecuteContext<'repo> {
             .map_err(|e| rollback(current_tree_id, e))?;
         }
 
-        crate::signal::critical(|| {
+        let conflict_msg;
+        let state_reflog_msg = if has_conflicts {
+            conflict_msg = format!("{reflog_msg} (CONFLICT)");
+            &conflict_msg
+        } else {
+            reflog_msg
+        };
+
+        let (prev_state_commit_id, state_commit_id) = crate::signal::critical(|| {
             // Commit updated stack state
-            let conflict_msg;
-            let state_reflog_msg = if has_conflicts {
-                conflict_msg = format!("{reflog_msg} (CONFLICT)");
-                &conflict_msg
-            } else {
-                reflog_msg
-            };
             let branch_ref_name = stack.get_branch_refname().to_owned();
             let prev_state_commit = repo
                 .find_reference(stack.get_stack_refname())?
                 .peel_to_commit()?;
+            let prev_state_commit_id = prev_state_commit.id;
             let state = stack.state_mut();
             for (patchname, maybe_patch) in &updated_patches {
                 if let Some(patch) = maybe_patch {
@@ -300,9 +304,20 @@ impl<'repo> ExecuteContext<'repo> {
                 );
             }
 
-            Ok(())
+            Ok((prev_state_commit_id, state_commit_id))
         })
         .map_err(|e| rollback(trans_head_tree_id, e))?;
+        stack.set_state_commit_id(state_commit_id);
+
+        // The post-transaction hook may itself run StGit commands on the stack.
+        drop(lock);
+
+        crate::hook::run_post_transaction_hook(
+            repo,
+            prev_state_commit_id,
+            state_commit_id,
+            state_reflog_msg,
+        );
 
         if let Some(err) = error {
             Err(err)
@@ -440,6 +455,10 @@ impl<'repo> StackTransaction<'repo> {
         for pn in to_reset_patches {
             if existing_patches.contains(pn) {
                 if matching_patches.contains(pn) {
+                    let meta = &state.patches[pn].meta;
+                    if self.get_patch_meta(pn) != meta {
+                        self.update_patch_meta(pn, meta.clone());
+                    }
                     continue;
                 }
             } else if state.hidden.contains(pn) {
@@ -479,16 +498,27 @@ impl<'repo> StackTransaction<'repo> {
             .stupid()
             .notes_copy(old_commit.id, commit_id)
             .ok();
+        let meta = self.get_patch_meta(patchname).clone();
         self.updated_patches.insert(
             patchname.clone(),
             Some(PatchState {
                 commit: Rc::new(commit),
+                meta,
             }),
         );
         self.ui.print_updated(patchname, self.applied())?;
         Ok(())
     }
 
+    /// Replace a patch's metadata.
+    ///
+    /// The patch's commit is unchanged.
+    pub(crate) fn update_patch_meta(&mut self, patchname: &PatchName, meta: PatchMeta) {
+        let commit = self.get_patch_commit(patchname).clone();
+        self.updated_patches
+            .insert(patchname.clone(), Some(PatchState { commit, meta }));
+    }
+
     /// Add new patch to the top of the stack.
     ///
     /// The commit for the new patch must be parented by the former top commit of the
@@ -501,6 +531,7 @@ impl<'repo> StackTransaction<'repo> {
             patchname.clone(),
             Some(PatchState {
                 commit: Rc::new(commit),
+                meta: PatchMeta::default(),
             }),
         );
         self.ui.print_pushed(patchname, PushStatus::New, true)?;
@@ -522,6 +553,7 @@ impl<'repo> StackTransaction<'repo> {
             patchname.clone(),
             Some(PatchState {
                 commit: Rc::new(commit),
+                meta: PatchMeta::default(),
             }),
         );
         self.ui.print_popped(&[patchname.clone()])?;
@@ -578,10 +610,12 @@ impl<'repo> StackTransaction<'repo> {
             repo.stupid()
                 .notes_copy(patch_commit.id, new_commit_id)
                 .ok();
+            let meta = self.get_patch_meta(patchname).clone();
             self.updated_patches.insert(
                 patchname.clone(),
                 Some(PatchState {
                     commit: Rc::new(commit),
+                    meta,
                 }),
             );
 
@@ -737,6 +771,7 @@ impl<'repo> StackTransaction<'repo> {
                 patchname.clone(),
                 Some(PatchState {
                     commit: Rc::new(commit),
+                    meta: PatchMeta::default(),
                 }),
             );
             new_applied.push(patchname.clone());
@@ -1013,6 +1048,9 @@ impl<'repo> StackTransaction<'repo> {
         let patch_commit = self.get_patch_commit(patchname).clone();
         let old_parent = patch_commit.get_parent_commit()?;
         let new_parent = self.top().clone();
+
+        crate::hook::run_pre_push_hook(repo, patchname, patch_commit.id, new_parent.id)?;
+
         let patch_commit_ref = patch_commit.decode()?;
         let old_parent_ref = old_parent.decode()?;
         let new_parent_ref = new_parent.decode()?;
@@ -1137,8 +1175,9 @@ impl<'repo> StackTransaction<'repo> {
                 push_status = PushStatus::Empty;
             }
 
+            let meta = self.get_patch_meta(patchname).clone();
             self.updated_patches
-                .insert(patchname.clone(), Some(PatchState { commit }));
+                .insert(patchname.clone(), Some(PatchState { commit, meta }));
         }
 
         if push_status == PushStatus::Conflict {
@@ -137,7 +137,13 @@ fn stack_upgrade_from_4(repo: &gix::Repository, branch_name: &str) -> Result<()>
                                     format!("converting `{oid_str}` for `{patchname}`")
                                 })?;
                             patch_list.push(patchname.clone());
-                            patches.insert(patchname, RawPatchState { oid: commit_id });
+                            patches.insert(
+                                patchname,
+                                RawPatchState {
+                                    oid: commit_id,
+                                    meta: Default::default(),
+                                },
+                            );
                         }
                     } else {
                         return Err(anyhow!("malformed metadata"));
@@ -253,7 +259,13 @@ fn stack_upgrade_from_3(repo: &gix::Repository, branch_name: &str) -> Result<()>
                     .with_context(|| format!("converting `{}` to patchname", &pn))?;
                 patch_list.push(patchname.clone());
                 cleanup.push(format!("refs/patches/{branch_name}/{pn}.log"));
-                patches.insert(patchname, RawPatchState { oid: commit_id });
+                patches.insert(
+                    patchname,
+                    RawPatchState {
+                        oid: commit_id,
+                        meta: Default::default(),
+                    },
+                );
             }
         }
     }
@@ -8,8 +8,8 @@ use std::{
     cell::RefCell,
     ffi::{OsStr, OsString},
     io::Write,
-    path::Path,
-    process::{Command, Stdio},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
 };
 
 use anyhow::{anyhow, Context, Result};
@@ -317,6 +317,74 @@ impl StupidContext<'_, '_> {
         }
     }
 
+    /// Apply a zero-context patch (diff) to the specified index.
+    ///
+    /// Uses `git apply --cached --unidiff-zero`, which is suitable for diffs generated
+    /// with `-U0`.
+    ///
+    /// Returns `true` if the patch application is successful, `false` otherwise.
+    pub(crate) fn apply_unidiff_zero_to_index(&self, diff: &BStr) -> Result<bool> {
+        let apply_output = self
+            .git_in_work_root()?
+            .args(["apply", "--cached", "--unidiff-zero"])
+            .stdout(Stdio::null())
+            .in_and_out(diff)?;
+
+        if apply_output.status.success() {
+            Ok(true)
+        } else if apply_output.status.code() == Some(1) {
+            Ok(false)
+        } else {
+            Err(git_command_error("apply", &apply_output.stderr))
+        }
+    }
+
+    /// Get the commit that last changed each line of a file using `git blame`.
+    ///
+    /// Only commits in the range `base..top` are considered. Lines last changed at or
+    /// before `base` are attributed to a boundary commit outside the range.
+    ///
+    /// The returned vector has an entry for each line of the file in `top` with the id
+    /// of the commit that last changed the line and the line's number in that commit.
+    /// The path is relative to the root of the work tree.
+    pub(crate) fn blame(
+        &self,
+        base: gix::ObjectId,
+        top: gix::ObjectId,
+        path: &Path,
+    ) -> Result<Vec<(gix::ObjectId, usize)>> {
+        let output = self
+            .git_in_work_root()?
+            .args(["blame", "--porcelain"])
+            .arg(format!("{base}..{top}"))
+            .arg("--")
+            .arg(path)
+            .output_git()?
+            .require_success("blame")?;
+
+        // Each line of the file is represented by a header line starting with the
+        // commit id and the line's original line number, followed by optional commit
+        // info lines, and finally the line's content prefixed with a tab.
+        let mut blamed = Vec::new();
+        let mut expect_header = true;
+        for line in output.stdout.lines() {
+            if expect_header {
+                let mut fields = line.split_str(" ");
+                let oid = parse_oid(fields.next().unwrap_or_default())?;
+                let orig_line = fields
+                    .next()
+                    .and_then(|field| field.to_str().ok())
+                    .and_then(|field| field.parse::<usize>().ok())
+                    .ok_or_else(|| anyhow!("unexpected blame output `{}`", line.as_bstr()))?;
+                blamed.push((oid, orig_line));
+                expect_header = false;
+            } else if line.starts_with(b"\t") {
+                expect_header = true;
+            }
+        }
+        Ok(blamed)
+    }
+
     /// Copy branch
     ///
     /// Copies branch ref, reflog, and `branch.<name>` config sections.
@@ -360,6 +428,57 @@ impl StupidContext<'_, '_> {
         Ok(())
     }
 
+    /// Create a bundle file using `git bundle create`.
+    ///
+    /// The bundle contains the objects reachable from `refnames` that are not
+    /// reachable from any of the `prerequisites`.
+    pub(crate) fn bundle_create(
+        &self,
+        path: &Path,
+        refnames: &[&str],
+        prerequisites: &[gix::ObjectId],
+    ) -> Result<()> {
+        let mut command = self.git();
+        command.args(["bundle", "create", "--quiet"]).arg(path);
+        command.args(refnames);
+        command.args(prerequisites.iter().map(|oid| format!("^{oid}")));
+        command
+            .stdout(Stdio::null())
+            .output_git()?
+            .require_success("bundle create")?;
+        Ok(())
+    }
+
+    /// Get the references recorded in a bundle file using `git bundle list-heads`.
+    pub(crate) fn bundle_list_heads(&self, path: &Path) -> Result<Vec<(gix::ObjectId, BString)>> {
+        let output = self
+            .git()
+            .args(["bundle", "list-heads"])
+            .arg(path)
+            .output_git()?
+            .require_success("bundle list-heads")?;
+        let mut heads = Vec::new();
+        for line in output.stdout.lines() {
+            if let Some((oid, refname)) = line.split_once_str(" ") {
+                heads.push((parse_oid(oid)?, BString::from(refname)));
+            }
+        }
+        Ok(heads)
+    }
+
+    /// Add the objects from a bundle file to the repository using `git bundle unbundle`.
+    ///
+    /// No references are updated.
+    pub(crate) fn bundle_unbundle(&self, path: &Path) -> Result<()> {
+        self.git()
+            .args(["bundle", "unbundle"])
+            .arg(path)
+            .stdout(Stdio::null())
+            .output_git()?
+            .require_success("bundle unbundle")?;
+        Ok(())
+    }
+
     /// Checkout a branch.
     pub(crate) fn checkout(&self, branch_name: &str) -> Result<()> {
         self.git()
@@ -582,6 +701,74 @@ impl StupidContext<'_, '_> {
         Ok(BString::from(output.stdout))
     }
 
+    /// Get the status and path of each file changed between two trees.
+    ///
+    /// The output of `git diff-tree --name-status -z` is parsed into a list of status
+    /// letter and path pairs.
+    pub(crate) fn diff_tree_name_status(
+        &self,
+        tree1: gix::ObjectId,
+        tree2: gix::ObjectId,
+    ) -> Result<Vec<(BString, BString)>> {
+        let output = self
+            .git()
+            .args(["diff-tree", "-r", "-z", "--name-status", "--no-renames"])
+            .args([tree1.to_string(), tree2.to_string()])
+            .output_git()?
+            .require_success("diff-tree")?;
+        let mut fields = output.stdout.split_str(b"\0");
+        let mut entries = Vec::new();
+        while let Some(status) = fields.next() {
+            if status.is_empty() {
+                break;
+            }
+            let path = fields
+                .next()
+                .ok_or_else(|| anyhow!("unexpected diff-tree output"))?;
+            entries.push((BString::from(status), BString::from(path)));
+        }
+        Ok(entries)
+    }
+
+    /// Count files changed, lines inserted, and lines deleted between two trees.
+    ///
+    /// Binary files count as changed files, but contribute no line counts.
+    pub(crate) fn diff_tree_numstat(
+        &self,
+        tree1: gix::ObjectId,
+        tree2: gix::ObjectId,
+    ) -> Result<(usize, usize, usize)> {
+        let output = self
+            .git()
+            .args(["diff-tree", "-r", "-z", "--numstat", "--no-renames"])
+            .args([tree1.to_string(), tree2.to_string()])
+            .output_git()?
+            .require_success("diff-tree")?;
+        let mut files = 0;
+        let mut insertions = 0;
+        let mut deletions = 0;
+        for record in output.stdout.split_str(b"\0") {
+            if record.is_empty() {
+                continue;
+            }
+            let mut fields = record.splitn_str(3, b"\t");
+            let added = fields.next().unwrap_or_default();
+            let removed = fields.next().unwrap_or_default();
+            files += 1;
+            insertions += added
+                .to_str()
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+            deletions += removed
+                .to_str()
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+        }
+        Ok((files, insertions, deletions))
+    }
+
     /// Generate diff between two trees using `git diff-tree -p`.
     pub(crate) fn diff_tree_patch<SpecIter, SpecArg, OptIter, OptArg>(
         &self,
@@ -634,6 +821,36 @@ impl StupidContext<'_, '_> {
         Ok(paths)
     }
 
+    /// Fetch references from a remote using `git fetch`.
+    pub(crate) fn fetch(&self, remote: &str, refspecs: &[String]) -> Result<()> {
+        self.git()
+            .args(["fetch", "--quiet", remote])
+            .args(refspecs)
+            .stdout(Stdio::null())
+            .output_git()?
+            .require_success("fetch")?;
+        Ok(())
+    }
+
+    /// List the names of a remote's references matching a pattern using
+    /// `git ls-remote --refs`.
+    pub(crate) fn ls_remote_refs(&self, remote: &str, pattern: &str) -> Result<Vec<BString>> {
+        let output = self
+            .git()
+            .args(["ls-remote", "--refs", remote, pattern])
+            .stdout(Stdio::piped())
+            .output_git()?
+            .require_success("ls-remote")?;
+        Ok(output
+            .stdout
+            .lines()
+            .filter_map(|line| {
+                line.split_once_str("\t")
+                    .map(|(_, name)| BString::from(name))
+            })
+            .collect())
+    }
+
     /// Run `git format-patch` with arbitrary arguments.
     pub(crate) fn format_patch<OptIter, OptArg>(&self, args: OptIter) -> Result<()>
     where
@@ -831,6 +1048,52 @@ impl StupidContext<'_, '_> {
         Ok(oids)
     }
 
+    /// Get stable patch ids of commits using `git patch-id --stable`.
+    ///
+    /// Returns pairs of commit id and patch id. Commits that do not change any files
+    /// have no patch id and are omitted.
+    pub(crate) fn patch_ids(
+        &self,
+        commit_ids: impl IntoIterator<Item = gix::ObjectId>,
+    ) -> Result<Vec<(gix::ObjectId, gix::ObjectId)>> {
+        let mut input = Vec::new();
+        for commit_id in commit_ids {
+            writeln!(input, "{commit_id}")?;
+        }
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+        let diff = self
+            .git()
+            .args([
+                "diff-tree",
+                "--stdin",
+                "--patch",
+                "--full-index",
+                "--binary",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .in_and_out(&input)?
+            .require_success("diff-tree")?
+            .stdout;
+        let output = self
+            .git()
+            .args(["patch-id", "--stable"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .in_and_out(&diff)?
+            .require_success("patch-id")?;
+        let mut ids = Vec::new();
+        for line in output.stdout.lines() {
+            let (patch_id, commit_id) = line
+                .split_once_str(" ")
+                .ok_or_else(|| anyhow!("unexpected patch-id output `{}`", line.as_bstr()))?;
+            ids.push((parse_oid(commit_id)?, parse_oid(patch_id)?));
+        }
+        Ok(ids)
+    }
+
     /// Perform three-way merge with `git merge-recursive`.
     ///
     /// Returns `true` if the merge was successful, `false` otherwise.
@@ -903,6 +1166,52 @@ impl StupidContext<'_, '_> {
         Ok(())
     }
 
+    /// Push references to a remote using `git push`.
+    pub(crate) fn push(&self, remote: &str, refspecs: &[String], force: bool) -> Result<()> {
+        let mut command = self.git();
+        command.args(["push", "--quiet"]);
+        if force {
+            command.arg("--force");
+        }
+        command
+            .arg(remote)
+            .args(refspecs)
+            .stdout(Stdio::null())
+            .output_git()?
+            .require_success("push")?;
+        Ok(())
+    }
+
+    /// Compare two commit ranges using `git range-diff`.
+    ///
+    /// Each range is given as a `(base, tip)` pair of commit ids. The maximum creation
+    /// factor is used such that commits are always paired rather than being reported
+    /// as removed and added, regardless of how much they differ.
+    pub(crate) fn range_diff<OptIter, OptArg>(
+        &self,
+        old_range: (gix::ObjectId, gix::ObjectId),
+        new_range: (gix::ObjectId, gix::ObjectId),
+        use_color: bool,
+        diff_opts: OptIter,
+    ) -> Result<BString>
+    where
+        OptIter: IntoIterator<Item = OptArg>,
+        OptArg: AsRef<OsStr>,
+    {
+        let mut command = self.git();
+        command.args(["range-diff", "--creation-factor=100"]);
+        command.arg(if use_color {
+            "--color=always"
+        } else {
+            "--color=never"
+        });
+        command.args(diff_opts);
+        command.arg(format!("{}..{}", old_range.0, old_range.1));
+        command.arg(format!("{}..{}", new_range.0, new_range.1));
+        let output = command.output_git()?.require_success("range-diff")?;
+        Ok(BString::from(output.stdout))
+    }
+
     /// Read content of a tree into specified index using `git read-tree`.
     pub(crate) fn read_tree(&self, tree_id: gix::ObjectId) -> Result<()> {
         self.git_in_work_root()?
@@ -1346,6 +1655,74 @@ impl StupidContext<'_, '_> {
         }
     }
 
+    /// Run user-provided shell command in the work tree.
+    ///
+    /// The command is run with `sh -c`, inheriting stdin, stdout, and stderr.
+    pub(crate) fn user_exec(&self, user_cmd_str: &str) -> Result<()> {
+        let status = self.user_exec_status(user_cmd_str)?;
+
+        if status.success() {
+            Ok(())
+        } else if let Some(code) = status.code() {
+            Err(anyhow!("`{user_cmd_str}` exited with code {code}"))
+        } else {
+            Err(anyhow!("`{user_cmd_str}` failed"))
+        }
+    }
+
+    /// Run user-provided shell command in the work tree, returning its exit status.
+    ///
+    /// Unlike [`StupidContext::user_exec()`], an unsuccessful exit status is not
+    /// considered an error.
+    pub(crate) fn user_exec_status(&self, user_cmd_str: &str) -> Result<ExitStatus> {
+        // TODO: Git chooses its shell path at compile time based on OS or user override.
+        let mut command = Command::new("sh");
+        if let Some(work_dir) = self.work_dir {
+            command.current_dir(work_dir);
+        }
+        command
+            .arg("-c")
+            .arg(user_cmd_str)
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .with_context(|| format!("could not execute `{user_cmd_str}`"))
+    }
+
+    /// Run user-provided shell command in the work tree as a filter.
+    ///
+    /// The command is run with `sh -c`, with `input` written to its stdin. The
+    /// command's stdout is captured and returned while stderr is inherited.
+    pub(crate) fn user_filter(&self, user_cmd_str: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let mut command = Command::new("sh");
+        if let Some(work_dir) = self.work_dir {
+            command.current_dir(work_dir);
+        }
+        let mut child = command
+            .arg("-c")
+            .arg(user_cmd_str)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("could not execute `{user_cmd_str}`"))?;
+        let mut stdin = child.stdin.take().unwrap();
+        let output = std::thread::scope(|scope| -> Result<std::process::Output> {
+            // The command may exit without reading all of its input.
+            scope.spawn(move || stdin.write_all(input).ok());
+            Ok(child.wait_with_output()?)
+        })?;
+
+        if output.status.success() {
+            Ok(output.stdout)
+        } else if let Some(code) = output.status.code() {
+            Err(anyhow!("`{user_cmd_str}` exited with code {code}"))
+        } else {
+            Err(anyhow!("`{user_cmd_str}` failed"))
+        }
+    }
+
     /// Get git version with `git version`.
     pub(crate) fn version(&self) -> Result<String> {
         let output = self
@@ -1361,6 +1738,43 @@ impl StupidContext<'_, '_> {
         Ok(version_line)
     }
 
+    /// Add a linked worktree at `path` with `branch_name` checked-out.
+    pub(crate) fn worktree_add(&self, path: &Path, branch_name: &str) -> Result<()> {
+        self.git()
+            .args(["worktree", "add", "--quiet"])
+            .arg(path)
+            .arg(branch_name)
+            .stdout(Stdio::null())
+            .output_git()?
+            .require_success("worktree add")?;
+        Ok(())
+    }
+
+    /// Get the path and checked-out branch of each worktree using `git worktree list`.
+    ///
+    /// The branch is the full reference name, or `None` for bare and detached worktrees.
+    pub(crate) fn worktree_list(&self) -> Result<Vec<(PathBuf, Option<BString>)>> {
+        let output = self
+            .git()
+            .args(["worktree", "list", "--porcelain"])
+            .output_git()?
+            .require_success("worktree list")?;
+        let mut worktrees = Vec::new();
+        for line in output.stdout.lines() {
+            if let Some(path) = line.strip_prefix(b"worktree ") {
+                let path = path
+                    .to_path()
+                    .map_err(|_| anyhow!("could not convert worktree path"))?;
+                worktrees.push((path.to_owned(), None));
+            } else if let Some(refname) = line.strip_prefix(b"branch ") {
+                if let Some((_, branch)) = worktrees.last_mut() {
+                    *branch = Some(BString::from(refname));
+                }
+            }
+        }
+        Ok(worktrees)
+    }
+
     /// Write tree object from content of specified index using `git write-tree`.
     pub(crate) fn write_tree(&self) -> Result<gix::ObjectId> {
         let output = self
@@ -2,9 +2,10 @@
 
 //! Support for parsing diff output from `git`.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use bstr::ByteSlice;
+use anyhow::{anyhow, Result};
+use bstr::{BStr, ByteSlice};
 
 /// Diff output containing only names of differing files.
 ///
@@ -52,6 +53,155 @@ impl<'a> Iterator for DiffFilesIter<'a> {
     }
 }
 
+/// A file's section of a diff.
+pub(crate) struct FileDiff<'a> {
+    /// The diff's header lines, up to but excluding the first hunk.
+    pub(crate) header: &'a [u8],
+
+    /// Path of the file prior to the change; `None` for new files.
+    pub(crate) old_path: Option<PathBuf>,
+
+    /// Path of the file after the change; `None` for deleted files.
+    pub(crate) new_path: Option<PathBuf>,
+
+    /// The file's hunks; empty for changes without textual hunks, e.g. binary changes.
+    pub(crate) hunks: Vec<Hunk<'a>>,
+}
+
+/// A single hunk of a diff.
+pub(crate) struct Hunk<'a> {
+    /// The hunk's `@@ ... @@` line, without trailing newline.
+    pub(crate) header: &'a BStr,
+
+    /// The hunk's lines following the header.
+    pub(crate) body: &'a [u8],
+
+    /// First line in the original file covered by the hunk.
+    ///
+    /// For hunks with no lines from the original file, this is the line after which
+    /// the hunk's lines are added, which may be zero.
+    pub(crate) old_start: usize,
+
+    /// Number of lines in the original file covered by the hunk.
+    pub(crate) old_count: usize,
+
+    /// Number of lines in the new file covered by the hunk.
+    pub(crate) new_count: usize,
+}
+
+impl<'a> FileDiff<'a> {
+    /// Get the paths from the diff's `diff --git` line, e.g. `a/foo b/foo`.
+    ///
+    /// Unlike [`FileDiff::old_path`] and [`FileDiff::new_path`], these are available
+    /// for every file, including binary files, but may be quoted.
+    pub(crate) fn git_paths(&self) -> &'a BStr {
+        self.header
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix(b"diff --git "))
+            .unwrap_or_default()
+            .as_bstr()
+    }
+
+    /// Get the path of the file for display purposes.
+    pub(crate) fn display_path(&self) -> std::path::Display<'_> {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_else(|| Path::new(""))
+            .display()
+    }
+
+    /// Describe a change with no hunks, e.g. a binary or mode change.
+    pub(crate) fn description(&self) -> &'static BStr {
+        let header = self.header.as_bstr();
+        if header.contains_str("\nGIT binary patch") || header.contains_str("\nBinary files ") {
+            "binary change".into()
+        } else if self.old_path.is_none() {
+            "new file".into()
+        } else if self.new_path.is_none() {
+            "deleted file".into()
+        } else {
+            "mode change".into()
+        }
+    }
+}
+
+/// Split a diff into per-file sections and hunks.
+///
+/// The diff is expected to be in the format produced by `git diff-tree -p`.
+pub(crate) fn parse_diff(diff: &BStr) -> Result<Vec<FileDiff<'_>>> {
+    let mut file_diffs: Vec<FileDiff> = Vec::new();
+    let mut offset = 0;
+    let mut header_start = 0;
+    let mut body_start = 0;
+
+    for line in diff.lines_with_terminator() {
+        let start = offset;
+        offset += line.len();
+
+        if line.starts_with(b"diff --git ") {
+            header_start = start;
+            file_diffs.push(FileDiff {
+                header: &diff[start..offset],
+                old_path: None,
+                new_path: None,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+
+        let file_diff = file_diffs
+            .last_mut()
+            .ok_or_else(|| anyhow!("unexpected diff line `{}`", line.trim_end().as_bstr()))?;
+
+        if line.starts_with(b"@@ ") {
+            let header = line.trim_end().as_bstr();
+            let (old_start, old_count, new_count) = parse_hunk_header(header)?;
+            body_start = offset;
+            file_diff.hunks.push(Hunk {
+                header,
+                body: &diff[offset..offset],
+                old_start,
+                old_count,
+                new_count,
+            });
+        } else if let Some(hunk) = file_diff.hunks.last_mut() {
+            hunk.body = &diff[body_start..offset];
+        } else {
+            file_diff.header = &diff[header_start..offset];
+            let line = line.trim_end_with(|c| c == '\n' || c == '\r');
+            if let Some(path) = line.strip_prefix(b"--- a/") {
+                file_diff.old_path = Some(path.to_path()?.to_path_buf());
+            } else if let Some(path) = line.strip_prefix(b"+++ b/") {
+                file_diff.new_path = Some(path.to_path()?.to_path_buf());
+            }
+        }
+    }
+
+    Ok(file_diffs)
+}
+
+/// Get the original start line, original line count, and new line count from a hunk
+/// header.
+fn parse_hunk_header(header: &BStr) -> Result<(usize, usize, usize)> {
+    let bad_header = || anyhow!("invalid hunk header `{header}`");
+    let parse_range = |range: Option<&[u8]>, prefix: &[u8]| -> Result<(usize, usize)> {
+        let range = range
+            .and_then(|range| range.strip_prefix(prefix))
+            .and_then(|range| range.to_str().ok())
+            .ok_or_else(bad_header)?;
+        let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+        let start = start.parse::<usize>().map_err(|_| bad_header())?;
+        let count = count.parse::<usize>().map_err(|_| bad_header())?;
+        Ok((start, count))
+    };
+    let mut fields = header.split_str(" ").skip(1);
+    let (old_start, old_count) = parse_range(fields.next(), b"-")?;
+    let (_, new_count) = parse_range(fields.next(), b"+")?;
+    Ok((old_start, old_count, new_count))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,4 +215,57 @@ mod tests {
         assert_eq!(it.next(), Some(Path::new("jkl")));
         assert!(it.next().is_none());
     }
+
+    #[test]
+    fn hunk_header() {
+        assert_eq!(parse_hunk_header("@@ -3 +3 @@".into()).unwrap(), (3, 1, 1));
+        assert_eq!(
+            parse_hunk_header("@@ -5,0 +6,2 @@".into()).unwrap(),
+            (5, 0, 2)
+        );
+        assert_eq!(
+            parse_hunk_header("@@ -10,3 +10 @@ fn main() {".into()).unwrap(),
+            (10, 3, 1)
+        );
+        assert!(parse_hunk_header("@@ +1 @@".into()).is_err());
+    }
+
+    #[test]
+    fn split_diff() {
+        let diff = BStr::new(
+            "diff --git a/a.txt b/a.txt\n\
+             index 1111111..2222222 100644\n\
+             --- a/a.txt\n\
+             +++ b/a.txt\n\
+             @@ -1 +1 @@\n\
+             -one\n\
+             +ONE\n\
+             @@ -3,0 +4 @@\n\
+             +four\n\
+             diff --git a/b.txt b/b.txt\n\
+             new file mode 100644\n\
+             index 0000000..3333333\n\
+             --- /dev/null\n\
+             +++ b/b.txt\n\
+             @@ -0,0 +1 @@\n\
+             +b\n",
+        );
+        let file_diffs = parse_diff(diff).unwrap();
+        assert_eq!(file_diffs.len(), 2);
+
+        let a = &file_diffs[0];
+        assert_eq!(a.old_path.as_deref(), Some(Path::new("a.txt")));
+        assert!(a.header.ends_with(b"+++ b/a.txt\n"));
+        assert_eq!(a.hunks.len(), 2);
+        assert_eq!(a.hunks[0].body, b"-one\n+ONE\n");
+        assert_eq!(a.hunks[1].header, "@@ -3,0 +4 @@");
+        assert_eq!((a.hunks[1].old_start, a.hunks[1].old_count), (3, 0));
+
+        assert_eq!(a.git_paths(), "a/a.txt b/a.txt");
+
+        let b = &file_diffs[1];
+        assert!(b.old_path.is_none());
+        assert_eq!(b.new_path.as_deref(), Some(Path::new("b.txt")));
+        assert_eq!(b.hunks.len(), 1);
+    }
 }
@@ -117,6 +117,16 @@ pub(crate) fn specialize_template(
                         special.push_char(c);
                     }
                     state = State::Start;
+                } else {
+                    special.push_str("%(");
+                    special.push_str(name.as_str());
+                    special.push_char(')');
+                    if c == '%' {
+                        state = State::Percent;
+                    } else {
+                        special.push_char(c);
+                        state = State::Start;
+                    }
                 }
             }
         }
@@ -160,6 +160,93 @@ test_expect_success 'Test effects' '
     test_line_count = 3 series.txt
 '
 
+test_expect_success 'Test format' '
+    stg series --format "%(index)s %(offset)s %(status)s %(name)s %(author.name)s %(subject)s" >series.txt &&
+    cat >expected.txt <<-\EOF &&
+	0 -2 applied p0 A Ú Thor message 0
+	1 -1 applied p1 B Author message 1
+	2 +0 applied p2 A Ú Thor message 2
+	3 +1 unapplied p3 A Ú Thor message 3
+	EOF
+    test_cmp expected.txt series.txt
+'
+
+test_expect_success 'Test format reverse' '
+    stg series --reverse --applied --format "%(prefix)s%(branch)s:%(name)s <%(author.email)s>" >series.txt &&
+    cat >expected.txt <<-\EOF &&
+	>master:p2 <author@example.com>
+	+master:p1 <author@example.com>
+	+master:p0 <author@example.com>
+	EOF
+    test_cmp expected.txt series.txt
+'
+
+test_expect_success 'Test format commit ids' '
+    stg series --format "%(commit)s %(shortcommit)s" p1 >series.txt &&
+    echo "$(stg id p1) $(git rev-parse --short $(stg id p1))" >expected.txt &&
+    test_cmp expected.txt series.txt
+'
+
+test_expect_success 'Test format diffstat' '
+    stg series --format "%(name)s %(files)s +%(insertions)s -%(deletions)s" >series.txt &&
+    cat >expected.txt <<-\EOF &&
+	p0 1 +1 -0
+	p1 1 +1 -0
+	p2 1 +1 -0
+	p3 0 +0 -0
+	EOF
+    test_cmp expected.txt series.txt
+'
+
+test_expect_success 'Test format unknown placeholder' '
+    stg series --format "%(name)s %(bogus)s" p0 >series.txt &&
+    echo "p0 %(bogus)s" >expected.txt &&
+    test_cmp expected.txt series.txt
+'
+
+test_expect_success 'Test format placeholders without trailing s' '
+    stg series --format "%(index) %(name) <%(author.email)> %(files)s %(subject)" p1 >series.txt &&
+    echo "1 p1 <author@example.com> 1 message 1" >expected.txt &&
+    test_cmp expected.txt series.txt &&
+    stg series --format "%(name):%(bogus) %(name)" p0 >series.txt &&
+    echo "p0:%(bogus) p0" >expected.txt &&
+    test_cmp expected.txt series.txt
+'
+
+test_expect_success 'Test format conflicts' '
+    general_error stg series --format "%(name)s" --description &&
+    general_error stg series --format "%(name)s" --count &&
+    general_error stg series --format "%(name)s" --json
+'
+
+test_expect_success 'Test format from config' '
+    test_config stgit.series.format "%(name)s: %(subject)s" &&
+    stg series >series.txt &&
+    cat >expected.txt <<-\EOF &&
+	p0: message 0
+	p1: message 1
+	p2: message 2
+	p3: message 3
+	EOF
+    test_cmp expected.txt series.txt &&
+    stg series --format "%(name)s" p3 >series.txt &&
+    echo "p3" >expected.txt &&
+    test_cmp expected.txt series.txt &&
+    stg series --no-format --applied >series.txt &&
+    cat >expected.txt <<-\EOF &&
+	+ p0
+	+ p1
+	> p2
+	EOF
+    test_cmp expected.txt series.txt &&
+    stg series --description p3 >series.txt &&
+    echo "- p3 # message 3" >expected.txt &&
+    test_cmp expected.txt series.txt &&
+    stg series --count >series.txt &&
+    echo "4" >expected.txt &&
+    test_cmp expected.txt series.txt
+'
+
 test_expect_success 'Test missing' '
     stg branch --clone -- other &&
     test "$(stg branch)" = "other" &&
@@ -0,0 +1,124 @@
+#!/bin/sh
+
+test_description='Test patch dependency tracking'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt deps with uninitialized stack' '
+    command_error stg deps 2>err &&
+    grep "error: no patches applied" err
+'
+
+test_expect_success 'Initialize stack with dependent patches' '
+    test_commit_bulk --start=1 --filename=f%s.txt 3 &&
+    printf "1\n2\n3\n" >a.txt &&
+    stg add a.txt &&
+    git commit -m a0 &&
+    stg new -m p1 &&
+    printf "one\n2\n3\n" >a.txt &&
+    stg refresh &&
+    stg new -m p2 &&
+    echo b >b.txt &&
+    stg add b.txt &&
+    stg refresh &&
+    stg new -m p3 &&
+    printf "one\ntwo\n3\n" >a.txt &&
+    stg refresh &&
+    stg new -m p4 &&
+    printf "one\ntwo\nthree\n" >a.txt &&
+    stg refresh
+'
+
+test_expect_success 'Show dependencies of top patch' '
+    stg deps >out &&
+    echo p3 >expected &&
+    test_cmp expected out
+'
+
+test_expect_success 'Show transitive dependencies' '
+    stg deps --transitive p4 >out &&
+    printf "p1\np3\n" >expected &&
+    test_cmp expected out
+'
+
+test_expect_success 'Show dependents' '
+    stg deps --reverse p1 >out &&
+    echo p3 >expected &&
+    test_cmp expected out &&
+    stg deps -r -t p1 >out &&
+    printf "p3\np4\n" >expected &&
+    test_cmp expected out &&
+    stg deps -r p2 >out &&
+    test_must_be_empty out
+'
+
+test_expect_success 'Float refuses to move patch past its dependency' '
+    command_error stg float p1 2>err &&
+    grep "patch \`p3\` depends on \`p1\`" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "p1 p2 p3 p4"
+'
+
+test_expect_success 'Float independent patch' '
+    stg float p2 &&
+    test "$(echo $(stg series --applied --noprefix))" = "p1 p3 p4 p2"
+'
+
+test_expect_success 'Sink refuses to move patch below its dependency' '
+    command_error stg sink p3 2>err &&
+    grep "patch \`p3\` depends on \`p1\`" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "p1 p3 p4 p2"
+'
+
+test_expect_success 'Sink with dependencies' '
+    stg sink --to p3 p2 &&
+    test "$(echo $(stg series --applied --noprefix))" = "p1 p2 p3 p4" &&
+    stg sink --with-deps p4 &&
+    test "$(echo $(stg series --applied --noprefix))" = "p1 p3 p4 p2"
+'
+
+test_expect_success 'Sink with dependencies below a dependency' '
+    command_error stg sink --to p1 --with-deps p4 2>err &&
+    grep "cannot sink below target patch \`p1\`" err
+'
+
+test_expect_success 'Pop refuses to pop patch needed by applied patches' '
+    command_error stg pop p1 2>err &&
+    grep "patch \`p3\` depends on \`p1\`" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "p1 p3 p4 p2"
+'
+
+test_expect_success 'Pop with dependencies' '
+    stg pop --with-deps p3 &&
+    test "$(echo $(stg series --applied --noprefix))" = "p1 p2" &&
+    test "$(echo $(stg series --unapplied --noprefix))" = "p3 p4"
+'
+
+test_expect_success 'Float with dependencies' '
+    stg push -a &&
+    stg float --with-deps p1 &&
+    test "$(echo $(stg series --applied --noprefix))" = "p2 p1 p3 p4"
+'
+
+test_expect_success 'Delete refuses to delete patch needed by applied patches' '
+    command_error stg delete p3 2>err &&
+    grep "patch \`p4\` depends on \`p3\`" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "p2 p1 p3 p4"
+'
+
+test_expect_success 'Ignore dependencies' '
+    test_expect_code 3 stg pop --ignore-deps p1 &&
+    stg undo --hard &&
+    test "$(echo $(stg series --applied --noprefix))" = "p2 p1 p3 p4"
+'
+
+test_expect_success 'Conflicting dependency options' '
+    general_error stg float --with-deps --ignore-deps p1 2>err &&
+    grep -e "cannot be used with" err
+'
+
+test_expect_success 'Delete with dependencies' '
+    stg delete --with-deps p3 &&
+    test "$(echo $(stg series --noprefix))" = "p2 p1"
+'
+
+test_done
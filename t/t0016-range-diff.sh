@@ -0,0 +1,97 @@
+#!/bin/sh
+
+test_description='Test patch versions and "stg range-diff"'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize stack with several patch versions' '
+    stg init &&
+    stg new -m "p0 subject" p0 &&
+    echo a >a.txt &&
+    stg add a.txt &&
+    stg refresh &&
+    stg new -m p1 &&
+    echo b >b.txt &&
+    stg add b.txt &&
+    stg refresh &&
+    echo b2 >b.txt &&
+    stg refresh &&
+    stg edit -m "p1 reworded" &&
+    echo b3 >b.txt &&
+    stg refresh
+'
+
+test_expect_success 'List patch versions' '
+    stg log --versions p1 >out &&
+    test_line_count = 5 out &&
+    head -n 1 out | grep -e "^p1  v1  [0-9a-f]*  p1$" &&
+    tail -n 1 out | grep -e "^p1  v5  [0-9a-f]*  p1 reworded$" &&
+    stg log --versions >out &&
+    test_line_count = 7 out &&
+    grep -e "^p0  v2  [0-9a-f]*  p0 subject$" out
+'
+
+test_expect_success 'Patch versions do not repeat after undo' '
+    stg edit -m "p1 temporary" &&
+    stg undo &&
+    stg log --versions p1 >out &&
+    test_line_count = 6 out &&
+    tail -n 1 out | grep -e "^p1  v6  [0-9a-f]*  p1 temporary$"
+'
+
+test_expect_success 'Range-diff of latest versions' '
+    stg range-diff p1 >out &&
+    head -n 1 out >header &&
+    echo "p1: v5..v6" >expected &&
+    test_cmp expected header &&
+    grep -e "^1: *[0-9a-f]* ! 1: *[0-9a-f]* p1 reworded" out &&
+    grep -e "-    p1 reworded" out &&
+    grep -e "+    p1 temporary" out
+'
+
+test_expect_success 'Range-diff defaults to top patch' '
+    stg range-diff >out &&
+    head -n 1 out | grep -e "^p1: v5..v6$"
+'
+
+test_expect_success 'Range-diff of specific versions' '
+    stg range-diff --v1 2 --v2 4 p1 >out &&
+    head -n 1 out | grep -e "^p1: v2..v4$" &&
+    grep -e "-+b$" out &&
+    grep -e "++b2" out &&
+    grep -e "p1 reworded" out
+'
+
+test_expect_success 'Range-diff with invalid versions' '
+    command_error stg range-diff --v2 7 p1 2>err &&
+    grep "patch \`p1\` does not have version 7" err &&
+    command_error stg range-diff --v1 0 p1 2>err &&
+    grep "patch \`p1\` does not have version 0" err
+'
+
+test_expect_success 'Range-diff with single version' '
+    stg new -m p2 &&
+    command_error stg range-diff p2 2>err &&
+    grep "patch \`p2\` has only one version" err
+'
+
+test_expect_success 'Range-diff of series between stack states' '
+    stg delete p0 &&
+    stg range-diff --from refs/stacks/master~8 >out &&
+    grep -e "^p1:$" out &&
+    grep -e "^p2: added$" out &&
+    grep -e "^p0: removed$" out
+'
+
+test_expect_success 'Range-diff of selected patches between stack states' '
+    stg range-diff --from refs/stacks/master~8 --to refs/stacks/master~1 p1 >out &&
+    head -n 1 out | grep -e "^p1:$" &&
+    ! grep -e "^p2" out
+'
+
+test_expect_success 'Range-diff with invalid stack state' '
+    command_error stg range-diff --from HEAD 2>err &&
+    grep "is not a stack state" err
+'
+
+test_done
@@ -0,0 +1,96 @@
+#!/bin/sh
+
+test_description='Test "stg bisect"'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt bisect with no applied patches' '
+    stg init &&
+    command_error stg bisect --test true 2>err &&
+    grep "error: no patches applied" err
+'
+
+test_expect_success 'Test command is required' '
+    general_error stg bisect 2>err &&
+    grep -e "--test <command>" err
+'
+
+test_expect_success 'Initialize stack' '
+    cat >>.git/info/exclude <<-\EOF &&
+	expected*.txt
+	out*.txt
+	err
+	EOF
+    echo 0 >value.txt &&
+    git add value.txt &&
+    git commit -m base &&
+    for i in 1 2 3 4 5 6 7
+    do
+        stg new -m "p$i" "p$i" &&
+        echo "$i" >value.txt &&
+        stg refresh || return 1
+    done &&
+    stg pop p6 p7 &&
+    stg hide p7
+'
+
+test_expect_success 'Find first failing patch' '
+    stg bisect --test "test \$(cat value.txt) -lt 3" >out.txt &&
+    grep "^First failing patch: p3$" out.txt &&
+    stg series --all >out.txt &&
+    cat >expected.txt <<-\EOF &&
+	+ p1
+	+ p2
+	+ p3
+	+ p4
+	> p5
+	- p6
+	! p7
+	EOF
+    test_cmp expected.txt out.txt
+'
+
+test_expect_success 'Find first patch when it is the first applied patch' '
+    stg bisect --test "test \$(cat value.txt) -lt 1" >out.txt &&
+    grep "^First failing patch: p1$" out.txt &&
+    test "$(stg top)" = "p5"
+'
+
+test_expect_success 'Find first patch when it is the top patch' '
+    stg bisect --test "test \$(cat value.txt) -lt 5" >out.txt &&
+    grep "^First failing patch: p5$" out.txt &&
+    test "$(stg top)" = "p5"
+'
+
+test_expect_success 'Bisect leaves stack state unchanged' '
+    stg log -n 1 >out.txt &&
+    grep "bisect" out.txt &&
+    test "$(stg id)" = "$(stg id p5)" &&
+    test -z "$(git status --porcelain)"
+'
+
+test_expect_success 'Bisect fails when test passes at top' '
+    command_error stg bisect --test true 2>err &&
+    grep "test command passes with all patches applied" err
+'
+
+test_expect_success 'Bisect fails when test fails without patches' '
+    command_error stg bisect --test false 2>err &&
+    grep "test command also fails with no patches applied" err &&
+    test "$(stg top)" = "p5"
+'
+
+test_expect_success 'Bisect requires clean worktree' '
+    echo dirty >value.txt &&
+    command_error stg bisect --test false 2>err &&
+    grep "worktree not clean" err &&
+    git checkout value.txt
+'
+
+test_expect_success 'Bash completion of bisect test command' '
+    stg completion bash >stgit.bash &&
+    sed -n "/^_stg-bisect ()/,/^}/p" stgit.bash >completion.txt &&
+    grep -e "compgen -A command" completion.txt
+'
+
+test_done
@@ -0,0 +1,158 @@
+#!/bin/sh
+
+test_description='Test "stg exec"'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt exec with no applied patches' '
+    stg init &&
+    command_error stg exec -- true 2>err &&
+    grep "error: no patches applied" err
+'
+
+test_expect_success 'Command is required' '
+    general_error stg exec 2>err &&
+    grep -e "<command>" err
+'
+
+test_expect_success 'Initialize stack' '
+    cat >>.git/info/exclude <<-\EOF &&
+	expected*.txt
+	out*.txt
+	err
+	EOF
+    echo 0 >value.txt &&
+    git add value.txt &&
+    git commit -m base &&
+    for i in 1 2 3 4 5
+    do
+        stg new -m "p$i" "p$i" &&
+        echo "$i" >value.txt &&
+        stg refresh || return 1
+    done &&
+    stg pop p4 p5
+'
+
+test_expect_success 'Run command for applied patches' '
+    stg exec -- "cat value.txt >>out-log.txt" >out.txt &&
+    grep -E "  (pass|FAIL|skipped)$" out.txt >out-table.txt &&
+    cat >expected.txt <<-\EOF &&
+	p1  pass
+	p2  pass
+	p3  pass
+	EOF
+    test_cmp expected.txt out-table.txt &&
+    test_write_lines 1 2 3 >expected.txt &&
+    test_cmp expected.txt out-log.txt &&
+    rm out-log.txt &&
+    test "$(stg top)" = "p3" &&
+    stg series --unapplied --noprefix >out.txt &&
+    test_write_lines p4 p5 >expected.txt &&
+    test_cmp expected.txt out.txt
+'
+
+test_expect_success 'Run command for range including unapplied patches' '
+    stg exec --range p2..p5 -- test -f value.txt >out.txt &&
+    grep -E "  (pass|FAIL|skipped)$" out.txt >out-table.txt &&
+    cat >expected.txt <<-\EOF &&
+	p2  pass
+	p3  pass
+	p4  pass
+	p5  pass
+	EOF
+    test_cmp expected.txt out-table.txt &&
+    test "$(stg top)" = "p3" &&
+    stg series --unapplied --noprefix >out.txt &&
+    test_write_lines p4 p5 >expected.txt &&
+    test_cmp expected.txt out.txt
+'
+
+test_expect_success 'Run command with arguments containing spaces and quotes' '
+    stg exec -- test "a b" = "a b" >out.txt &&
+    grep -E "  (pass|FAIL|skipped)$" out.txt >out-table.txt &&
+    cat >expected.txt <<-\EOF &&
+	p1  pass
+	p2  pass
+	p3  pass
+	EOF
+    test_cmp expected.txt out-table.txt &&
+    stg exec --range p1 -- grep -q -e "^1\$" -e "it${SQ}s" value.txt >out.txt &&
+    grep -E "^p1  pass$" out.txt &&
+    command_error stg exec --range p1 -- grep -q "1 value.txt" value.txt >out.txt &&
+    grep -E "^p1  FAIL$" out.txt &&
+    stg goto p3
+'
+
+test_expect_success 'Stop at first failure' '
+    command_error stg exec --range p1..p5 -- "test \$(cat value.txt) -ne 2" >out.txt 2>err &&
+    grep -E "  (pass|FAIL|skipped)$" out.txt >out-table.txt &&
+    cat >expected.txt <<-\EOF &&
+	p1  pass
+	p2  FAIL
+	p3  skipped
+	p4  skipped
+	p5  skipped
+	EOF
+    test_cmp expected.txt out-table.txt &&
+    grep "failed for \`p2\`; stopped with \`p2\` as the topmost patch" err &&
+    test "$(stg top)" = "p2" &&
+    stg goto p3
+'
+
+test_expect_success 'Keep going after failures' '
+    command_error stg exec -k --range p1..p5 -- "test \$(cat value.txt) -ne 2 -a \$(cat value.txt) -ne 4" >out.txt 2>err &&
+    grep -E "  (pass|FAIL|skipped)$" out.txt >out-table.txt &&
+    cat >expected.txt <<-\EOF &&
+	p1  pass
+	p2  FAIL
+	p3  pass
+	p4  FAIL
+	p5  pass
+	EOF
+    test_cmp expected.txt out-table.txt &&
+    grep "failed for 2 of 5 patches" err &&
+    test "$(stg top)" = "p3"
+'
+
+test_expect_success 'JSON output' '
+    command_error stg exec --json -k -- "cat value.txt; test \$(cat value.txt) -ne 2" >out.txt 2>err &&
+    cat >expected.txt <<-\EOF &&
+	{
+	  "command": "cat value.txt; test $(cat value.txt) -ne 2",
+	  "results": [
+	    {
+	      "patch": "p1",
+	      "status": "pass",
+	      "exit_code": 0
+	    },
+	    {
+	      "patch": "p2",
+	      "status": "fail",
+	      "exit_code": 1
+	    },
+	    {
+	      "patch": "p3",
+	      "status": "pass",
+	      "exit_code": 0
+	    }
+	  ]
+	}
+	EOF
+    test_cmp expected.txt out.txt &&
+    grep "^2$" err
+'
+
+test_expect_success 'Exec requires clean worktree' '
+    echo dirty >value.txt &&
+    command_error stg exec -- true 2>err &&
+    grep "worktree not clean" err &&
+    git checkout value.txt
+'
+
+test_expect_success 'Bash completion of exec command' '
+    stg completion bash >stgit.bash &&
+    sed -n "/^_stg-exec ()/,/^}/p" stgit.bash >completion.txt &&
+    grep -e "compgen -A command" completion.txt
+'
+
+test_done
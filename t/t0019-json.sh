@@ -0,0 +1,175 @@
+#!/bin/sh
+
+test_description='Test JSON output of inspection commands'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize stack' '
+    cat >>.git/info/exclude <<-\EOF &&
+	expected*.txt
+	out*.txt
+	err
+	EOF
+    test_commit_bulk --message="p%s" 4 &&
+    stg uncommit -n 4 &&
+    stg new -m "empty patch" e &&
+    stg pop p4 e &&
+    stg hide e
+'
+
+test_expect_success 'Series as JSON' '
+    stg series --json --all >out.txt &&
+    grep -c "^  {" out.txt >out-count.txt &&
+    echo 5 >expected.txt &&
+    test_cmp expected.txt out-count.txt &&
+    grep -A 16 "\"name\": \"p3\"" out.txt >out-p3.txt &&
+    cat >expected.txt <<-EOF &&
+	    "name": "p3",
+	    "branch": "master",
+	    "status": "applied",
+	    "top": true,
+	    "commit": "$(stg id p3)",
+	    "author": {
+	      "name": "A Ú Thor",
+	      "email": "author@example.com",
+	      "date": "2005-04-07T15:15:13-07:00"
+	    },
+	    "subject": "p3",
+	    "empty": false,
+	    "index": 2,
+	    "offset": 0
+	  },
+	  {
+	    "name": "p4",
+	EOF
+    test_cmp expected.txt out-p3.txt &&
+    grep -A 2 "\"name\": \"p4\"" out.txt | grep "\"status\": \"unapplied\"" &&
+    grep -A 2 "\"name\": \"e\"" out.txt | grep "\"status\": \"hidden\"" &&
+    grep -A 12 "\"name\": \"e\"" out.txt | grep "\"empty\": true" &&
+    grep -A 13 "\"name\": \"p1\"" out.txt | grep "\"offset\": -2"
+'
+
+test_expect_success 'Series as JSON honors selection and reverse' '
+    stg series --json --applied --reverse >out.txt &&
+    grep "^    \"name\":" out.txt >out-names.txt &&
+    cat >expected.txt <<-\EOF &&
+	    "name": "p3",
+	    "name": "p2",
+	    "name": "p1",
+	EOF
+    test_cmp expected.txt out-names.txt &&
+    stg series --json --hidden >out.txt &&
+    grep "\"name\": \"e\"" out.txt &&
+    general_error stg series --json --count
+'
+
+test_expect_success 'Empty series as JSON' '
+    stg series --json -m master >out.txt &&
+    echo "[]" >expected.txt &&
+    test_cmp expected.txt out.txt
+'
+
+test_expect_success 'Top as JSON' '
+    stg top --json >out.txt &&
+    head -n 3 out.txt >out-head.txt &&
+    cat >expected.txt <<-\EOF &&
+	{
+	  "name": "p3",
+	  "branch": "master",
+	EOF
+    test_cmp expected.txt out-head.txt
+'
+
+test_expect_success 'Patches as JSON' '
+    stg patches --json 2.t >out.txt &&
+    grep "^    \"name\":" out.txt >out-names.txt &&
+    echo "    \"name\": \"p2\"," >expected.txt &&
+    test_cmp expected.txt out-names.txt &&
+    general_error stg patches --json --diff 2.t
+'
+
+test_expect_success 'Files as JSON' '
+    stg files --json p2 >out.txt &&
+    cat >expected.txt <<-\EOF &&
+	[
+	  {
+	    "status": "A",
+	    "path": "2.t"
+	  }
+	]
+	EOF
+    test_cmp expected.txt out.txt &&
+    general_error stg files --json --stat p2
+'
+
+test_expect_success 'Id as JSON' '
+    stg id --json p1 >out.txt &&
+    cat >expected.txt <<-EOF &&
+	{
+	  "id": "$(stg id p1)",
+	  "type": "commit"
+	}
+	EOF
+    test_cmp expected.txt out.txt &&
+    stg id --json "p1^{tree}" >out.txt &&
+    grep "\"type\": \"tree\"" out.txt
+'
+
+test_expect_success 'Log as JSON' '
+    stg log --json -n 2 >out.txt &&
+    grep "\"summary\":" out.txt >out-summaries.txt &&
+    cat >expected.txt <<-\EOF &&
+	    "summary": "hide",
+	    "summary": "pop",
+	EOF
+    test_cmp expected.txt out-summaries.txt &&
+    head -n 3 out.txt >out-head.txt &&
+    cat >expected.txt <<-EOF &&
+	[
+	  {
+	    "commit": "$(git rev-parse refs/stacks/master)",
+	EOF
+    test_cmp expected.txt out-head.txt &&
+    general_error stg log --json --diff
+'
+
+test_expect_success 'Log as JSON for patches' '
+    stg log --json p4 >out.txt &&
+    grep "\"summary\":" out.txt >out-summaries.txt &&
+    cat >expected.txt <<-\EOF &&
+	    "summary": "uncommit",
+	EOF
+    test_cmp expected.txt out-summaries.txt &&
+    stg log --json e >out.txt &&
+    grep "\"summary\":" out.txt >out-summaries.txt &&
+    echo "    \"summary\": \"new: e\"," >expected.txt &&
+    test_cmp expected.txt out-summaries.txt
+'
+
+test_expect_success 'Branch list as JSON' '
+    git branch other &&
+    stg branch --protect &&
+    stg branch --describe "the master branch" &&
+    stg branch --list --json >out.txt &&
+    cat >expected.txt <<-\EOF &&
+	[
+	  {
+	    "name": "master",
+	    "current": true,
+	    "stack": true,
+	    "protected": true,
+	    "description": "the master branch"
+	  },
+	  {
+	    "name": "other",
+	    "current": false,
+	    "stack": false,
+	    "protected": false,
+	    "description": ""
+	  }
+	]
+	EOF
+    test_cmp expected.txt out.txt
+'
+
+test_done
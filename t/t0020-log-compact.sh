@@ -0,0 +1,90 @@
+#!/bin/sh
+
+test_description='Test compacting the stack log'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize stack with history' '
+    test_commit_bulk 1 &&
+    stg init &&
+    for i in 1 2 3 4
+    do
+        test_tick &&
+        echo "p$i" >>foo.txt &&
+        git add foo.txt &&
+        stg new --refresh -m "p$i" || return 1
+    done &&
+    stg log >log.txt &&
+    test_line_count = 5 log.txt
+'
+
+test_expect_success 'Compact requires a limit' '
+    general_error stg log --compact 2>err &&
+    grep -e "the following required arguments were not provided" err &&
+    general_error stg log --keep 2 2>err &&
+    grep -e "the following required arguments were not provided" err &&
+    general_error stg log --compact --keep 2 --older-than now 2>err &&
+    grep -e "the argument .--keep <n>. cannot be used with .--older-than <date>." err &&
+    general_error stg log --compact --keep 0 2>err &&
+    grep -e "at least one stack history entry must be kept" err
+'
+
+test_expect_success 'Compact with keep' '
+    stg log >log-before.txt &&
+    stg log --compact --keep 3 2>err &&
+    grep -e "Dropped 2 stack history entries" err &&
+    stg log >log.txt &&
+    test_line_count = 3 log.txt &&
+    head -n 3 log-before.txt | cut -d" " -f2- >expected &&
+    cut -d" " -f2- log.txt >actual &&
+    test_cmp expected actual &&
+    test "$(echo $(stg series --noprefix))" = "p1 p2 p3 p4"
+'
+
+test_expect_success 'Nothing to compact' '
+    stg log --compact --keep 3 2>err &&
+    grep -e "No stack history entries to drop" err &&
+    stg log >log.txt &&
+    test_line_count = 3 log.txt
+'
+
+test_expect_success 'Undo and redo within retained history' '
+    stg undo -n 2 &&
+    test "$(echo $(stg series --noprefix))" = "p1 p2" &&
+    test "$(tail -n 1 foo.txt)" = "p2" &&
+    stg redo &&
+    test "$(echo $(stg series --noprefix))" = "p1 p2 p3 p4" &&
+    test "$(tail -n 1 foo.txt)" = "p4"
+'
+
+test_expect_success 'Compact with older-than' '
+    stg log >log-before.txt &&
+    test_line_count = 5 log-before.txt &&
+    stg log --compact --older-than "$(($test_tick - 1)) -0700" &&
+    stg log >log.txt &&
+    test_line_count = 3 log.txt &&
+    test "$(echo $(stg series --noprefix))" = "p1 p2 p3 p4"
+'
+
+test_expect_success 'Compact with older-than in the future keeps current state' '
+    stg log --compact --older-than "$(($test_tick + 1000)) -0700" &&
+    stg log >log.txt &&
+    test_line_count = 1 log.txt &&
+    command_error stg undo 2>err &&
+    grep -e "not enough undo information" err
+'
+
+test_expect_success 'Compact deletes stale patch refs' '
+    stg branch --create other &&
+    stg new -m other-patch &&
+    stg branch master &&
+    git branch -D other &&
+    git update-ref -d refs/stacks/other &&
+    git show-ref --verify refs/patches/other/other-patch &&
+    stg log --compact --keep 1 2>err &&
+    grep -e "Deleted stale patch ref .refs/patches/other/other-patch." err &&
+    test_must_fail git show-ref --verify refs/patches/other/other-patch &&
+    git show-ref --verify refs/patches/master/p1
+'
+
+test_done
@@ -0,0 +1,80 @@
+#!/bin/sh
+
+test_description='Test the interactive stack log browser'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize stack' '
+    test_commit_bulk 1 &&
+    stg init &&
+    stg new -m p0 &&
+    stg new -m p1
+'
+
+test_expect_success 'Interactive log requires a terminal' '
+    command_error stg log --interactive </dev/null >out 2>err &&
+    grep -e "--interactive requires a terminal" err &&
+    test_must_be_empty out
+'
+
+test_expect_success 'Interactive log conflicts with other log modes' '
+    general_error stg log --interactive --diff 2>err &&
+    grep -e "cannot be used with" err &&
+    general_error stg log --interactive --graphical 2>err &&
+    grep -e "cannot be used with" err &&
+    general_error stg log --interactive --clear 2>err &&
+    grep -e "cannot be used with" err &&
+    general_error stg log --interactive --json 2>err &&
+    grep -e "cannot be used with" err &&
+    general_error stg log --interactive p0 2>err &&
+    grep -e "cannot be used with" err
+'
+
+test_expect_success 'Stack is unchanged' '
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1"
+'
+
+test_lazy_prereq SCRIPT '
+    script -qec true /dev/null
+'
+
+# Run the interactive log browser in a pseudo-terminal, feeding it the given keys
+# followed by enough quit keys to leave the browser should no action be taken.
+browse_log () {
+    printf "%sqqq" "$1" | script -qec "stg log --interactive" /dev/null >/dev/null 2>&1
+}
+
+test_expect_success SCRIPT 'Quit without action' '
+    browse_log "jj" &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1"
+'
+
+test_expect_success SCRIPT 'Reset stack from log list' '
+    stg delete p1 &&
+    test "$(echo $(stg series --noprefix --all))" = "p0" &&
+    browse_log "jr" &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1" &&
+    stg log -n1 >log &&
+    grep -e "reset$" log
+'
+
+test_expect_success SCRIPT 'Reset stack from state view' '
+    stg new -m p2 &&
+    stg pop -a &&
+    test "$(echo $(stg series --noprefix --unapplied))" = "p0 p1 p2" &&
+    browse_log "jl" &&
+    test "$(echo $(stg series --noprefix --unapplied))" = "p0 p1 p2" &&
+    browse_log "jlr" &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1 p2" &&
+    test "$(echo $(stg series --noprefix --unapplied))" = ""
+'
+
+test_expect_success SCRIPT 'Restore single patch from state view' '
+    stg delete p1 p2 &&
+    test "$(echo $(stg series --noprefix --all))" = "p0" &&
+    browse_log "jljjp" &&
+    test "$(echo $(stg series --noprefix --all))" = "p0 p2" &&
+    test_must_fail stg id p1
+'
+
+test_done
@@ -0,0 +1,149 @@
+#!/bin/sh
+
+test_description='Test continuing, skipping, and aborting stopped operations'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize the StGit repository' '
+    echo base >f &&
+    git add f &&
+    git commit -m base &&
+    for name in p1 q1 p2 q2 p3 q3 p4; do
+        stg new $name -m $name &&
+        case $name in
+        p*) echo $name >f && stg add f ;;
+        q*) echo $name >$name.txt && stg add $name.txt ;;
+        esac &&
+        stg refresh &&
+        stg pop || return 1
+    done &&
+    stg new x -m x &&
+    echo x >f &&
+    stg refresh
+'
+
+test_expect_success 'Continue without an operation in progress' '
+    command_error stg push --continue 2>err &&
+    grep -e "no \`push\` operation in progress" err
+'
+
+test_expect_success 'Push stops on conflict' '
+    conflict stg push p1 q1 2>err &&
+    grep -e "use \`stg push --continue\` to resume" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "x p1"
+'
+
+test_expect_success 'Continue with unresolved conflicts' '
+    command_error stg push --continue 2>err &&
+    grep -e "resolve outstanding conflicts first" err
+'
+
+test_expect_success 'Continue with wrong command' '
+    command_error stg rebase --continue 2>err &&
+    grep -e "a \`push\` operation is in progress; use \`stg push --continue\`" err
+'
+
+test_expect_success 'Continue after resolving conflicts' '
+    echo resolved >f &&
+    stg add f &&
+    stg push --continue &&
+    test "$(echo $(stg series --applied --noprefix))" = "x p1 q1" &&
+    stg show p1 | grep -e "^+resolved" &&
+    git diff-index --quiet HEAD &&
+    command_error stg push --continue
+'
+
+test_expect_success 'Skip the conflicting patch' '
+    conflict stg push p2 q2 &&
+    stg push --skip &&
+    test "$(echo $(stg series --applied --noprefix))" = "x p1 q1 q2" &&
+    test "$(stg series --unapplied --noprefix | head -n 1)" = "p2" &&
+    stg show p2 | grep -e "^+p2" &&
+    test "$(cat f)" = "resolved" &&
+    git diff-index --quiet HEAD
+'
+
+test_expect_success 'Abort the push' '
+    conflict stg push p3 q3 &&
+    stg push --abort &&
+    test "$(echo $(stg series --applied --noprefix))" = "x p1 q1 q2" &&
+    stg show p3 | grep -e "^+p3" &&
+    test "$(cat f)" = "resolved" &&
+    git diff-index --quiet HEAD &&
+    command_error stg push --abort
+'
+
+test_expect_success 'New push discards a stopped push' '
+    conflict stg push p3 q3 &&
+    stg reset --hard &&
+    stg undo --hard &&
+    stg push q3 &&
+    command_error stg push --continue 2>err &&
+    grep -e "no \`push\` operation in progress" err
+'
+
+test_expect_success 'Pick stops on conflict and skip deletes the picked patch' '
+    stg branch --create other "$(stg id "{base}")" &&
+    echo other >f &&
+    git commit -a -m other-f &&
+    echo other >other.txt &&
+    git add other.txt &&
+    git commit -m other-txt &&
+    stg branch master &&
+    conflict stg pick other~1 other &&
+    test "$(stg top)" = "other-f" &&
+    stg pick --skip &&
+    test "$(stg top)" = "other-txt" &&
+    ! stg series --all --noprefix | grep -e "^other-f$" &&
+    git diff-index --quiet HEAD
+'
+
+test_expect_success 'Import series stops on conflict' '
+    mkdir import &&
+    stg new r1 -m r1 &&
+    echo r1 >r1.txt &&
+    stg add r1.txt &&
+    stg refresh &&
+    stg export -d import p4 r1 &&
+    stg delete r1 other-txt &&
+    conflict stg import --reject --series import/series 2>err &&
+    grep -e "use \`stg import --continue\` to resume" err &&
+    test "$(stg top)" = "p5" &&
+    test_path_is_file f.rej
+'
+
+test_expect_success 'Continue importing series' '
+    rm f.rej &&
+    echo p4 >f &&
+    stg add f &&
+    stg import --continue &&
+    test "$(echo $(stg series --applied --noprefix))" = "x p1 q1 q2 q3 p5 r1" &&
+    stg show p5 | grep -e "^+p4"
+'
+
+test_expect_success 'Sync stops on conflict and continues' '
+    stg delete p5 r1 &&
+    stg export -d sync-series p1 &&
+    stg goto p1 &&
+    echo mine >f &&
+    stg refresh &&
+    conflict stg sync --series sync-series/series p1 2>err &&
+    grep -e "use \`stg sync --continue\` to resume" err &&
+    test "$(stg top)" = "p1" &&
+    echo synced >f &&
+    stg add f &&
+    stg sync --continue &&
+    stg show p1 | grep -e "^+synced" &&
+    git diff-index --quiet HEAD
+'
+
+test_expect_success 'Abort sync' '
+    echo mine >f &&
+    stg refresh &&
+    conflict stg sync --series sync-series/series p1 &&
+    stg sync --abort &&
+    stg show p1 | grep -e "^+mine" &&
+    git diff-index --quiet HEAD
+'
+
+test_done
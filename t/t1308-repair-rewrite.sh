@@ -0,0 +1,98 @@
+#!/bin/sh
+
+test_description='Test "stg repair" of patches rewritten with git'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize StGit patches' '
+    write_script edit-second <<-\EOF &&
+	sed -e "2s/^pick/edit/" "$1" >"$1.tmp" &&
+	mv "$1.tmp" "$1"
+	EOF
+    write_script swap-first-two <<-\EOF &&
+	sed -e "1{h;d;}" -e "2G" "$1" >"$1.tmp" &&
+	mv "$1.tmp" "$1"
+	EOF
+    cat >>.git/info/exclude <<-\EOF &&
+	edit-second
+	swap-first-two
+	EOF
+    test_write_lines 1 2 3 4 5 6 7 >foo.txt &&
+    git add foo.txt &&
+    git commit -m "add foo" &&
+    stg init &&
+    test_write_lines "1 from p1" 2 3 4 5 6 7 >foo.txt &&
+    stg new -m p1 &&
+    stg refresh &&
+    test_write_lines "1 from p1" 2 3 "4 from p4" 5 6 7 >foo.txt &&
+    stg new -m p4 &&
+    stg refresh &&
+    test_write_lines "1 from p1" 2 3 "4 from p4" 5 6 "7 from p7" >foo.txt &&
+    stg new -m p7 &&
+    stg refresh &&
+    stg new -m p-unapplied &&
+    stg pop p-unapplied &&
+    stg series --noprefix --applied >applied-before
+'
+
+test_expect_success 'Repair patch amended with git' '
+    test_write_lines "1 from p1" 2 3 "4 from p4" 5 6 "7 amended" >foo.txt &&
+    git commit -a --amend --no-edit &&
+    stg repair 2>err &&
+    grep "\`p7\` is now commit $(git rev-parse HEAD), rewritten from" err &&
+    stg series --noprefix --applied >actual &&
+    test_cmp applied-before actual &&
+    test "$(echo $(stg series --noprefix --unapplied))" = "p-unapplied" &&
+    test "$(stg id p7)" = "$(git rev-parse HEAD)" &&
+    test "$(stg top)" = "p7"
+'
+
+test_expect_success 'Repair middle patch amended during interactive rebase' '
+    GIT_SEQUENCE_EDITOR=./edit-second git rebase -i HEAD~3 &&
+    test_write_lines "1 from p1" 2 3 "4 amended" 5 6 7 >foo.txt &&
+    git commit -a --amend --no-edit &&
+    git rebase --continue &&
+    stg repair &&
+    stg series --noprefix --applied >actual &&
+    test_cmp applied-before actual &&
+    test "$(stg id p4)" = "$(git rev-parse HEAD~)" &&
+    test "$(stg id p7)" = "$(git rev-parse HEAD)" &&
+    test "$(stg id p1)" = "$(git rev-parse HEAD~2)" &&
+    git show $(stg id p4) | grep "^+4 amended"
+'
+
+test_expect_success 'Repair reordered patches by patch id' '
+    GIT_SEQUENCE_EDITOR=./swap-first-two git rebase -i HEAD~3 &&
+    git log -3 --format=%s >log &&
+    test_write_lines p7 p1 p4 >expected &&
+    test_cmp expected log &&
+    stg repair &&
+    test "$(echo $(stg series --noprefix --applied))" = "p4 p1 p7" &&
+    test "$(echo $(stg series --noprefix --unapplied))" = "p-unapplied" &&
+    test "$(stg id p4)" = "$(git rev-parse HEAD~2)" &&
+    test "$(stg id p1)" = "$(git rev-parse HEAD~)" &&
+    test "$(stg id p7)" = "$(git rev-parse HEAD)"
+'
+
+test_expect_success 'Repair rewritten patch with new commit inserted below' '
+    GIT_SEQUENCE_EDITOR=./edit-second git rebase -i HEAD~3 &&
+    echo "new" >bar.txt &&
+    git add bar.txt &&
+    git commit -m "add bar" &&
+    git rebase --continue &&
+    stg repair &&
+    test "$(echo $(stg series --noprefix --applied))" = "p4 p1 add-bar p7" &&
+    test "$(stg id add-bar)" = "$(git rev-parse HEAD~)" &&
+    test "$(stg id p7)" = "$(git rev-parse HEAD)" &&
+    test "$(echo $(stg series --noprefix --unapplied))" = "p-unapplied"
+'
+
+test_expect_success 'Reworded patch with different changes is not adopted' '
+    test_write_lines "1 from p1" 2 3 "4 amended" 5 6 "7 reworked" >foo.txt &&
+    git commit -a --amend -m "p7 reworked" &&
+    stg repair &&
+    test "$(echo $(stg series --noprefix --applied))" = "p4 p1 add-bar p7-reworked" &&
+    test "$(echo $(stg series --noprefix --unapplied))" = "p7 p-unapplied"
+'
+
+test_done
@@ -22,7 +22,7 @@ test_expect_success 'Test setup' '
 '
 
 test_expect_success 'Float a patch, causing a conflict two patches down' '
-    conflict stg float p1 &&
+    conflict stg float --ignore-deps p1 &&
     test "$(echo $(stg series))" = "+ p0 + p2 > p3 - p1" &&
     test "$(stg id p3)" = "$(git rev-list HEAD~0 -n 1)" &&
     test "$(stg id p2)" = "$(git rev-list HEAD~1 -n 1)" &&
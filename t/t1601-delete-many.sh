@@ -77,7 +77,7 @@ test_expect_success 'Delete leading to conflict when re-pushing' '
     echo "other stuff" >foo.txt &&
     stg new -m p-other &&
     stg refresh &&
-    conflict stg delete p-stuff
+    conflict stg delete --ignore-deps p-stuff
 '
 
 test_done
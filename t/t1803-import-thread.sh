@@ -0,0 +1,176 @@
+#!/bin/sh
+
+test_description='Test thread-aware import of patch series from mailboxes'
+
+. ./test-lib.sh
+
+# write_mail <mbox> <subject> <message-id> [<in-reply-to> [<file> <content>]]
+write_mail () {
+    {
+        echo "From nobody Mon Sep 17 00:00:00 2001" &&
+        echo "From: A U Thor <author@example.com>" &&
+        echo "Date: Thu, 7 Apr 2005 15:13:13 -0700" &&
+        echo "Subject: $2" &&
+        echo "Message-Id: <$3>" &&
+        if test -n "$4"
+        then
+            echo "In-Reply-To: <$4>" &&
+            echo "References: <$4>"
+        fi &&
+        echo &&
+        echo "Body of $3." &&
+        if test -n "$5"
+        then
+            echo "---" &&
+            echo "diff --git a/$5 b/$5" &&
+            echo "new file mode 100644" &&
+            echo "--- /dev/null" &&
+            echo "+++ b/$5" &&
+            echo "@@ -0,0 +1 @@" &&
+            echo "+$6"
+        fi &&
+        echo
+    } >>"$1"
+}
+
+test_expect_success 'Initialize repository' '
+    cat >>.git/info/exclude <<-\EOF &&
+	*.mbox
+	expected*
+	actual*
+	err
+	EOF
+    test_commit_bulk 1 &&
+    stg init
+'
+
+test_expect_success 'Create mailbox with interleaved series versions' '
+    write_mail series.mbox "[PATCH 0/3] Widget series" v1-0 &&
+    write_mail series.mbox "[PATCH 1/3] Add a" v1-1 v1-0 a.txt "a v1" &&
+    write_mail series.mbox "Re: [PATCH 1/3] Add a" reply-1 v1-1 &&
+    write_mail series.mbox "[PATCH v2 3/3] Add c" v2-3 v2-0 c.txt "c v2" &&
+    cat >>series.mbox <<-\EOF &&
+	From nobody Mon Sep 17 00:00:00 2001
+	From: A U Thor <author@example.com>
+	Date: Thu, 7 Apr 2005 15:13:13 -0700
+	Subject: [PATCH v2 0/3] Widget series
+	Message-Id: <v2-0>
+	In-Reply-To: <v1-0>
+	References: <v1-0>
+
+	This series adds widgets.
+
+	Widgets are added one file at a time.
+
+	A U Thor (3):
+	  Add a
+	  Add b
+	  Add c
+
+	 a.txt | 1 +
+	 b.txt | 1 +
+	 c.txt | 1 +
+	 3 files changed, 3 insertions(+)
+
+	EOF
+    write_mail series.mbox "[PATCH v2 1/3] Add a" v2-1 v2-0 a.txt "a v2" &&
+    write_mail series.mbox "[PATCH 2/3] Add b" v1-2 v1-0 b.txt "b v1" &&
+    write_mail series.mbox "[PATCH v2 2/3] Add b" v2-2 v2-0 b.txt "b v2" &&
+    write_mail series.mbox "Re: [PATCH v2 2/3] Add b" reply-2 v2-2 &&
+    write_mail series.mbox "[PATCH v3 1/3] Add a" v3-1 v3-0 a.txt "a v3" &&
+    write_mail series.mbox "[PATCH 3/3] Add c" v1-3 v1-0 c.txt "c v1" &&
+    write_mail series.mbox "[PATCH v2 2/3] Unrelated" other-2 other-0 b.txt "other" &&
+    write_mail series.mbox "[PATCH v3 3/3] Add c" v3-3 v3-0 c.txt "c v3"
+'
+
+test_expect_success 'Import latest complete version of series' '
+    stg import --mbox --thread series.mbox 2>err &&
+    grep "Skipping v3 is incomplete: missing 2/3" err &&
+    grep "Importing 3 patches from v2" err &&
+    stg series --noprefix >actual &&
+    test_write_lines add-a add-b add-c >expected &&
+    test_cmp expected actual &&
+    cat a.txt b.txt c.txt >actual &&
+    test_write_lines "a v2" "b v2" "c v2" >expected &&
+    test_cmp expected actual &&
+    test_must_fail git config branch.master.description &&
+    stg delete --top &&
+    stg delete --top &&
+    stg delete --top
+'
+
+test_expect_success 'Import series and describe branch from cover letter' '
+    stg import -M --thread --describe-branch series.mbox &&
+    stg series --noprefix >actual &&
+    test_write_lines add-a add-b add-c >expected &&
+    test_cmp expected actual &&
+    git config branch.master.description >actual &&
+    cat >expected <<-\EOF &&
+	Widget series
+
+	This series adds widgets.
+
+	Widgets are added one file at a time.
+	EOF
+    test_cmp expected actual &&
+    stg delete .. &&
+    git config --unset branch.master.description
+'
+
+test_expect_success 'Import series from stdin' '
+    stg import -M --thread <series.mbox &&
+    stg series --noprefix >actual &&
+    test_line_count = 3 actual &&
+    stg delete ..
+'
+
+test_expect_success 'Import unthreaded series in order' '
+    write_mail unthreaded.mbox "[PATCH 2/2] Add y" y-msg "" y.txt "y" &&
+    write_mail unthreaded.mbox "[PATCH 1/2] Add x" x-msg "" x.txt "x" &&
+    stg import -M --thread unthreaded.mbox &&
+    stg series --noprefix >actual &&
+    test_write_lines add-x add-y >expected &&
+    test_cmp expected actual &&
+    stg delete ..
+'
+
+test_expect_success 'Import single unnumbered patch' '
+    write_mail single.mbox "Re: [PATCH] Add z" reply-z &&
+    write_mail single.mbox "[PATCH] Add z" z-msg "" z.txt "z" &&
+    stg import -m --thread single.mbox &&
+    stg series --noprefix >actual &&
+    echo add-z >expected &&
+    test_cmp expected actual &&
+    stg delete ..
+'
+
+test_expect_success 'Describe branch without cover letter' '
+    stg import -M --thread --describe-branch unthreaded.mbox 2>err &&
+    grep "No cover letter found; branch description unchanged" err &&
+    test_must_fail git config branch.master.description &&
+    stg delete ..
+'
+
+test_expect_success 'Incomplete series' '
+    write_mail incomplete.mbox "[PATCH v2 1/3] Add a" v2-1 v2-0 a.txt "a v2" &&
+    write_mail incomplete.mbox "[PATCH v2 3/3] Add c" v2-3 v2-0 c.txt "c v2" &&
+    command_error stg import -M --thread incomplete.mbox 2>err &&
+    grep "error: patch series v2 is incomplete: missing 2/3" err &&
+    stg series --noprefix >actual &&
+    test_line_count = 0 actual
+'
+
+test_expect_success 'No patches in mailbox' '
+    write_mail nopatch.mbox "Re: [PATCH] Add z" reply-z &&
+    write_mail nopatch.mbox "Announcement" announce &&
+    command_error stg import -M --thread nopatch.mbox 2>err &&
+    grep "error: no \`\[PATCH\]\` messages found" err
+'
+
+test_expect_success 'Invalid option combinations' '
+    general_error stg import --thread series.mbox &&
+    general_error stg import -S --thread series.mbox &&
+    general_error stg import -M --describe-branch series.mbox
+'
+
+test_done
@@ -0,0 +1,168 @@
+#!/bin/sh
+
+test_description='Test collecting review trailers from mail replies'
+
+. ./test-lib.sh
+
+# write_mail <mbox> <subject> <message-id> [<in-reply-to> [<file> <content>]]
+write_mail () {
+    {
+        echo "From nobody Mon Sep 17 00:00:00 2001" &&
+        echo "From: A U Thor <author@example.com>" &&
+        echo "Date: Thu, 7 Apr 2005 15:13:13 -0700" &&
+        echo "Subject: $2" &&
+        echo "Message-Id: <$3>" &&
+        if test -n "$4"
+        then
+            echo "In-Reply-To: <$4>" &&
+            echo "References: <$4>"
+        fi &&
+        echo &&
+        echo "Body of $3." &&
+        if test -n "$5"
+        then
+            echo "---" &&
+            echo "diff --git a/$5 b/$5" &&
+            echo "new file mode 100644" &&
+            echo "--- /dev/null" &&
+            echo "+++ b/$5" &&
+            echo "@@ -0,0 +1 @@" &&
+            echo "+$6"
+        fi &&
+        echo
+    } >>"$1"
+}
+
+# write_reply <mbox> <subject> <message-id> <in-reply-to> <body-line>...
+write_reply () {
+    mbox="$1" subject="$2" id="$3" parent="$4" &&
+    shift 4 &&
+    {
+        echo "From nobody Mon Sep 17 00:00:00 2001" &&
+        echo "From: Re Viewer <reviewer@example.com>" &&
+        echo "Date: Fri, 8 Apr 2005 15:13:13 -0700" &&
+        echo "Subject: $subject" &&
+        echo "Message-Id: <$id>" &&
+        echo "In-Reply-To: <$parent>" &&
+        echo "References: <$parent>" &&
+        echo &&
+        test_write_lines "$@" &&
+        echo
+    } >>"$mbox"
+}
+
+test_expect_success 'Initialize repository' '
+    cat >>.git/info/exclude <<-\EOF &&
+	*.mbox
+	expected*
+	actual*
+	err
+	EOF
+    test_commit_bulk 1 &&
+    stg init
+'
+
+test_expect_success 'Import series with message ids' '
+    write_mail series.mbox "[PATCH 0/3] Widget series" cover &&
+    write_mail series.mbox "[PATCH 1/3] Add a" p1 cover a.txt "a" &&
+    write_mail series.mbox "[PATCH 2/3] Add b" p2 cover b.txt "b" &&
+    write_mail series.mbox "[PATCH 3/3] Add c" p3 cover c.txt "c" &&
+    stg import -M --thread --message-id series.mbox &&
+    stg series --noprefix >actual &&
+    test_write_lines add-a add-b add-c >expected &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Collect trailers from replies' '
+    cp series.mbox replies.mbox &&
+    write_reply replies.mbox "Re: [PATCH 2/3] Add b" r1 p2 \
+        "> Body of p2." \
+        "> Reviewed-by: Quoted Person <quoted@example.com>" \
+        "" \
+        "Looks good." \
+        "" \
+        "Reviewed-by: Re Viewer <reviewer@example.com>" &&
+    write_reply replies.mbox "Re: Re: [PATCH 2/3] Add b" r2 r1 \
+        "Tested-by: Tes Ter <tester@example.com>" &&
+    write_reply replies.mbox "Re: [PATCH 0/3] Widget series" r3 cover \
+        "For the series:" \
+        "Acked-by: Main Tainer <maintainer@example.com>" &&
+    write_reply replies.mbox "Re: [PATCH 3/3] Add c" r4 p3 \
+        "Nice, but no trailer." &&
+    stg import -M --collect-trailers replies.mbox &&
+    stg series --noprefix >actual &&
+    test_write_lines add-a add-b add-c >expected &&
+    test_cmp expected actual &&
+    test_write_lines a b c >expected &&
+    cat a.txt b.txt c.txt >actual &&
+    test_cmp expected actual &&
+    git log -1 --format=%B $(stg id add-b) >actual &&
+    cat >expected-b <<-\EOF &&
+	Message-Id: <p2>
+	Reviewed-by: Re Viewer <reviewer@example.com>
+	Tested-by: Tes Ter <tester@example.com>
+	Acked-by: Main Tainer <maintainer@example.com>
+	EOF
+    sed "/^\$/d" actual | tail -n 4 >actual-b &&
+    test_cmp expected-b actual-b &&
+    ! grep "Quoted Person" actual &&
+    git log -1 --format=%B $(stg id add-a) >actual &&
+    grep "Acked-by: Main Tainer <maintainer@example.com>" actual &&
+    ! grep "Reviewed-by" actual &&
+    git log -1 --format=%B $(stg id add-c) >actual &&
+    grep "Acked-by: Main Tainer <maintainer@example.com>" actual
+'
+
+test_expect_success 'Existing trailers are not added again' '
+    stg id add-a >expected &&
+    stg import -M --collect-trailers replies.mbox 2>err &&
+    grep "No new trailers to add" err &&
+    stg id add-a >actual &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Match patches by subject' '
+    write_reply subject.mbox "Re: [PATCH v2 1/3] Add a" r5 unknown-id \
+        "Reviewed-by: Other Reviewer <other@example.com>" &&
+    write_reply subject.mbox "Re: [PATCH v2 4/3] Add d" r6 other-unknown-id \
+        "Reviewed-by: Other Reviewer <other@example.com>" &&
+    stg pop add-c &&
+    stg import -M --collect-trailers subject.mbox 2>err &&
+    grep "No patch matches \`Add d\`; trailers ignored" err &&
+    git log -1 --format=%B $(stg id add-a) >actual &&
+    grep "Reviewed-by: Other Reviewer <other@example.com>" actual &&
+    stg series --noprefix --applied >actual &&
+    test_write_lines add-a add-b >expected &&
+    test_cmp expected actual &&
+    stg push
+'
+
+test_expect_success 'Match patches by link trailer' '
+    stg edit -m "Add b
+
+Link: https://lore.kernel.org/r/p2-link" add-b &&
+    write_reply link.mbox "Re: [PATCH] Changed subject" r7 p2-link \
+        "Tested-by: Tes Ter <tester@example.com>" &&
+    stg import -m --collect-trailers link.mbox &&
+    git log -1 --format=%B $(stg id add-b) >actual &&
+    grep "Tested-by: Tes Ter <tester@example.com>" actual &&
+    stg series --noprefix >actual &&
+    test_write_lines add-a add-b add-c >expected &&
+    test_cmp expected actual
+'
+
+test_expect_success 'No trailers in mailbox' '
+    stg id add-c >expected &&
+    stg import -M --collect-trailers series.mbox 2>err &&
+    grep "No new trailers to add" err &&
+    stg id add-c >actual &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Invalid option combinations' '
+    general_error stg import --collect-trailers replies.mbox &&
+    general_error stg import -S --collect-trailers replies.mbox &&
+    general_error stg import -M --thread --collect-trailers replies.mbox
+'
+
+test_done
@@ -0,0 +1,122 @@
+#!/bin/sh
+
+test_description="Test 'stg series-version'"
+
+. ./test-lib.sh
+
+test_expect_success 'Setup StGit stack' '
+    test_commit_bulk --message="p%s" 3 &&
+    stg uncommit -n 3
+'
+
+test_expect_success 'List with no saved versions' '
+    stg series-version list >out &&
+    test_must_be_empty out
+'
+
+test_expect_success 'Save first version' '
+    stg series-version save >out &&
+    echo "Saved series version v1" >expected &&
+    test_cmp expected out &&
+    test "$(git rev-parse refs/stacks/master.versions/v1)" = \
+         "$(git rev-parse refs/stacks/master)"
+'
+
+test_expect_success 'Saved versions are immutable' '
+    command_error stg series-version save --name v1 2>err &&
+    grep "series version \`v1\` already exists" err
+'
+
+test_expect_success 'Save named version' '
+    stg edit -m "p3 reworded" p3 &&
+    stg series-version save --name rfc &&
+    stg series-version save &&
+    stg series-version list >out &&
+    test_line_count = 3 out &&
+    head -n 1 out | grep -e "^v1  [0-9a-f]*  3 patches$" &&
+    sed -n 2p out | grep -e "^v2  " &&
+    tail -n 1 out | grep -e "^rfc  "
+'
+
+test_expect_success 'Show saved version' '
+    stg pop &&
+    stg series-version show v1 >out &&
+    test_line_count = 3 out &&
+    grep -e "^+ [0-9a-f]* p1 # p1$" out &&
+    grep -e "^> [0-9a-f]* p3 # p3$" out &&
+    stg series-version show v2 >out &&
+    grep -e "^> [0-9a-f]* p3 # p3 reworded$" out &&
+    stg push
+'
+
+test_expect_success 'Show unknown version' '
+    command_error stg series-version show v9 2>err &&
+    grep "series version \`v9\` not found" err
+'
+
+test_expect_success 'Diff saved versions' '
+    stg series-version diff v1 v2 >out &&
+    grep -e "^p3:$" out &&
+    grep -e "-    p3$" out &&
+    grep -e "+    p3 reworded$" out
+'
+
+test_expect_success 'Diff saved version with current series' '
+    stg new -m p4 &&
+    echo p4 >p4.txt &&
+    stg add p4.txt &&
+    stg refresh &&
+    stg delete p1 &&
+    stg series-version diff v2 >out &&
+    grep -e "^p4: added$" out &&
+    grep -e "^p1: removed$" out &&
+    stg series-version diff v1 -p p3 >out &&
+    head -n 1 out | grep -e "^p3:$" &&
+    ! grep -e "^p4" out
+'
+
+test_expect_success 'Email format uses next version' '
+    stg email format -o mail --all --cover-letter &&
+    test_path_exists mail/v3-0000-cover-letter.patch &&
+    grep -e "Subject: \[PATCH v3 0/3\]" mail/v3-0000-cover-letter.patch &&
+    grep -e "Range-diff against v2:" mail/v3-0000-cover-letter.patch &&
+    rm -r mail
+'
+
+test_expect_success 'Email format of saved version' '
+    stg series-version save &&
+    stg email format -o mail --all --cover-letter &&
+    test_path_exists mail/v3-0001-p2.patch &&
+    grep -e "Range-diff against v2:" mail/v3-0000-cover-letter.patch &&
+    rm -r mail
+'
+
+test_expect_success 'Email format explicit reroll count' '
+    stg email format -o mail --all --reroll-count=7 &&
+    test_path_exists mail/v7-0001-p2.patch &&
+    ! grep "Range-diff" mail/v7-0001-p2.patch &&
+    rm -r mail
+'
+
+test_expect_success 'Email format without series versions' '
+    stg email format -o mail --all --cover-letter --no-series-version &&
+    test_path_exists mail/0000-cover-letter.patch &&
+    ! grep "Range-diff" mail/0000-cover-letter.patch &&
+    rm -r mail
+'
+
+test_expect_success 'Rename branch keeps versions' '
+    stg branch --rename renamed &&
+    stg series-version list >out &&
+    test_line_count = 4 out &&
+    test_must_fail git rev-parse --verify -q refs/stacks/master.versions/v1 &&
+    git rev-parse --verify -q refs/stacks/renamed.versions/v1
+'
+
+test_expect_success 'Deleting branch deletes versions' '
+    stg branch --create other &&
+    stg branch --delete --force renamed &&
+    test_must_fail git rev-parse --verify -q refs/stacks/renamed.versions/v1
+'
+
+test_done
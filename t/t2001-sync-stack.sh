@@ -0,0 +1,152 @@
+#!/bin/sh
+
+test_description='Test sharing stacks with stg stack and stg sync --from-stack'
+
+. ./test-lib.sh
+
+test_expect_success 'Create stack and shared repository' '
+    cat >>.git/info/exclude <<-\EOF &&
+	*.bundle
+	*.git
+	other
+	expected*
+	actual*
+	err
+	EOF
+    for p in a b c
+    do
+        test_write_lines "$p 1" "$p 2" "$p 3" "$p 4" "$p 5" "$p 6" "$p 7" >$p.txt || return 1
+    done &&
+    git add a.txt b.txt c.txt &&
+    git commit -m base &&
+    git init --bare shared.git &&
+    git remote add shared "$(pwd)/shared.git" &&
+    git push -q shared master &&
+    stg init &&
+    for p in a b c
+    do
+        test_write_lines "$p 1" "$p 2" "$p 3" "$p 4 patched" "$p 5" "$p 6" "$p 7" >$p.txt &&
+        stg new -m "patch $p" p$p &&
+        stg refresh || return 1
+    done
+'
+
+test_expect_success 'Push stack' '
+    stg stack push shared &&
+    git rev-parse refs/stacks/master >expected &&
+    git --git-dir=shared.git rev-parse refs/stacks/master >actual &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Push stack without stack' '
+    git branch nostack &&
+    command_error stg stack push shared nostack 2>err &&
+    grep "StGit stack not initialized for branch \`nostack\`" err
+'
+
+test_expect_success 'Fetch stack and create copy of it' '
+    stg export --stack-archive stack.bundle &&
+    git clone -q shared.git other &&
+    (
+        cd other &&
+        stg stack fetch origin 2>../err &&
+        git rev-parse refs/remote-stacks/origin/master >../actual &&
+        stg import --stack-archive --branch-name work ../stack.bundle
+    ) &&
+    grep "Fetched stack \`origin/master\`" err &&
+    git rev-parse refs/stacks/master >expected &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Fetch only stack state references' '
+    git --git-dir=shared.git update-ref refs/stacks/master.versions/v1 refs/stacks/master &&
+    git --git-dir=shared.git update-ref refs/stacks/master.published/pub refs/stacks/master &&
+    (
+        cd other &&
+        stg stack fetch origin 2>../err &&
+        git for-each-ref --format="%(refname)" refs/remote-stacks/ >../actual
+    ) &&
+    grep "Fetched stack \`origin/master\`" err &&
+    ! grep -e versions -e published err &&
+    echo refs/remote-stacks/origin/master >expected &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Change, rename, remove, and add patches' '
+    stg goto pa &&
+    test_write_lines "a 1" "a 2" "a 3" "a 4 patched" "a 5" "a 6" "a 7 changed" >a.txt &&
+    stg refresh &&
+    stg goto pb &&
+    test_write_lines "b 1" "b 2" "b 3" "b 4 patched" "b 5" "b 6" "b 7 changed" >b.txt &&
+    stg refresh &&
+    stg rename pb pb-renamed &&
+    stg push &&
+    stg delete pc &&
+    echo d >d.txt &&
+    git add d.txt &&
+    stg new -m "patch d" pd &&
+    stg refresh &&
+    stg stack push shared
+'
+
+test_expect_success 'Sync from fetched stack' '
+    (
+        cd other &&
+        test_write_lines "a 1 changed" "a 2" "a 3" "a 4 patched" "a 5" "a 6" "a 7" >a.txt &&
+        stg refresh -p pa &&
+        stg stack fetch origin master &&
+        stg sync --from-stack origin/master --all 2>../err &&
+        stg series --noprefix >../actual-series &&
+        cat a.txt b.txt c.txt >../actual
+    ) &&
+    grep "Patch \`pd\` added in \`origin/master\`" err &&
+    grep "Patch \`pc\` removed in \`origin/master\`" err &&
+    grep "Patch \`pb\` renamed to \`pb-renamed\` in \`origin/master\`" err &&
+    test_write_lines pa pb pc >expected-series &&
+    test_cmp expected-series actual-series &&
+    test_write_lines "a 1 changed" "a 2" "a 3" "a 4 patched" "a 5" "a 6" "a 7 changed" >expected &&
+    test_write_lines "b 1" "b 2" "b 3" "b 4 patched" "b 5" "b 6" "b 7 changed" >>expected &&
+    test_write_lines "c 1" "c 2" "c 3" "c 4 patched" "c 5" "c 6" "c 7" >>expected &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Sync from local branch stack' '
+    (
+        cd other &&
+        stg branch --create local-copy master &&
+        stg new -m "patch a" pa &&
+        test_write_lines "a 1" "a 2" "a 3" "a 4 patched" "a 5" "a 6" "a 7" >a.txt &&
+        stg refresh &&
+        stg sync --from-stack work pa &&
+        cat a.txt >../actual
+    ) &&
+    test_write_lines "a 1 changed" "a 2" "a 3" "a 4 patched" "a 5" "a 6" "a 7 changed" >expected &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Push non-fast-forward stack' '
+    stg log --clear &&
+    command_error stg stack push shared &&
+    stg stack push --force shared &&
+    git rev-parse refs/stacks/master >expected &&
+    git --git-dir=shared.git rev-parse refs/stacks/master >actual &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Sync from unknown stack' '
+    command_error stg sync --from-stack shared/unknown --all 2>err &&
+    grep "stack \`shared/unknown\` not found" err
+'
+
+test_expect_success 'Sync from current stack' '
+    command_error stg sync --from-stack master --all 2>err &&
+    grep "cannot synchronize with the current branch" err
+'
+
+test_expect_success 'Invalid option combinations' '
+    general_error stg sync --from-stack master -B nostack --all &&
+    general_error stg stack &&
+    general_error stg stack fetch
+'
+
+test_done
@@ -286,4 +286,157 @@ test_expect_success 'Patches with multi-line subject' '
     test "$(stg series --applied -c)" = "2"
 '
 
+test_expect_success 'Reword a patch' '
+    stg delete $(stg series --all --noprefix --no-description) &&
+    stg new -m p0 &&
+    echo p1 >p1.txt &&
+    stg add p1.txt &&
+    stg new -m p1 &&
+    stg refresh &&
+    write_script fake-editor <<-\EOF &&
+	if [ ! -f .fake-editor-has-run-once ]
+	then
+	    printf "keep p0\nreword p1\n" >"$1" &&
+	    touch .fake-editor-has-run-once
+	else
+	    ! grep -e "^diff --git" "$1" &&
+	    sed "s/^p1$/p1 reworded/" "$1" >"$1".tmp && mv "$1".tmp "$1"
+	fi
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    test_when_finished rm -f .fake-editor-has-run-once &&
+    stg rebase --interactive &&
+    git diff-index --quiet HEAD &&
+    test "$(git log -1 --format=%s)" = "p1 reworded" &&
+    stg show p1 | grep -e "p1.txt"
+'
+
+test_expect_success 'Exec commands between pushes' '
+    stg delete $(stg series --all --noprefix --no-description) &&
+    stg new -m p0 &&
+    stg new -m p1 &&
+    stg new -m p2 &&
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\nexec stg top >exec-out # comment\nkeep p1\nx stg top >>exec-out\nkeep p2\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    stg rebase --interactive &&
+    test "$(echo $(cat exec-out))" = "p0 p1" &&
+    test "$(stg series --applied -c)" = "3" &&
+    rm exec-out
+'
+
+test_expect_success 'Failed exec stops the rebase' '
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\nkeep p1\nexec false\nkeep p2\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    command_error stg rebase --interactive 2>err &&
+    grep -e "\`false\` exited with code 1" err &&
+    grep -e "Stopped at \`exec\`; remaining patches to push: p2" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "p0 p1" &&
+    test "$(echo $(stg series --unapplied --noprefix))" = "p2" &&
+    stg push
+'
+
+test_expect_success 'Break stops the rebase' '
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\nbreak\nkeep p2\nkeep p1\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    stg rebase --interactive 2>err &&
+    grep -e "Stopped at \`break\`; remaining patches to push: p2 p1" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "p0" &&
+    test "$(echo $(stg series --unapplied --noprefix))" = "p2 p1" &&
+    stg push -a
+'
+
+test_expect_success 'Continue rebase after break' '
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\nbreak\nkeep p2\nexec stg top >exec-out\nkeep p1\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    stg rebase --interactive 2>err &&
+    grep -e "stg rebase --continue" err &&
+    test "$(echo $(stg series --applied --noprefix))" = "p0" &&
+    stg rebase --continue &&
+    test "$(cat exec-out)" = "p2" &&
+    test "$(echo $(stg series --applied --noprefix))" = "p0 p2 p1" &&
+    command_error stg rebase --continue 2>err &&
+    grep -e "no \`rebase\` operation in progress" err &&
+    rm exec-out
+'
+
+test_expect_success 'Continue rebase after failed exec' '
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\nexec test -e exec-ok\nkeep p1\nkeep p2\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    command_error stg rebase --interactive &&
+    test "$(echo $(stg series --applied --noprefix))" = "p0" &&
+    touch exec-ok &&
+    stg rebase --continue &&
+    test "$(echo $(stg series --applied --noprefix))" = "p0 p1 p2" &&
+    rm exec-ok
+'
+
+test_expect_success 'Abort rebase after break' '
+    write_script fake-editor <<-\EOF &&
+	printf "keep p2\nbreak\nkeep p0\nkeep p1\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    stg rebase --interactive &&
+    test "$(echo $(stg series --applied --noprefix))" = "p2" &&
+    stg rebase --abort &&
+    test "$(echo $(stg series --applied --noprefix))" = "p0 p1 p2" &&
+    git diff-index --quiet HEAD
+'
+
+test_expect_success 'Exec and break must be above APPLY_LINE' '
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\n# --- APPLY_LINE ---\nbreak\nkeep p1\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    command_error stg rebase --interactive 2>err &&
+    grep -e "\`break\` instructions must be above the APPLY_LINE" err
+'
+
+test_expect_success 'Squash after exec is an error' '
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\nexec true\nsquash p1\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    command_error stg rebase --interactive 2>err &&
+    grep -e "cannot squash \`p1\`: no preceding patch" err
+'
+
+test_expect_success 'Spill a patch' '
+    stg delete $(stg series --all --noprefix --no-description) &&
+    stg new -m p0 &&
+    echo spilled >spilled.txt &&
+    stg add spilled.txt &&
+    stg new -m p1 &&
+    stg refresh &&
+    write_script fake-editor <<-\EOF &&
+	printf "keep p0\nspill p1\n" >"$1"
+	EOF
+    test_set_editor "$(pwd)/fake-editor" &&
+    test_when_finished test_set_editor false &&
+    stg rebase --interactive &&
+    test "$(echo $(stg series --all --noprefix))" = "p0" &&
+    test "$(cat spilled.txt)" = "spilled" &&
+    git diff --cached --name-only >changed &&
+    grep -e "^spilled.txt$" changed &&
+    git reset --hard
+'
+
 test_done
@@ -0,0 +1,146 @@
+#!/bin/sh
+
+test_description='Test "stg split"'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt split with no applied patches' '
+    stg init &&
+    command_error stg split --path a.txt 2>err &&
+    grep "error: no patches applied" err
+'
+
+test_expect_success 'Initialize stack' '
+    cat >>.git/info/exclude <<-\EOF &&
+	expected*.txt
+	out*.txt
+	err
+	EOF
+    test_write_lines 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 >a.txt &&
+    git add a.txt &&
+    git commit -m base &&
+    stg new -m "big patch" --author "Some Author <some@example.com>" big &&
+    test_write_lines 1 two 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 nineteen 20 >a.txt &&
+    echo b >b.txt &&
+    echo c >c.txt &&
+    git add a.txt b.txt c.txt &&
+    stg refresh --index &&
+    stg new -m top &&
+    echo d >d.txt &&
+    git add d.txt &&
+    stg refresh --index
+'
+
+test_expect_success 'Split mode is required' '
+    general_error stg split big 2>err &&
+    grep -e "--path" err
+'
+
+test_expect_success 'Split by paths' '
+    stg split --path b.txt --path a.txt big &&
+    stg series --applied -d >out.txt &&
+    cat >expected.txt <<-\EOF &&
+	+ big         # big patch
+	+ big-patch   # big patch
+	+ big-patch-1 # big patch
+	> top         # top
+	EOF
+    test_cmp expected.txt out.txt &&
+    stg files big >out.txt &&
+    echo "A b.txt" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg files big-patch >out.txt &&
+    echo "M a.txt" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg files big-patch-1 >out.txt &&
+    echo "A c.txt" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    test "$(git log -1 --format="%an <%ae>" $(stg id big-patch-1))" = \
+         "Some Author <some@example.com>"
+'
+
+test_expect_success 'Split is a single undo step' '
+    stg undo --hard &&
+    stg series --applied --noprefix >out.txt &&
+    test_write_lines big top >expected.txt &&
+    test_cmp expected.txt out.txt
+'
+
+test_expect_success 'Split fails for paths without changes' '
+    command_error stg split --path d.txt big 2>err &&
+    grep "no remaining changes match \`--path d.txt\`" err &&
+    command_error stg split --path a.txt,b.txt,c.txt big 2>err &&
+    grep "not split; all changes would remain in a single patch" err
+'
+
+test_expect_success 'Split unapplied patch' '
+    stg pop -a &&
+    stg split --path c.txt big &&
+    stg series --noprefix >out.txt &&
+    test_write_lines big big-patch top >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg files big >out.txt &&
+    echo "A c.txt" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    test -z "$(stg series --applied)" &&
+    stg undo &&
+    stg push -a
+'
+
+test_expect_success 'Split interactively' '
+    printf "y\nn\nn\nn\n" | stg split -i big >out.txt &&
+    grep "Select changes for new patch #1" out.txt &&
+    grep "Select changes for new patch #2" out.txt &&
+    stg series --applied --noprefix >out.txt &&
+    test_write_lines big big-patch top >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg show big >out.txt &&
+    grep "^+two$" out.txt &&
+    ! grep "^+nineteen$" out.txt &&
+    ! grep "b.txt" out.txt &&
+    stg show big-patch >out.txt &&
+    grep "^+nineteen$" out.txt &&
+    grep "^+b$" out.txt &&
+    grep "^+c$" out.txt &&
+    stg undo --hard
+'
+
+test_expect_success 'Split interactively over several patches' '
+    printf "a\nn\nn\nn\ny\n" | stg split -i big >out.txt &&
+    stg series --applied --noprefix >out.txt &&
+    test_write_lines big big-patch big-patch-1 top >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg files big >out.txt &&
+    echo "M a.txt" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg files big-patch >out.txt &&
+    echo "A c.txt" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg files big-patch-1 >out.txt &&
+    echo "A b.txt" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg undo --hard
+'
+
+test_expect_success 'Interactive split selecting everything does not split' '
+    printf "a\na\na\n" | command_error stg split -i big 2>err &&
+    grep "not split; all changes would remain in a single patch" err
+'
+
+test_expect_success 'Split binary change interactively' '
+    stg new -m bin bin &&
+    printf "\000\001" >bin.dat &&
+    echo e >e.txt &&
+    git add bin.dat e.txt &&
+    stg refresh --index &&
+    printf "y\nn\n" | stg split -i bin >out.txt &&
+    grep "Include this change" out.txt &&
+    stg files bin >out.txt &&
+    echo "A bin.dat" >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg files bin-1 >out.txt &&
+    echo "A e.txt" >expected.txt &&
+    test_cmp expected.txt out.txt
+'
+
+test_done
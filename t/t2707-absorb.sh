@@ -0,0 +1,108 @@
+#!/bin/sh
+
+test_description='Test "stg absorb"'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt absorb with no applied patches' '
+    stg init &&
+    command_error stg absorb 2>err &&
+    grep "error: no patches applied" err
+'
+
+test_expect_success 'Initialize stack' '
+    cat >>.git/info/exclude <<-\EOF &&
+	expected*.txt
+	out*.txt
+	err
+	EOF
+    test_write_lines 1 2 3 4 5 6 7 8 9 10 >f.txt &&
+    git add f.txt &&
+    git commit -m base &&
+    stg new -m p1 &&
+    test_write_lines 1 two 3 4 5 6 7 eight 9 10 >f.txt &&
+    stg refresh &&
+    stg new -m p2 &&
+    test_write_lines 1 1a 1b two 3 four 5 6 7 eight 9 10 >f.txt &&
+    stg refresh &&
+    stg new -m p3 &&
+    echo x >g.txt &&
+    stg add g.txt &&
+    stg refresh
+'
+
+test_expect_success 'Absorb with no local changes' '
+    command_error stg absorb 2>err &&
+    grep "error: no local changes to absorb" err
+'
+
+test_expect_success 'Dry run reports hunk attribution' '
+    test_write_lines 1 1a 1b TWO 3 four 5 six 7 eight nine-ish 9 10 >f.txt &&
+    echo y >>g.txt &&
+    stg absorb --dry-run >out.txt &&
+    grep "^f.txt: @@ -4 +4 @@.* -> p2$" out.txt &&
+    grep "^f.txt: @@ -8 +8 @@.* -> p3 (unattributed)$" out.txt &&
+    grep "^f.txt: @@ -10,0 +11 @@.* -> p1$" out.txt &&
+    grep "^g.txt: @@ -1,0 +2 @@.* -> p3$" out.txt &&
+    git diff --name-only >out.txt &&
+    test_write_lines f.txt g.txt >expected.txt &&
+    test_cmp expected.txt out.txt
+'
+
+test_expect_success 'Absorb hunks into patches' '
+    stg absorb &&
+    test -z "$(git status --porcelain -- f.txt g.txt)" &&
+    stg show p1 >out.txt &&
+    grep "^+eight$" out.txt &&
+    grep "^+nine-ish$" out.txt &&
+    ! grep "TWO" out.txt &&
+    stg show p2 >out.txt &&
+    grep "^+TWO$" out.txt &&
+    grep "^+four$" out.txt &&
+    stg show p3 >out.txt &&
+    grep "^+six$" out.txt &&
+    grep "^+y$" out.txt &&
+    test_write_lines 1 1a 1b TWO 3 four 5 six 7 eight nine-ish 9 10 >expected.txt &&
+    test_cmp expected.txt f.txt
+'
+
+test_expect_success 'Absorb is a single stack log entry' '
+    stg log -n 1 >out.txt &&
+    grep "absorb" out.txt &&
+    stg undo --hard &&
+    stg show p1 >out.txt &&
+    ! grep "nine-ish" out.txt
+'
+
+test_expect_success 'Refuse absorb with dirty index and worktree' '
+    test_write_lines 1 1a 1b two 3 four 5 6 7 EIGHT 9 10 >f.txt &&
+    git add f.txt &&
+    echo z >>g.txt &&
+    command_error stg absorb 2>err &&
+    grep "the index is dirty" err
+'
+
+test_expect_success 'Absorb from index' '
+    stg absorb --index &&
+    stg show p1 >out.txt &&
+    grep "^+EIGHT$" out.txt &&
+    git diff --name-only >out.txt &&
+    echo g.txt >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    git checkout g.txt
+'
+
+test_expect_success 'Absorb limited to paths' '
+    test_write_lines 1 1a 1b two 3 four 5 6 7 EIGHT 9 ten >f.txt &&
+    echo z >>g.txt &&
+    stg absorb f.txt &&
+    stg show p3 >out.txt &&
+    grep "^+ten$" out.txt &&
+    ! grep "^+z$" out.txt &&
+    git diff --name-only >out.txt &&
+    echo g.txt >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    git checkout g.txt
+'
+
+test_done
@@ -0,0 +1,138 @@
+#!/bin/sh
+
+test_description='Test "stg meta"'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt meta with uninitialized stack' '
+    command_error stg meta 2>err &&
+    grep "error: no patches applied" err
+'
+
+test_expect_success 'Initialize stack' '
+    stg init &&
+    stg new -m p0 &&
+    echo a >a.txt &&
+    stg add a.txt &&
+    stg refresh &&
+    stg new -m p1 &&
+    echo b >b.txt &&
+    stg add b.txt &&
+    stg refresh &&
+    stg new -m p2 &&
+    echo c >c.txt &&
+    stg add c.txt &&
+    stg refresh
+'
+
+test_expect_success 'No metadata by default' '
+    stg meta >out &&
+    test_must_be_empty out &&
+    stg meta p0 >out &&
+    test_must_be_empty out
+'
+
+test_expect_success 'Set metadata' '
+    stg meta -l net -l fix --status draft -m "<p1@example.com>" -c "v2: address review" p1 &&
+    stg meta p1 >out &&
+    cat >expected <<-\EOF &&
+	Status: draft
+	Labels: fix, net
+	Message-Id: <p1@example.com>
+	Changelog:
+	  v2: address review
+	EOF
+    test_cmp expected out
+'
+
+test_expect_success 'Metadata is recorded in stack.json' '
+    git cat-file -p refs/stacks/master:stack.json >stack.json &&
+    grep "\"status\": \"draft\"" stack.json &&
+    grep "\"message-id\": \"<p1@example.com>\"" stack.json
+'
+
+test_expect_success 'Metadata is shown in patch log' '
+    stg log --diff p1 >out &&
+    grep "^ *+Status: draft" out
+'
+
+test_expect_success 'Edit metadata' '
+    stg meta --unlabel fix --status posted -c "v3: rebase" p1 &&
+    stg meta p1 >out &&
+    cat >expected <<-\EOF &&
+	Status: posted
+	Labels: net
+	Message-Id: <p1@example.com>
+	Changelog:
+	  v2: address review
+	  v3: rebase
+	EOF
+    test_cmp expected out
+'
+
+test_expect_success 'Invalid status' '
+    general_error stg meta --status bogus p1
+'
+
+test_expect_success 'Undo metadata edit' '
+    stg undo &&
+    stg meta p1 >out &&
+    grep "Status: draft" out
+'
+
+test_expect_success 'Metadata follows rename' '
+    stg rename p1 p1-renamed &&
+    stg meta p1-renamed >out &&
+    grep "Status: draft" out
+'
+
+test_expect_success 'Metadata follows reordering' '
+    stg float p1-renamed &&
+    stg meta p1-renamed >out &&
+    grep "Status: draft" out &&
+    stg pop -a &&
+    stg push -a &&
+    stg meta p1-renamed >out &&
+    grep "Status: draft" out
+'
+
+test_expect_success 'Metadata follows pick' '
+    stg pick --name p1-copy --noapply p1-renamed &&
+    stg meta p1-copy >out &&
+    grep "Labels: fix, net" out
+'
+
+test_expect_success 'Reset metadata of a single patch' '
+    stg meta --clear p1-copy &&
+    stg meta p1-copy >out &&
+    test_must_be_empty out &&
+    stg reset refs/stacks/master^~1 p1-copy &&
+    stg meta p1-copy >out &&
+    grep "Labels: fix, net" out
+'
+
+test_expect_success 'Squash combines metadata' '
+    stg delete p1-copy &&
+    stg meta -l ui -c "v1: initial" p2 &&
+    stg squash -n squashed -m squashed p2 p1-renamed &&
+    stg meta squashed >out &&
+    cat >expected <<-\EOF &&
+	Status: draft
+	Labels: fix, net, ui
+	Message-Id: <p1@example.com>
+	Changelog:
+	  v1: initial
+	  v2: address review
+	EOF
+    test_cmp expected out
+'
+
+test_expect_success 'Clear metadata' '
+    stg meta --clear squashed &&
+    stg meta squashed >out &&
+    test_must_be_empty out &&
+    git cat-file -p refs/stacks/master:stack.json >stack.json &&
+    ! grep "\"meta\"" stack.json
+'
+
+test_done
@@ -29,7 +29,7 @@ test_expect_success 'Invalid arguments' '
 '
 
 test_expect_success 'Pop middle patch, creating a conflict' '
-    conflict stg pop p2 &&
+    conflict stg pop --ignore-deps p2 &&
     stg status a >actual.txt &&
     cat >expected.txt <<-\EOF &&
 	UU a
@@ -0,0 +1,111 @@
+#!/bin/sh
+
+test_description='Test "stg undo --patch"'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize StGit stack with three patches' '
+    test_commit_bulk 1 &&
+    stg init &&
+    for p in p0 p1 p2
+    do
+        echo "$p version 1" >$p.txt &&
+        stg add $p.txt &&
+        stg new --refresh -m "$p" || return 1
+    done
+'
+
+test_expect_success 'Attempt undo of patch without history' '
+    command_error stg undo --patch p1 2>err &&
+    grep -e "not enough undo information available for patch .p1." err
+'
+
+test_expect_success 'Attempt undo of unknown patch' '
+    command_error stg undo --patch p3 2>err &&
+    grep -e "patch .p3. does not exist" err
+'
+
+test_expect_success 'Modify middle patch' '
+    stg goto p1 &&
+    echo "p1 version 2" >p1.txt &&
+    stg refresh &&
+    stg goto p2 &&
+    test "$(cat p1.txt)" = "p1 version 2"
+'
+
+test_expect_success 'Undo modification of middle patch' '
+    p0_id=$(stg id p0) &&
+    stg undo --patch p1 &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1 p2" &&
+    test "$(stg top)" = "p2" &&
+    test "$(stg id p0)" = "$p0_id" &&
+    test "$(cat p1.txt)" = "p1 version 1" &&
+    test "$(cat p2.txt)" = "p2 version 1" &&
+    stg log -n 1 >log.txt &&
+    grep -e "undo 1: p1" log.txt
+'
+
+test_expect_success 'Undo of patch undo restores modification' '
+    stg undo --patch p1 &&
+    test "$(cat p1.txt)" = "p1 version 2"
+'
+
+test_expect_success 'Undo message change' '
+    stg edit -m "p1 reworded" p1 &&
+    test "$(git log -1 --format=%s $(stg id p1))" = "p1 reworded" &&
+    stg undo --patch p1 &&
+    test "$(git log -1 --format=%s $(stg id p1))" = "p1" &&
+    test "$(cat p1.txt)" = "p1 version 2"
+'
+
+test_expect_success 'Rebased patches are not new versions' '
+    stg pop -a &&
+    echo "base" >base.txt &&
+    git add base.txt &&
+    git commit -m "base change" &&
+    stg push -a &&
+    test "$(cat base.txt)" = "base" &&
+    stg undo --patch p1 &&
+    test "$(cat p1.txt)" = "p1 version 2" &&
+    test "$(git log -1 --format=%s $(stg id p1))" = "p1 reworded" &&
+    test "$(cat base.txt)" = "base"
+'
+
+test_expect_success 'Undo several versions back' '
+    stg undo --patch p1 -n 4 &&
+    test "$(cat p1.txt)" = "p1 version 1" &&
+    test "$(git log -1 --format=%s $(stg id p1))" = "p1" &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1 p2"
+'
+
+test_expect_success 'Undo unapplied patch' '
+    stg goto p1 &&
+    echo "p1 version 3" >p1.txt &&
+    stg refresh &&
+    stg pop &&
+    stg undo --patch p1 &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0" &&
+    test "$(echo $(stg series --noprefix --unapplied))" = "p1 p2" &&
+    test_path_is_missing p1.txt &&
+    stg push &&
+    test "$(cat p1.txt)" = "p1 version 1"
+'
+
+test_expect_success 'Undo patch with conflict' '
+    stg push p2 &&
+    stg goto p1 &&
+    echo "p1 version 4" >p1.txt &&
+    stg refresh &&
+    stg goto p2 &&
+    echo "p2 changes p1" >p1.txt &&
+    stg refresh &&
+    conflict stg undo --patch p1 2>err &&
+    grep -e "merge conflicts" err &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1 p2" &&
+    test "$(git show $(stg id p1):p1.txt)" = "p1 version 1" &&
+    git diff --name-only --diff-filter=U >conflicts &&
+    test_write_lines p1.txt >expected &&
+    test_cmp expected conflicts
+'
+
+test_done
@@ -0,0 +1,165 @@
+#!/bin/sh
+
+test_description='Test patch name generation strategies'
+
+. ./test-lib.sh
+
+test_expect_success 'Initialize repository' '
+    test_commit_bulk --message="base %s" 2 &&
+    stg init
+'
+
+test_expect_success 'Subject strategy is the default' '
+    stg new -m "Add a feature" &&
+    test_config stgit.namestrategy subject &&
+    stg new -m "Add another feature" &&
+    stg top >top.txt &&
+    echo "add-another-feature" >expected.txt &&
+    test_cmp expected.txt top.txt &&
+    stg series --noprefix -A >series.txt &&
+    test_write_lines add-a-feature add-another-feature >expected.txt &&
+    test_cmp expected.txt series.txt &&
+    stg delete --top &&
+    stg delete --top
+'
+
+test_expect_success 'Invalid strategy' '
+    test_config stgit.namestrategy bogus &&
+    command_error stg new -m "Some patch" 2>err &&
+    grep "invalid \`stgit.namestrategy\` value \`bogus\`" err
+'
+
+test_expect_success 'Ticket strategy from trailer' '
+    test_config stgit.namestrategy ticket &&
+    stg new -m "Fix the frobnicator
+
+Longer description.
+
+Ticket: JIRA-123" &&
+    test "$(stg top)" = "JIRA-123-fix-the-frobnicator"
+'
+
+test_expect_success 'Ticket strategy does not repeat ticket in subject' '
+    test_config stgit.namestrategy ticket &&
+    stg new -m "JIRA-123: Fix the widget
+
+Refs: JIRA-123" &&
+    test "$(stg top)" = "jira-123-fix-the-widget"
+'
+
+test_expect_success 'Ticket strategy from branch name' '
+    test_config stgit.namestrategy ticket &&
+    stg branch --create feature/PROJ-42-widgets &&
+    stg new -m "Improve widgets" &&
+    test "$(stg top)" = "PROJ-42-improve-widgets" &&
+    stg new -m "Improve widgets" &&
+    test "$(stg top)" = "PROJ-42-improve-widgets-1" &&
+    stg new -m "Other ticket
+
+Ticket: OTHER-7" &&
+    test "$(stg top)" = "OTHER-7-other-ticket" &&
+    stg branch master
+'
+
+test_expect_success 'Ticket strategy falls back to subject' '
+    test_config stgit.namestrategy ticket &&
+    stg new -m "No ticket here" &&
+    test "$(stg top)" = "no-ticket-here"
+'
+
+test_expect_success 'Ticket strategy honors name length' '
+    test_config stgit.namestrategy ticket &&
+    test_config stgit.namelength 20 &&
+    stg new -m "A rather long subject for a patch
+
+Ticket: ABC-1" &&
+    test "$(stg top)" = "ABC-1-a-rather-long"
+'
+
+test_expect_success 'Date strategy' '
+    test_config stgit.namestrategy date &&
+    today=$(date +%Y%m%d) &&
+    stg new -m "First dated patch" &&
+    stg new -m "Second dated patch" &&
+    stg series --noprefix >series.txt &&
+    tail -n 2 series.txt >dated.txt &&
+    test_write_lines "$today-1" "$today-2" >expected.txt &&
+    test_cmp expected.txt dated.txt
+'
+
+test_expect_success 'Date strategy follows highest sequence number' '
+    test_config stgit.namestrategy date &&
+    today=$(date +%Y%m%d) &&
+    stg new -m "Explicitly dated patch" "$today-5" &&
+    stg new -m "Next dated patch" &&
+    test "$(stg top)" = "$today-6" &&
+    stg delete --top &&
+    stg delete --top
+'
+
+test_expect_success 'Command strategy' '
+    test_config stgit.namestrategy command &&
+    test_config stgit.namecmd "head -n 1 | tr a-z A-Z | sed -e \"s/^/cmd-/\"" &&
+    stg new -m "from command" &&
+    test "$(stg top)" = "cmd-FROM-COMMAND"
+'
+
+test_expect_success 'Command strategy respects name length limit' '
+    test_config stgit.namestrategy command &&
+    test_config stgit.namelength 12 &&
+    test_config stgit.namecmd "echo long-name-from-command" &&
+    stg new -m "limited" &&
+    test "$(stg top)" = "long-name" &&
+    stg delete --top
+'
+
+test_expect_success 'Command strategy errors' '
+    test_config stgit.namestrategy command &&
+    command_error stg new -m "no command" 2>err &&
+    grep "\`stgit.namecmd\` must be set" err &&
+    test_config stgit.namecmd "exit 3" &&
+    command_error stg new -m "failing command" 2>err &&
+    grep "\`exit 3\` exited with code 3" err &&
+    test_config stgit.namecmd "cat >/dev/null" &&
+    command_error stg new -m "silent command" 2>err &&
+    grep "did not output a patch name" err
+'
+
+test_expect_success 'Explicit patch names are not affected' '
+    test_config stgit.namestrategy date &&
+    stg new -m "Explicit name" explicit &&
+    test "$(stg top)" = "explicit"
+'
+
+test_expect_success 'Uncommit uses strategy' '
+    stg commit --all --allow-empty &&
+    test_config stgit.namestrategy command &&
+    test_config stgit.namecmd "head -n 1 | sed -e \"s/^/un-/\"" &&
+    stg uncommit -n 2 &&
+    stg series --noprefix >series.txt &&
+    test_write_lines un-from-command un-Explicit-name >expected.txt &&
+    test_cmp expected.txt series.txt
+'
+
+test_expect_success 'Pick uses strategy' '
+    git checkout -b side master~1 &&
+    echo "side" >side.txt &&
+    git add side.txt &&
+    git commit -m "Side change
+
+Ticket: SIDE-5" &&
+    git checkout master &&
+    test_config stgit.namestrategy ticket &&
+    stg pick side &&
+    test "$(stg top)" = "SIDE-5-Side-change"
+'
+
+test_expect_success 'Import uses strategy' '
+    git format-patch --stdout -1 side >side.patch &&
+    stg delete SIDE-5-Side-change &&
+    test_config stgit.namestrategy ticket &&
+    stg import --mail side.patch &&
+    test "$(stg top)" = "SIDE-5-side-change"
+'
+
+test_done
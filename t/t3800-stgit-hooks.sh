@@ -0,0 +1,149 @@
+#!/bin/sh
+
+test_description='Test StGit-specific hooks'
+
+. ./test-lib.sh
+
+HOOKDIR="$(git rev-parse --git-dir)/hooks"
+HOOK_LOG="$TRASH_DIRECTORY/hook-log"
+
+test_expect_success 'Initialize stack' '
+    mkdir -p "$HOOKDIR" &&
+    cat >>.git/info/exclude <<-\EOF &&
+	expected*.txt
+	out*.txt
+	err
+	hook-log
+	EOF
+    test_commit_bulk --message="p%s" 3 &&
+    stg uncommit -n 3 &&
+    stg pop -a
+'
+
+test_expect_success 'Pre-push hook is given patch and parent' '
+    write_script "$HOOKDIR/stg-pre-push" <<-EOF &&
+	echo "\$@" >>"$HOOK_LOG"
+	EOF
+    stg push p1 &&
+    echo "p1 $(stg id p1) $(stg id {base})" >expected.txt &&
+    test_cmp expected.txt "$HOOK_LOG" &&
+    rm "$HOOK_LOG"
+'
+
+test_expect_success 'Pre-push hook vetoes push' '
+    write_script "$HOOKDIR/stg-pre-push" <<-\EOF &&
+	test "$1" != p3
+	EOF
+    command_error stg push -a 2>err &&
+    grep "\`stg-pre-push\` hook returned 1; patch \`p3\` not pushed" err &&
+    stg series --applied --noprefix >out.txt &&
+    echo p1 >expected.txt &&
+    test_cmp expected.txt out.txt &&
+    stg push p2 &&
+    rm "$HOOKDIR/stg-pre-push"
+'
+
+test_expect_success 'Post-transaction hook is given state ids and message' '
+    write_script "$HOOKDIR/stg-post-transaction" <<-EOF &&
+	echo "\$@" >>"$HOOK_LOG"
+	EOF
+    old_state=$(git rev-parse refs/stacks/master) &&
+    stg push p3 &&
+    new_state=$(git rev-parse refs/stacks/master) &&
+    echo "$old_state $new_state push" >expected.txt &&
+    test_cmp expected.txt "$HOOK_LOG" &&
+    rm "$HOOK_LOG"
+'
+
+test_expect_success 'Post-transaction hook exit status is ignored' '
+    write_script "$HOOKDIR/stg-post-transaction" <<-\EOF &&
+	exit 1
+	EOF
+    stg pop &&
+    test "$(stg top)" = "p2" &&
+    rm "$HOOKDIR/stg-post-transaction"
+'
+
+test_expect_success 'Post-refresh hook is given patch and new commit' '
+    write_script "$HOOKDIR/stg-post-refresh" <<-EOF &&
+	echo "\$@" >>"$HOOK_LOG"
+	exit 1
+	EOF
+    echo change >>p2.t &&
+    stg refresh &&
+    echo "p2 $(stg id p2)" >expected.txt &&
+    test_cmp expected.txt "$HOOK_LOG" &&
+    rm "$HOOK_LOG"
+'
+
+test_expect_success 'Post-refresh hook for renamed patch' '
+    stg rename p2 renamed &&
+    echo change >>p2.t &&
+    stg refresh &&
+    echo "renamed $(stg id renamed)" >expected.txt &&
+    tail -n 1 "$HOOK_LOG" >out.txt &&
+    test_cmp expected.txt out.txt &&
+    rm "$HOOK_LOG"
+'
+
+test_expect_success 'Post-refresh hook for applied patch below top' '
+    echo change >>1.t &&
+    stg refresh -p p1 &&
+    test "$(stg top)" = "renamed" &&
+    echo "p1 $(stg id p1)" >expected.txt &&
+    test_cmp expected.txt "$HOOK_LOG" &&
+    rm "$HOOK_LOG"
+'
+
+test_expect_success 'Post-refresh hook for unapplied patch' '
+    echo change >>2.t &&
+    stg refresh -p p3 &&
+    test "$(stg top)" = "renamed" &&
+    echo "p3 $(stg id p3)" >expected.txt &&
+    test_cmp expected.txt "$HOOK_LOG" &&
+    rm "$HOOK_LOG"
+'
+
+test_expect_success 'Post-refresh hook not run when changes do not apply' '
+    echo other >>2.t &&
+    stg refresh -p p3 >out.txt &&
+    grep "The new changes did not apply cleanly to p3" out.txt &&
+    test_path_is_missing "$HOOK_LOG" &&
+    stg delete refresh-temp &&
+    rm "$HOOKDIR/stg-post-refresh"
+'
+
+test_expect_success 'Failure to run post hooks only warns' '
+    printf "#!/nonexistent/interpreter\n" >"$HOOKDIR/stg-post-refresh" &&
+    chmod +x "$HOOKDIR/stg-post-refresh" &&
+    cp "$HOOKDIR/stg-post-refresh" "$HOOKDIR/stg-post-transaction" &&
+    echo change >>1.t &&
+    stg refresh 2>err &&
+    grep "warning: \`stg-post-refresh\` hook" err &&
+    grep "warning: \`stg-post-transaction\` hook" err &&
+    test "$(stg top)" = "renamed" &&
+    git diff --quiet &&
+    rm "$HOOKDIR/stg-post-refresh" "$HOOKDIR/stg-post-transaction"
+'
+
+test_expect_success 'Pre-email hook vetoes sending email' '
+    write_script "$HOOKDIR/stg-pre-email" <<-EOF &&
+	echo "\$@" >>"$HOOK_LOG"
+	exit 1
+	EOF
+    command_error stg email send --dry-run --to someone@example.com p1 2>err &&
+    grep "\`stg-pre-email\` hook returned 1" err &&
+    echo "$(stg id {base})..$(stg id p1)" >expected.txt &&
+    test_cmp expected.txt "$HOOK_LOG" &&
+    rm "$HOOK_LOG"
+'
+
+test_expect_success GITSENDEMAIL 'Pre-email hook allows sending email' '
+    write_script "$HOOKDIR/stg-pre-email" <<-\EOF &&
+	exit 0
+	EOF
+    stg email send --dry-run --to someone@example.com p1 >out.txt &&
+    grep "Subject: \[PATCH\] p1" out.txt
+'
+
+test_done
@@ -0,0 +1,98 @@
+#!/bin/sh
+
+test_description='Test locking of stacks against concurrent modification'
+
+. ./test-lib.sh
+
+LOCK=.git/stgit/locks/master.lock
+
+test_expect_success 'Initialize stack' '
+    cat >>.git/info/exclude <<-\EOF &&
+	concurrent-new
+	err
+	EOF
+    test_commit_bulk 1 &&
+    stg init &&
+    stg new -m p0 &&
+    stg new -m p1
+'
+
+test_expect_success 'Lock is released after transaction' '
+    stg pop &&
+    test_path_is_missing $LOCK
+'
+
+test_expect_success 'Modifying a busy stack fails' '
+    mkdir -p .git/stgit/locks &&
+    touch $LOCK &&
+    command_error stg push 2>err &&
+    grep -e "stack .master. is busy" err &&
+    grep -e "remove .*$LOCK" err &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0" &&
+    test_path_is_file $LOCK
+'
+
+test_expect_success 'Clearing the log of a busy stack fails' '
+    command_error stg log --clear 2>err &&
+    grep -e "stack .master. is busy" err &&
+    stg log >log.txt &&
+    test_line_count = 4 log.txt
+'
+
+test_expect_success 'Reading a busy stack does not wait by default' '
+    test "$(echo $(stg series --noprefix))" = "p0 p1"
+'
+
+test_expect_success 'Wait for busy stack with timeout' '
+    command_error stg --wait=1 push 2>err &&
+    grep -e "stack .master. is busy" err &&
+    command_error stg --wait=1 series 2>err &&
+    grep -e "stack .master. is busy" err &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0"
+'
+
+test_expect_success 'Wait for busy stack to become available' '
+    sh -c "(sleep 1 && rm $LOCK) >/dev/null 2>&1 &" &&
+    stg --wait push &&
+    test "$(echo $(stg series --noprefix --applied))" = "p0 p1" &&
+    test_path_is_missing $LOCK
+'
+
+test_expect_success 'Lock is per branch' '
+    stg branch --create feature/x &&
+    mkdir -p .git/stgit/locks/feature &&
+    touch .git/stgit/locks/feature/x.lock &&
+    command_error stg new -m x0 2>err &&
+    grep -e "stack .feature/x. is busy" err &&
+    stg rename -b master p1 p2 &&
+    rm .git/stgit/locks/feature/x.lock &&
+    stg new -m x0 &&
+    stg branch master &&
+    test "$(echo $(stg series --noprefix))" = "p0 p2"
+'
+
+test_expect_success 'Stack modified while a command runs is not overwritten' '
+    write_script concurrent-new <<-\EOF &&
+	stg new -m concurrent &&
+	echo outer >"$1"
+	EOF
+    GIT_EDITOR=./concurrent-new command_error stg new 2>err &&
+    grep -e "stack .master. was modified by another process" err &&
+    test "$(echo $(stg series --noprefix))" = "p0 p2 concurrent"
+'
+
+test_expect_success 'Post-transaction hook may modify the stack' '
+    mkdir -p .git/hooks &&
+    write_script .git/hooks/stg-post-transaction <<-\EOF &&
+	if test "$3" = "new: p3"
+	then
+	    stg edit -m "edited by hook" concurrent
+	fi
+	EOF
+    stg new -m p3 &&
+    rm .git/hooks/stg-post-transaction &&
+    test "$(git log -1 --format=%s $(stg id concurrent))" = "edited by hook" &&
+    test "$(echo $(stg series --noprefix))" = "p0 p2 concurrent p3"
+'
+
+test_done
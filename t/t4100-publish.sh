@@ -0,0 +1,140 @@
+#!/bin/sh
+
+test_description='Test publishing a stack to a non-rewinding branch'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt publish on uninitialized branch' '
+    command_error stg publish 2>err &&
+    grep "error: StGit stack not initialized for branch \`master\`" err
+'
+
+test_expect_success 'Initialize repo with patches' '
+    test_commit_bulk 2 &&
+    git branch upstream &&
+    stg init &&
+    for i in 1 2 3
+    do
+        echo "line $i" >>foo.txt &&
+        git add foo.txt &&
+        stg new -m "patch-$i" &&
+        stg refresh || return 1
+    done
+'
+
+test_expect_success 'Attempt publish to the current branch' '
+    command_error stg publish master 2>err &&
+    grep "error: cannot publish to the current branch" err
+'
+
+test_expect_success 'All patches are unpublished' '
+    stg publish --unpublished >actual &&
+    test_write_lines patch-1 patch-2 patch-3 >expected &&
+    test_cmp expected actual
+'
+
+test_expect_success 'Publish creates branch at top' '
+    stg publish &&
+    test "$(git rev-parse master.public)" = "$(stg id patch-3)" &&
+    git show-ref --verify refs/stacks/master.published/master.public &&
+    stg publish --unpublished >actual &&
+    test_must_be_empty actual
+'
+
+test_expect_success 'Refuse to publish unchanged stack' '
+    command_error stg publish 2>err &&
+    grep "error: nothing to publish; \`master.public\` is up to date with the stack" err
+'
+
+test_expect_success 'Publish new patches as individual commits' '
+    for i in 4 5
+    do
+        echo "line $i" >>foo.txt &&
+        git add foo.txt &&
+        stg new -m "patch-$i" &&
+        stg refresh || return 1
+    done &&
+    stg publish --unpublished >actual &&
+    test_write_lines patch-4 patch-5 >expected &&
+    test_cmp expected actual &&
+    old_public=$(git rev-parse master.public) &&
+    stg publish &&
+    git rev-list $old_public..master.public >commits &&
+    test_line_count = 2 commits &&
+    git log -2 --format=%s master.public >actual &&
+    test_write_lines patch-5 patch-4 >expected &&
+    test_cmp expected actual &&
+    test "$(git rev-parse master.public^{tree})" = "$(git rev-parse master^{tree})"
+'
+
+test_expect_success 'Publish modified patch as update commit' '
+    stg goto patch-2 &&
+    echo "more" >bar.txt &&
+    git add bar.txt &&
+    stg refresh &&
+    stg goto patch-5 &&
+    old_public=$(git rev-parse master.public) &&
+    stg publish -m "Rework patch-2" &&
+    test "$(git rev-parse master.public^)" = "$old_public" &&
+    git log -1 --format=%s master.public >actual &&
+    echo "Rework patch-2" >expected &&
+    test_cmp expected actual &&
+    test "$(git rev-parse master.public^{tree})" = "$(git rev-parse master^{tree})"
+'
+
+test_expect_success 'Publish rebased stack with merge commit' '
+    git checkout upstream &&
+    echo "upstream" >upstream.txt &&
+    git add upstream.txt &&
+    git commit -m "upstream change" &&
+    git checkout master &&
+    stg rebase upstream &&
+    old_public=$(git rev-parse master.public) &&
+    stg publish &&
+    git merge-base --is-ancestor $old_public master.public &&
+    git merge-base --is-ancestor upstream master.public &&
+    test "$(git rev-parse master.public^{tree})" = "$(git rev-parse master^{tree})"
+'
+
+test_expect_success 'Publish popped patches' '
+    stg pop &&
+    stg publish &&
+    test "$(git rev-parse master.public^{tree})" = "$(git rev-parse master^{tree})" &&
+    command_error stg publish
+'
+
+test_expect_success 'Publish to named branch' '
+    stg publish other-public &&
+    test "$(git rev-parse other-public)" = "$(stg id patch-4)" &&
+    git show-ref --verify refs/stacks/master.published/other-public
+'
+
+test_expect_success 'Overwrite published branch' '
+    stg new -m "patch-6" &&
+    stg publish --overwrite &&
+    test "$(git rev-parse master.public)" = "$(stg id patch-6)"
+'
+
+test_expect_success 'Attempt publish to branch checked out in worktree' '
+    stg new -m "patch-7" &&
+    git worktree add publish-worktree master.public &&
+    test_when_finished "git worktree remove --force publish-worktree" &&
+    command_error stg publish 2>err &&
+    grep "error: branch \`master.public\` is checked out in worktree" err
+'
+
+test_expect_success 'Renaming branch moves published state' '
+    stg branch --rename renamed &&
+    test_must_fail git rev-parse --verify -q refs/stacks/master.published/other-public &&
+    git rev-parse --verify -q refs/stacks/renamed.published/other-public &&
+    git rev-parse --verify -q refs/stacks/renamed.published/master.public
+'
+
+test_expect_success 'Deleting branch deletes published state' '
+    stg branch --create another &&
+    stg branch --delete --force renamed &&
+    test_must_fail git rev-parse --verify -q refs/stacks/renamed.published/other-public &&
+    test_must_fail git rev-parse --verify -q refs/stacks/renamed.published/master.public
+'
+
+test_done
@@ -0,0 +1,120 @@
+#!/bin/sh
+
+test_description='Test stack archive export and import'
+
+. ./test-lib.sh
+
+test_expect_success 'Attempt archive export on uninitialized branch' '
+    command_error stg export --stack-archive stack.bundle 2>err &&
+    grep "error: StGit stack not initialized for branch \`master\`" err &&
+    test_path_is_missing stack.bundle
+'
+
+test_expect_success 'Initialize repo with patches' '
+    cat >>.git/info/exclude <<-\EOF &&
+	*.bundle
+	expected*
+	actual*
+	err
+	EOF
+    test_commit_bulk 2 &&
+    stg init &&
+    for i in 1 2 3 4 5
+    do
+        echo "line $i" >>foo.txt &&
+        git add foo.txt &&
+        stg new -m "patch-$i" --author "Some Author <some@example.com>" &&
+        stg refresh || return 1
+    done &&
+    stg pop patch-4 patch-5 &&
+    stg hide patch-5 &&
+    stg series --all >expected-series &&
+    for p in patch-1 patch-2 patch-3 patch-4 patch-5
+    do
+        stg id $p || return 1
+    done >expected-ids
+'
+
+test_expect_success 'Export stack archive' '
+    stg export --stack-archive stack.bundle &&
+    git bundle list-heads stack.bundle >actual &&
+    test_line_count = 1 actual &&
+    grep " refs/stacks/master$" actual
+'
+
+test_expect_success 'Import stack archive into repository with base' '
+    base=$(stg id "{base}") &&
+    git init other &&
+    (
+        cd other &&
+        git fetch -q .. "$base:refs/heads/main" &&
+        git checkout -q main &&
+        stg import --stack-archive ../stack.bundle &&
+        test "$(git symbolic-ref --short HEAD)" = master &&
+        stg series --all >../actual-series &&
+        for p in patch-1 patch-2 patch-3 patch-4 patch-5
+        do
+            stg id $p || return 1
+        done >../actual-ids &&
+        test "$(stg id "{base}")" = "$base" &&
+        test "$(git rev-parse HEAD)" = "$(stg id patch-3)" &&
+        git log -1 --format=%an $(stg id patch-5) >../actual-author &&
+        stg log -n 1 >../actual-log &&
+        git rev-parse refs/patches/master/patch-5 &&
+        stg undo &&
+        stg series --all >../actual-undo
+    ) &&
+    test_cmp expected-series actual-series &&
+    test_cmp expected-ids actual-ids &&
+    echo "Some Author" >expected-author &&
+    test_cmp expected-author actual-author &&
+    grep "hide" actual-log &&
+    grep "^- patch-5" actual-undo
+'
+
+test_expect_success 'Import stack archive to named branch' '
+    (
+        cd other &&
+        git checkout -q main &&
+        stg import --stack-archive --branch-name archived ../stack.bundle &&
+        test "$(git symbolic-ref --short HEAD)" = archived &&
+        stg series --all >../actual-series
+    ) &&
+    test_cmp expected-series actual-series
+'
+
+test_expect_success 'Import stack archive to existing branch' '
+    (
+        cd other &&
+        command_error stg import --stack-archive ../stack.bundle 2>../err
+    ) &&
+    grep "error: branch \`master\` already exists" err
+'
+
+test_expect_success 'Import stack archive without base commit' '
+    git init without-base &&
+    (
+        cd without-base &&
+        git commit --allow-empty -m unrelated &&
+        command_error stg import --stack-archive --branch-name imported ../stack.bundle 2>../err &&
+        test_must_fail git rev-parse --verify -q refs/heads/imported &&
+        test_must_fail git rev-parse --verify -q refs/stacks/imported
+    ) &&
+    grep "error: reading stack archive" err
+'
+
+test_expect_success 'Import bundle that is not a stack archive' '
+    git bundle create plain.bundle master &&
+    command_error stg import --stack-archive plain.bundle 2>err &&
+    grep "is not a stack archive" err
+'
+
+test_expect_success 'Invalid option combinations' '
+    general_error stg export --stack-archive x.bundle --stdout &&
+    general_error stg export --stack-archive x.bundle patch-1 &&
+    general_error stg import --stack-archive &&
+    general_error stg import --stack-archive -M stack.bundle &&
+    general_error stg import --branch-name foo stack.bundle
+'
+
+test_done
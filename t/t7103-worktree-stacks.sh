@@ -0,0 +1,169 @@
+#!/bin/sh
+
+test_description='Test stacks checked out in multiple worktrees'
+
+. ./test-lib.sh
+
+test_expect_success 'Setup repository' '
+    git init main &&
+    (
+        cd main &&
+        echo "actual" >>.git/info/exclude &&
+        echo "expected" >>.git/info/exclude &&
+        echo "err" >>.git/info/exclude &&
+        test_commit_bulk 3 &&
+        stg init &&
+        echo "main change" >>1.t &&
+        stg new -rm m1 &&
+        git worktree add ../linked &&
+        cd ../linked &&
+        stg init &&
+        echo "linked change" >>2.t &&
+        stg new -rm l1
+    )
+'
+
+test_expect_success 'Stacks in worktrees do not affect each other' '
+    (
+        cd main &&
+        echo "dirty" >>3.t &&
+        git add 3.t &&
+        echo "more dirt" >>3.t
+    ) &&
+    (
+        cd linked &&
+        echo "another linked change" >>3.t &&
+        stg new -rm l2 &&
+        stg pop -a &&
+        stg push -a &&
+        stg series >actual &&
+        cat >expected <<-\EOF &&
+	+ l1
+	> l2
+	EOF
+        test_cmp expected actual &&
+        git status --porcelain >actual &&
+        test_must_be_empty actual
+    ) &&
+    (
+        cd main &&
+        git status --porcelain --untracked-files=no >actual &&
+        echo "MM 3.t" >expected &&
+        test_cmp expected actual &&
+        stg series >actual &&
+        echo "> m1" >expected &&
+        test_cmp expected actual &&
+        git checkout 3.t &&
+        git reset --hard
+    )
+'
+
+test_expect_success 'Disallow modifying stack checked out in other worktree' '
+    (
+        cd main &&
+        command_error stg delete -b linked l2 2>err &&
+        grep "error: branch \`linked\` is checked out in worktree \`.*linked\`" err &&
+        command_error stg hide -b linked l2 2>err &&
+        grep "is checked out in worktree" err &&
+        command_error stg rename -b linked l2 l3 2>err &&
+        grep "is checked out in worktree" err &&
+        stg series -b linked >actual &&
+        cat >expected <<-\EOF &&
+	+ l1
+	> l2
+	EOF
+        test_cmp expected actual
+    ) &&
+    (
+        cd linked &&
+        command_error stg delete -b master m1 2>err &&
+        grep "error: branch \`master\` is checked out in worktree \`.*main\`" err &&
+        stg series -b master >actual &&
+        echo "> m1" >expected &&
+        test_cmp expected actual
+    )
+'
+
+test_expect_success 'Allow modifying stack of branch in current worktree' '
+    (
+        cd linked &&
+        stg rename l2 l3 &&
+        stg series >actual &&
+        cat >expected <<-\EOF &&
+	+ l1
+	> l3
+	EOF
+        test_cmp expected actual
+    )
+'
+
+test_expect_success 'Disallow deleting branch checked out in other worktree' '
+    (
+        cd main &&
+        command_error stg branch --delete --force linked 2>err &&
+        grep "cannot delete branch \`linked\` checked out in worktree" err &&
+        git show-ref --verify refs/heads/linked &&
+        git show-ref --verify refs/stacks/linked
+    )
+'
+
+test_expect_success 'Create branch in new worktree' '
+    (
+        cd main &&
+        stg branch --create --worktree ../feature feature &&
+        test "$(stg branch)" = "master" &&
+        git show-ref --verify refs/stacks/feature
+    ) &&
+    (
+        cd feature &&
+        test "$(stg branch)" = "feature" &&
+        test "$(git rev-parse HEAD)" = "$(git -C ../main rev-parse master)" &&
+        echo "feature change" >>1.t &&
+        stg new -rm f1 &&
+        stg series >actual &&
+        echo "> f1" >expected &&
+        test_cmp expected actual
+    ) &&
+    (
+        cd main &&
+        stg series -b feature >actual &&
+        echo "> f1" >expected &&
+        test_cmp expected actual &&
+        command_error stg delete -b feature f1 2>err &&
+        grep "is checked out in worktree" err
+    )
+'
+
+test_expect_success 'Create branch in new worktree from committish' '
+    (
+        cd main &&
+        echo "dirty" >>1.t &&
+        stg branch --create --worktree ../based based master~1 &&
+        git checkout 1.t &&
+        test "$(stg branch)" = "master" &&
+        test "$(git -C ../based rev-parse HEAD)" = "$(git rev-parse master~1)" &&
+        test "$(stg branch --list | grep -c based)" = "1"
+    )
+'
+
+test_expect_success 'Create branch in existing path fails' '
+    (
+        cd main &&
+        command_error stg branch --create --worktree ../linked other 2>err &&
+        grep "already exists" err &&
+        test_must_fail git show-ref --verify refs/heads/other &&
+        test_must_fail git show-ref --verify refs/stacks/other
+    )
+'
+
+test_expect_success 'Modify stack after its worktree is removed' '
+    (
+        cd main &&
+        git worktree remove ../feature &&
+        stg delete -b feature f1 &&
+        stg series -b feature >actual &&
+        test_must_be_empty actual
+    )
+'
+
+test_done